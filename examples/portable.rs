@@ -0,0 +1,140 @@
+//! Runs jobs in child processes where the target supports it, and in the current process
+//! elsewhere.
+//!
+//! On targets without `fork`, every entry point of this crate fails with
+//! [`ErrorKind::Unsupported`], so that such a fallback can be written once for all targets.
+
+use std::io::{ErrorKind, Result};
+use std::net::TcpListener;
+use std::time::Duration;
+
+use safe_fork::{
+    ForkBuilder, ForkChannel, ForkError, ForkPool, Pid, PreforkServer, Reaper, Restart,
+    RestartPolicy, RetryPolicy, Rlimits, Stdio, Supervisor,
+};
+
+fn is_unsupported(err: &std::io::Error) -> bool {
+    err.kind() == ErrorKind::Unsupported
+}
+
+fn is_fork_unsupported(err: &ForkError) -> bool {
+    matches!(err, ForkError::Io(err) if is_unsupported(err))
+}
+
+/// Computes `f(x)` in a child process, or in the current process if it cannot fork.
+fn compute(x: u64, f: fn(u64) -> u64) -> Result<u64> {
+    match safe_fork::fork_join_value(|| f(x)) {
+        Err(err) if is_unsupported(&err) => Ok(f(x)),
+        result => result,
+    }
+}
+
+fn main() -> Result<()> {
+    println!("running as {}", Pid::current());
+    let square = |x| x * x;
+
+    println!("square: {}", compute(12, square)?);
+
+    let parsed =
+        match safe_fork::fork_join_result(|| "42".parse::<u32>().map_err(|e| e.to_string())) {
+            Err(err) if is_fork_unsupported(&err) => "42".parse::<u32>().map_err(|e| e.to_string()),
+            result => result?,
+        };
+    println!("parsed: {parsed:?}");
+
+    let squares = match safe_fork::fork_map(0..4u64, square) {
+        Err(err) if is_unsupported(&err) => (0..4).map(square).collect(),
+        result => result?,
+    };
+    println!("squares: {squares:?}");
+
+    match ForkPool::new(2, |x: u64| x + 1) {
+        Ok(pool) => println!("pool: {:?}", pool.map([1, 2, 3])?),
+        Err(err) if is_unsupported(&err) => println!("pool: unsupported"),
+        Err(err) => return Err(err),
+    }
+
+    match safe_fork::fork_service(|x: u64| x * 2) {
+        Ok(mut service) => {
+            println!("service: {}", service.call(21)?);
+            service.shutdown()?;
+        }
+        Err(err) if is_unsupported(&err) => println!("service: unsupported"),
+        Err(err) => return Err(err),
+    }
+
+    match safe_fork::fork_with_channel::<u64>() {
+        Ok(ForkChannel::Parent(child, _tx, rx)) => {
+            println!("channel: {}", rx.recv()?);
+            child.join()?;
+        }
+        Ok(ForkChannel::Child(tx, _rx)) => {
+            tx.send(&7)?;
+            std::process::exit(0);
+        }
+        Err(err) if is_unsupported(&err) => println!("channel: unsupported"),
+        Err(err) => return Err(err),
+    }
+
+    let child = ForkBuilder::new()
+        .stdout(Stdio::Null)
+        .rlimits(Rlimits::new().cpu(1))
+        .spawn(|| println!("hidden"));
+    match child {
+        Ok(child) => println!("builder: {}", child.join()?),
+        Err(err) if is_fork_unsupported(&err) => println!("builder: unsupported"),
+        Err(err) => return Err(err.into()),
+    }
+
+    match safe_fork::run_isolated(|| 3) {
+        Ok(outcome) => println!("isolated: {outcome:?}"),
+        Err(err) if is_fork_unsupported(&err) => println!("isolated: unsupported"),
+        Err(err) => return Err(err.into()),
+    }
+
+    match safe_fork::fork_retry(&RetryPolicy::new(3), || 0) {
+        Ok(status) => println!("retry: {status}"),
+        Err(err) if is_fork_unsupported(&err) => println!("retry: unsupported"),
+        Err(err) => return Err(err.into()),
+    }
+
+    safe_fork::fork_scope(|scope| -> Result<()> {
+        match scope.spawn(|| ()) {
+            Ok(child) => println!("scope: {}", child.join()?),
+            Err(err) if is_fork_unsupported(&err) => println!("scope: unsupported"),
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    })?;
+
+    let mut supervisor = Supervisor::new();
+    match supervisor.spawn(RestartPolicy::new(Restart::Never), || 0) {
+        Ok(()) => println!("supervisor: {:?}", supervisor.run()?),
+        Err(err) if is_fork_unsupported(&err) => println!("supervisor: unsupported"),
+        Err(err) => return Err(err.into()),
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    match PreforkServer::new(listener, 1).spawn(drop) {
+        Ok(mut supervisor) => {
+            supervisor.shutdown()?;
+            println!("prefork: stopped");
+        }
+        Err(err) if is_fork_unsupported(&err) => println!("prefork: unsupported"),
+        Err(err) => return Err(err.into()),
+    }
+
+    match Reaper::install() {
+        Ok(reaper) => println!("reaper: {:?}", reaper.wait(Some(Duration::ZERO))?),
+        Err(err) if is_unsupported(&err) => println!("reaper: unsupported"),
+        Err(err) => return Err(err),
+    }
+
+    match safe_fork::daemonize(|| ()) {
+        Ok(pid) => println!("daemon: {pid}"),
+        Err(err) if is_unsupported(&err) => println!("daemon: unsupported"),
+        Err(err) => return Err(err),
+    }
+
+    Ok(())
+}
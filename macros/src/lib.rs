@@ -0,0 +1,50 @@
+//! Procedural macros for `safe-fork`. Use them through the `macros` feature of `safe-fork`.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, ItemFn};
+
+/// Turns the function into a test that runs in a separate process.
+///
+/// See the documentation of `safe_fork::forked_test`.
+#[proc_macro_attribute]
+pub fn forked_test(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let attr = proc_macro2::TokenStream::from(attr);
+        return syn::Error::new_spanned(attr, "`forked_test` does not take arguments")
+            .into_compile_error()
+            .into();
+    }
+
+    let mut item = parse_macro_input!(item as ItemFn);
+    let sig = &item.sig;
+    let error = if sig.asyncness.is_some() {
+        Some("async tests are not supported")
+    } else if !sig.inputs.is_empty() || !sig.generics.params.is_empty() {
+        Some("test functions cannot take arguments or generic parameters")
+    } else {
+        None
+    };
+    if let Some(error) = error {
+        return syn::Error::new_spanned(&item.sig, error)
+            .into_compile_error()
+            .into();
+    }
+
+    let attrs = std::mem::take(&mut item.attrs);
+    let vis = std::mem::replace(&mut item.vis, syn::Visibility::Inherited);
+    let name = std::mem::replace(&mut item.sig.ident, format_ident!("__forked_test_body"));
+    quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #name() {
+            #item
+            ::safe_fork::__private::run_forked_test(
+                module_path!(),
+                stringify!(#name),
+                __forked_test_body,
+            );
+        }
+    }
+    .into()
+}
@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+type Hook = Box<dyn Fn() + Send + Sync>;
+
+struct Hooks {
+    id: u64,
+    prepare: Hook,
+    parent: Hook,
+    child: Hook,
+}
+
+/// Registered hooks, in registration order.
+static HOOKS: Mutex<Vec<Arc<Hooks>>> = Mutex::new(Vec::new());
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Registers hooks to run around each fork performed by this crate.
+///
+/// `prepare` runs in the parent right before forking, `parent` runs in the parent right after
+/// forking, and `child` runs in the child right after forking, before any setup configured with
+/// [`ForkBuilder`](crate::ForkBuilder). `parent` also runs if forking fails, so it can undo what
+/// `prepare` did. As with `pthread_atfork`, `prepare` hooks run in reverse registration order,
+/// while `parent` and `child` hooks run in registration order.
+///
+/// Unlike `pthread_atfork`, the hooks only run for forks performed by this crate, and not for
+/// [`fork_exec`](crate::fork_exec), which does not run any Rust code in the child. The hooks are
+/// kept registered until [`AtForkHandle::unregister`] is called.
+///
+/// # Example
+///
+/// ```
+/// safe_fork::register_atfork(|| (), || (), || println!("forked"));
+/// ```
+pub fn register_atfork(
+    prepare: impl Fn() + Send + Sync + 'static,
+    parent: impl Fn() + Send + Sync + 'static,
+    child: impl Fn() + Send + Sync + 'static,
+) -> AtForkHandle {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    HOOKS.lock().unwrap().push(Arc::new(Hooks {
+        id,
+        prepare: Box::new(prepare),
+        parent: Box::new(parent),
+        child: Box::new(child),
+    }));
+    AtForkHandle { id }
+}
+
+/// Handle to hooks registered with [`register_atfork`].
+///
+/// Dropping the handle leaves the hooks registered.
+#[derive(Debug)]
+pub struct AtForkHandle {
+    id: u64,
+}
+
+impl AtForkHandle {
+    /// Unregisters the hooks, so they no longer run for subsequent forks.
+    pub fn unregister(self) {
+        HOOKS.lock().unwrap().retain(|hooks| hooks.id != self.id);
+    }
+}
+
+/// Hooks captured for a fork in progress, so that registrations made by the hooks themselves do
+/// not affect it.
+pub(crate) struct Pending(Vec<Arc<Hooks>>);
+
+/// Run the `prepare` hooks.
+pub(crate) fn prepare() -> Pending {
+    // The lock is not held while running the hooks, so that they may register other hooks.
+    let hooks = HOOKS.lock().unwrap().clone();
+    for hooks in hooks.iter().rev() {
+        (hooks.prepare)();
+    }
+    Pending(hooks)
+}
+
+impl Pending {
+    /// Run the `parent` hooks.
+    pub(crate) fn parent(self) {
+        for hooks in &self.0 {
+            (hooks.parent)();
+        }
+    }
+
+    /// Run the `child` hooks.
+    pub(crate) fn child(self) {
+        for hooks in &self.0 {
+            (hooks.child)();
+        }
+    }
+}
@@ -0,0 +1,1820 @@
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+use crate::caps::CapabilityDrop;
+use crate::child::Tree;
+use crate::exec::{CStringArray, SpawnConfig};
+#[cfg(target_os = "linux")]
+use crate::idmap::IdMaps;
+#[cfg(all(target_os = "linux", feature = "landlock"))]
+use crate::landlock::Ruleset;
+#[cfg(target_os = "linux")]
+use crate::mount::Mounts;
+use crate::scrub::ScrubHooks;
+#[cfg(all(target_os = "linux", feature = "seccomp"))]
+use crate::SeccompFilter;
+use crate::Termination;
+#[cfg(target_os = "linux")]
+use crate::{Capability, CloneFlags, IdMap, IoPriorityClass, SchedPolicy, TimeOffsets};
+use crate::{Child, ChildStderr, ChildStdin, ChildStdout, ForkError, Resource, Rlimits, SigSet};
+
+/// Describes what to do with a standard I/O stream of the child process.
+#[derive(Debug, Default)]
+pub enum Stdio {
+    /// The child inherits the stream from the parent.
+    #[default]
+    Inherit,
+    /// The stream is redirected to `/dev/null`.
+    Null,
+    /// A pipe is created to connect the parent and the child.
+    ///
+    /// The parent end is available as [`Child::stdin`], [`Child::stdout`] or [`Child::stderr`].
+    Piped,
+    /// The stream is redirected to the given file descriptor, e.g. an open file.
+    ///
+    /// The child receives a duplicate of the file descriptor, so it stays open in the parent until
+    /// the builder is dropped.
+    Fd(OwnedFd),
+}
+
+impl From<OwnedFd> for Stdio {
+    fn from(fd: OwnedFd) -> Self {
+        Stdio::Fd(fd)
+    }
+}
+
+impl From<File> for Stdio {
+    fn from(file: File) -> Self {
+        Stdio::Fd(file.into())
+    }
+}
+
+impl From<ChildStdin> for Stdio {
+    fn from(stdin: ChildStdin) -> Self {
+        Stdio::Fd(stdin.into())
+    }
+}
+
+impl From<ChildStdout> for Stdio {
+    fn from(stdout: ChildStdout) -> Self {
+        Stdio::Fd(stdout.into())
+    }
+}
+
+impl From<ChildStderr> for Stdio {
+    fn from(stderr: ChildStderr) -> Self {
+        Stdio::Fd(stderr.into())
+    }
+}
+
+impl Stdio {
+    /// Prepare file descriptors before forking.
+    ///
+    /// Returns the file descriptor to be installed in the child, and the parent end of the pipe if
+    /// the stream is piped.
+    fn prepare(&self, readable: bool) -> Result<(Option<OwnedFd>, Option<File>)> {
+        match self {
+            Stdio::Inherit => Ok((None, None)),
+            Stdio::Null => {
+                let null = File::options()
+                    .read(readable)
+                    .write(!readable)
+                    .open("/dev/null")?;
+                Ok((Some(null.into()), None))
+            }
+            Stdio::Piped => {
+                let (reader, writer) = crate::pipe()?;
+                if readable {
+                    Ok((Some(reader.into()), Some(writer)))
+                } else {
+                    Ok((Some(writer.into()), Some(reader)))
+                }
+            }
+            Stdio::Fd(fd) => Ok((Some(fd.try_clone()?), None)),
+        }
+    }
+}
+
+/// How [`ForkBuilder::exec`] creates the child process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpawnBackend {
+    /// Fork, and fall back to `posix_spawn` if forking fails with `ENOMEM` or `EAGAIN` and the
+    /// configuration can be applied by `posix_spawn`.
+    ///
+    /// Forking a process with a large address space may fail when memory overcommit is disabled,
+    /// even though the child would execute a new program right away.
+    #[default]
+    Auto,
+    /// Always fork.
+    Fork,
+    /// Always use `posix_spawn`, which does not copy the address space of the parent.
+    ///
+    /// Only the working directory, process group, session, signal dispositions and mask, standard
+    /// I/O and environment can be configured, and only the process group, signals, standard I/O
+    /// and environment outside of Linux. Spawning fails with [`ErrorKind::Unsupported`] if other
+    /// options are set. In return, the forking process does not need to be single-threaded.
+    PosixSpawn,
+}
+
+/// Cgroup to create the child process in.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+enum Cgroup {
+    Path(PathBuf),
+    Fd(OwnedFd),
+}
+
+/// Builder for configuring the child process before the closure runs.
+///
+/// The setup steps are performed in the child process right after forking. If any of them fails,
+/// the child exits without running the closure and the error is reported back by [`spawn`].
+///
+/// [`spawn`]: ForkBuilder::spawn
+#[derive(Debug, Default)]
+pub struct ForkBuilder {
+    cwd: Option<PathBuf>,
+    chroot: Option<PathBuf>,
+    umask: Option<libc::mode_t>,
+    process_group: bool,
+    session: bool,
+    reset_signal_handlers: bool,
+    signal_mask: Option<SigSet>,
+    sanitize: bool,
+    scrub: ScrubHooks,
+    #[cfg(target_os = "linux")]
+    dumpable: Option<bool>,
+    #[cfg(target_os = "linux")]
+    name: Option<OsString>,
+    core_dumps: bool,
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+    stderr_to_stdout: bool,
+    /// Whether to start from an empty environment rather than the inherited one.
+    env_clear: bool,
+    /// Environment variables to set, or to remove if the value is `None`, in order.
+    env: Vec<(OsString, Option<OsString>)>,
+    #[cfg(target_os = "linux")]
+    clone_flags: CloneFlags,
+    #[cfg(target_os = "linux")]
+    pdeathsig: Option<libc::c_int>,
+    #[cfg(target_os = "linux")]
+    oom_score_adj: Option<i32>,
+    #[cfg(target_os = "linux")]
+    cgroup: Option<Cgroup>,
+    #[cfg(target_os = "linux")]
+    id_maps: IdMaps,
+    #[cfg(target_os = "linux")]
+    mounts: Mounts,
+    #[cfg(target_os = "linux")]
+    loopback_up: bool,
+    #[cfg(target_os = "linux")]
+    hostname: Option<OsString>,
+    #[cfg(target_os = "linux")]
+    time_offsets: Option<TimeOffsets>,
+    #[cfg(target_os = "linux")]
+    no_new_privs: bool,
+    #[cfg(target_os = "linux")]
+    drop_caps: CapabilityDrop,
+    #[cfg(all(target_os = "linux", feature = "landlock"))]
+    landlock: Ruleset,
+    #[cfg(all(target_os = "linux", feature = "seccomp"))]
+    seccomp: Option<SeccompFilter>,
+    rlimits: Rlimits,
+    cpu_limit: Option<Duration>,
+    memory_limit: Option<u64>,
+    nice: Option<i32>,
+    #[cfg(target_os = "linux")]
+    sched_policy: Option<SchedPolicy>,
+    #[cfg(target_os = "linux")]
+    cpu_affinity: Option<Vec<usize>>,
+    #[cfg(target_os = "linux")]
+    io_priority: Option<(IoPriorityClass, u8)>,
+    uid: Option<libc::uid_t>,
+    gid: Option<libc::gid_t>,
+    groups: Option<Vec<libc::gid_t>>,
+    /// File descriptors to keep open if other file descriptors are to be closed.
+    keep_fds: Option<Vec<RawFd>>,
+    /// File descriptors of the parent, and the numbers they are installed as in the child.
+    fd_mappings: Vec<(RawFd, RawFd)>,
+    panic_exit_code: Option<i32>,
+    capture_panics: bool,
+    backend: SpawnBackend,
+    /// Whether to trace the child with `ptrace`, and whether to trace system calls as well.
+    #[cfg(target_os = "linux")]
+    trace: Option<bool>,
+}
+
+/// State prepared by the parent before forking, used by the child during setup.
+struct Prepared {
+    stdio: [Option<OwnedFd>; 3],
+    #[cfg(target_os = "linux")]
+    parent: libc::pid_t,
+    /// Pipe that the parent writes to once it completes its part of the setup.
+    #[cfg(target_os = "linux")]
+    parent_setup_done: Option<File>,
+    /// File descriptor used to report setup errors to the parent.
+    report: RawFd,
+    /// Other file descriptors used by the crate in the child after setup.
+    internal: Vec<RawFd>,
+}
+
+impl ForkBuilder {
+    /// Creates a new builder with the default configuration, which performs no setup at all.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the working directory of the child process.
+    pub fn chdir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Changes the root directory of the child process with `chroot`.
+    ///
+    /// The working directory is changed to the new root, unless set with [`chdir`](Self::chdir),
+    /// in which case it is resolved within the new root. The root is changed before the
+    /// credentials, as `chroot` requires privilege.
+    pub fn chroot(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.chroot = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Puts the child into a new process group, with the child as its leader.
+    ///
+    /// This isolates the child from signals sent to the process group of the parent, such as
+    /// `SIGINT` from Ctrl-C in a terminal, and allows [`Child::kill_tree`] to kill the descendants
+    /// of the child as well.
+    pub fn new_process_group(&mut self) -> &mut Self {
+        self.process_group = true;
+        self
+    }
+
+    /// Puts the child into a new session, with the child as the leader of the session and of a
+    /// new process group.
+    ///
+    /// In addition to the effects of [`new_process_group`](Self::new_process_group), this detaches
+    /// the child from the controlling terminal.
+    pub fn new_session(&mut self) -> &mut Self {
+        self.session = true;
+        self
+    }
+
+    /// Resets the dispositions of all signals to the default in the child.
+    ///
+    /// Otherwise, the child inherits the signal handlers of the parent, as well as the signals it
+    /// ignores. For instance, the Rust runtime ignores `SIGPIPE`.
+    pub fn reset_signal_handlers(&mut self) -> &mut Self {
+        self.reset_signal_handlers = true;
+        self
+    }
+
+    /// Sets the signal mask of the child, instead of inheriting the one of the forking thread.
+    pub fn signal_mask(&mut self, mask: SigSet) -> &mut Self {
+        self.signal_mask = Some(mask);
+        self
+    }
+
+    /// Applies common hygiene steps to the child, so that it does not share state with the parent
+    /// by accident:
+    ///
+    /// * `SIGPIPE` is reset to its default disposition, as the Rust runtime ignores it.
+    /// * On Linux, inherited epoll, io_uring, timerfd, signalfd, eventfd and inotify file
+    ///   descriptors are closed. Their state is shared with the parent, so using them from both
+    ///   processes leads to missed or stolen events.
+    ///
+    /// Random number generators in user space are left to reseed themselves with at-fork hooks
+    /// (see [`register_atfork`](crate::register_atfork)), which run before this. The state of
+    /// `arc4random` in glibc is wiped by the kernel on fork, and `getrandom` has no state to clear.
+    /// Use [`dumpable`](Self::dumpable) to control whether the child can be inspected.
+    pub fn sanitize_child(&mut self) -> &mut Self {
+        self.sanitize = true;
+        self
+    }
+
+    /// Registers a hook that runs in the child before any other setup, to wipe sensitive data
+    /// inherited from the parent.
+    ///
+    /// The hook is meant to call [`Scrubbable::scrub`](crate::Scrubbable::scrub) on key material,
+    /// password buffers and the like, which it can reach through shared ownership or statics, so
+    /// that neither the closure nor the setup steps run with the secrets in memory. Hooks run in
+    /// the order they are registered, after the at-fork hooks (see
+    /// [`register_atfork`](crate::register_atfork)). They do not run for children spawned with
+    /// `posix_spawn`, which do not inherit the memory of the parent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use safe_fork::{ForkBuilder, Scrubbable};
+    ///
+    /// let key = Arc::new(Mutex::new(vec![0x42u8; 32]));
+    /// let secret = key.clone();
+    /// let child = ForkBuilder::new()
+    ///     .scrub(move || secret.lock().unwrap().scrub())
+    ///     .spawn(move || key.lock().unwrap().iter().all(|&b| b == 0) as i32)
+    ///     .unwrap();
+    /// assert_eq!(child.join().unwrap().code(), Some(1));
+    /// ```
+    pub fn scrub(&mut self, hook: impl Fn() + Send + Sync + 'static) -> &mut Self {
+        self.scrub.push(hook);
+        self
+    }
+
+    /// Sets whether the child is dumpable, which controls whether it produces core dumps and
+    /// whether other processes of the same user can attach to it with `ptrace`.
+    ///
+    /// This is applied after the credentials change, which may reset the flag.
+    #[cfg(target_os = "linux")]
+    pub fn dumpable(&mut self, dumpable: bool) -> &mut Self {
+        self.dumpable = Some(dumpable);
+        self
+    }
+
+    /// Sets the name of the child, as shown by `top` and `ps -o comm`, using `PR_SET_NAME`.
+    ///
+    /// Names longer than 15 bytes are truncated, and names containing NUL bytes are rejected. The
+    /// command line of the child is left as is. Executed programs are named after their file
+    /// instead.
+    #[cfg(target_os = "linux")]
+    pub fn name(&mut self, name: impl AsRef<OsStr>) -> &mut Self {
+        self.name = Some(name.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the file mode creation mask of the child process.
+    pub fn umask(&mut self, mask: u32) -> &mut Self {
+        self.umask = Some(mask as _);
+        self
+    }
+
+    /// Configures the standard input of the child process.
+    pub fn stdin(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.stdin = cfg.into();
+        self
+    }
+
+    /// Configures the standard output of the child process.
+    pub fn stdout(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.stdout = cfg.into();
+        self
+    }
+
+    /// Configures the standard error of the child process.
+    pub fn stderr(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.stderr = cfg.into();
+        self
+    }
+
+    /// Redirects the standard error of the child process to its standard output, like `2>&1`.
+    ///
+    /// Output written to either stream is then interleaved in the order that it is written. The
+    /// configuration set by [`stderr`](Self::stderr) is ignored.
+    pub fn stderr_to_stdout(&mut self) -> &mut Self {
+        self.stderr_to_stdout = true;
+        self
+    }
+
+    /// Sets an environment variable in the child process.
+    ///
+    /// The environment is changed in the child before the closure runs, or passed to the program
+    /// by [`exec`](Self::exec); the environment of the parent is never modified.
+    pub fn env(&mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> &mut Self {
+        self.env
+            .push((key.as_ref().to_owned(), Some(val.as_ref().to_owned())));
+        self
+    }
+
+    /// Removes an environment variable from the child process.
+    pub fn env_remove(&mut self, key: impl AsRef<OsStr>) -> &mut Self {
+        self.env.push((key.as_ref().to_owned(), None));
+        self
+    }
+
+    /// Clears the environment of the child process, so that only the variables set with
+    /// [`env`](Self::env) after this call are present.
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.env_clear = true;
+        self.env.clear();
+        self
+    }
+
+    /// Runs the child in a new user namespace.
+    ///
+    /// The child has a full set of capabilities in the new namespace, which allows it to create
+    /// other namespaces without privilege.
+    #[cfg(target_os = "linux")]
+    pub fn new_user_ns(&mut self) -> &mut Self {
+        self.clone_flags |= CloneFlags::NEWUSER;
+        self
+    }
+
+    /// Runs the child in a new mount namespace.
+    #[cfg(target_os = "linux")]
+    pub fn new_mount_ns(&mut self) -> &mut Self {
+        self.clone_flags |= CloneFlags::NEWNS;
+        self
+    }
+
+    /// Mounts a fresh tmpfs at `path` in the child.
+    ///
+    /// This implies [`new_mount_ns`](Self::new_mount_ns), so the mounts performed are only visible
+    /// to the child and its descendants. Mounts are performed in the order they are requested,
+    /// after [`readonly_root`](Self::readonly_root) takes effect.
+    #[cfg(target_os = "linux")]
+    pub fn mount_tmpfs(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.mounts.tmpfs(path.as_ref());
+        self.new_mount_ns()
+    }
+
+    /// Makes the root mount read-only in the child.
+    ///
+    /// Other mounts, including the ones requested with [`mount_tmpfs`](Self::mount_tmpfs) and
+    /// [`bind_mount`](Self::bind_mount), are not affected. This implies
+    /// [`new_mount_ns`](Self::new_mount_ns).
+    #[cfg(target_os = "linux")]
+    pub fn readonly_root(&mut self) -> &mut Self {
+        self.mounts.readonly_root();
+        self.new_mount_ns()
+    }
+
+    /// Bind mounts `src` at `dst` in the child, optionally as read-only.
+    ///
+    /// `dst` must already exist. This implies [`new_mount_ns`](Self::new_mount_ns).
+    #[cfg(target_os = "linux")]
+    pub fn bind_mount(
+        &mut self,
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        readonly: bool,
+    ) -> &mut Self {
+        self.mounts.bind(src.as_ref(), dst.as_ref(), readonly);
+        self.new_mount_ns()
+    }
+
+    /// Changes the root mount of the child to `path` with `pivot_root`, and detaches the old root.
+    ///
+    /// Unlike [`chroot`](Self::chroot), the old root is no longer reachable at all. This implies
+    /// [`new_mount_ns`](Self::new_mount_ns), and takes effect after the other mounts, so they may
+    /// be used to populate the new root. The working directory is handled as with `chroot`.
+    #[cfg(target_os = "linux")]
+    pub fn pivot_root(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.mounts.pivot_root(path.as_ref());
+        self.new_mount_ns()
+    }
+
+    /// Runs the child in a new PID namespace.
+    ///
+    /// The child becomes PID 1 of the namespace. When it exits, all other processes in the
+    /// namespace are killed.
+    ///
+    /// Unless the child executes a program, it is created with `clone3` rather than by the C
+    /// library, see [`CloneFlags`] for the consequences.
+    #[cfg(target_os = "linux")]
+    pub fn new_pid_ns(&mut self) -> &mut Self {
+        self.clone_flags |= CloneFlags::NEWPID;
+        self
+    }
+
+    /// Runs the child in a new network namespace.
+    #[cfg(target_os = "linux")]
+    pub fn new_net_ns(&mut self) -> &mut Self {
+        self.clone_flags |= CloneFlags::NEWNET;
+        self
+    }
+
+    /// Brings up the loopback interface in the new network namespace of the child.
+    ///
+    /// The child can then use `127.0.0.1` and `::1` while still being cut off from all other
+    /// networks. This implies [`new_net_ns`](Self::new_net_ns).
+    #[cfg(target_os = "linux")]
+    pub fn loopback_up(&mut self) -> &mut Self {
+        self.loopback_up = true;
+        self.new_net_ns()
+    }
+
+    /// Runs the child in a new UTS namespace.
+    #[cfg(target_os = "linux")]
+    pub fn new_uts_ns(&mut self) -> &mut Self {
+        self.clone_flags |= CloneFlags::NEWUTS;
+        self
+    }
+
+    /// Sets the hostname of the child.
+    ///
+    /// This implies [`new_uts_ns`](Self::new_uts_ns), so the hostname of the parent is not
+    /// affected.
+    #[cfg(target_os = "linux")]
+    pub fn hostname(&mut self, name: impl AsRef<OsStr>) -> &mut Self {
+        self.hostname = Some(name.as_ref().to_owned());
+        self.new_uts_ns()
+    }
+
+    /// Runs the child in a new IPC namespace.
+    ///
+    /// The child starts with no System V IPC objects and no POSIX message queues, so it cannot
+    /// access the shared memory segments, semaphores and message queues of the parent. Memory
+    /// shared through inherited `MAP_SHARED` mappings is not affected.
+    #[cfg(target_os = "linux")]
+    pub fn new_ipc_ns(&mut self) -> &mut Self {
+        self.clone_flags |= CloneFlags::NEWIPC;
+        self
+    }
+
+    /// Runs the child in a new time namespace, where `CLOCK_MONOTONIC` and `CLOCK_BOOTTIME` are
+    /// shifted by `offsets`.
+    ///
+    /// This requires `CAP_SYS_ADMIN`, which an unprivileged parent may obtain with
+    /// [`new_user_ns`](Self::new_user_ns). The namespace is entered before the other setup
+    /// steps, so the closure or executed program only ever observes the shifted clocks.
+    #[cfg(target_os = "linux")]
+    pub fn new_time_ns(&mut self, offsets: TimeOffsets) -> &mut Self {
+        self.time_offsets = Some(offsets);
+        self
+    }
+
+    /// Sets the user ID mappings of the new user namespace.
+    ///
+    /// This implies [`new_user_ns`](Self::new_user_ns). The mappings are written by the parent
+    /// before the child performs any other setup. Unprivileged processes may only map their own
+    /// user ID, unless [`use_id_map_helpers`](Self::use_id_map_helpers) is called.
+    #[cfg(target_os = "linux")]
+    pub fn uid_map(&mut self, maps: &[IdMap]) -> &mut Self {
+        self.id_maps.uid = maps.to_owned();
+        self.new_user_ns()
+    }
+
+    /// Sets the group ID mappings of the new user namespace.
+    ///
+    /// This implies [`new_user_ns`](Self::new_user_ns). Unless configured otherwise with
+    /// [`setgroups`](Self::setgroups), `setgroups` is denied in the namespace, as unprivileged
+    /// processes cannot map groups otherwise.
+    #[cfg(target_os = "linux")]
+    pub fn gid_map(&mut self, maps: &[IdMap]) -> &mut Self {
+        self.id_maps.gid = maps.to_owned();
+        self.new_user_ns()
+    }
+
+    /// Sets whether `setgroups` is allowed in the new user namespace.
+    ///
+    /// This implies [`new_user_ns`](Self::new_user_ns).
+    #[cfg(target_os = "linux")]
+    pub fn setgroups(&mut self, allow: bool) -> &mut Self {
+        self.id_maps.setgroups = Some(allow);
+        self.new_user_ns()
+    }
+
+    /// Writes the ID mappings with the `newuidmap` and `newgidmap` programs, which allow
+    /// unprivileged users to map the ID ranges delegated to them in `/etc/subuid` and
+    /// `/etc/subgid`.
+    ///
+    /// The programs handle `setgroups` themselves, so [`setgroups`](Self::setgroups) is ignored.
+    #[cfg(target_os = "linux")]
+    pub fn use_id_map_helpers(&mut self) -> &mut Self {
+        self.id_maps.helpers = true;
+        self
+    }
+
+    /// Passes additional flags to `clone3` when creating the child.
+    #[cfg(target_os = "linux")]
+    pub fn clone_flags(&mut self, flags: CloneFlags) -> &mut Self {
+        self.clone_flags |= flags;
+        self
+    }
+
+    /// Passes additional raw flags to `clone3` when creating the child.
+    ///
+    /// # Safety
+    ///
+    /// See [`CloneFlags::from_bits_unchecked`].
+    #[cfg(target_os = "linux")]
+    pub unsafe fn raw_clone_flags(&mut self, flags: u64) -> &mut Self {
+        // SAFETY: forwarded to the caller.
+        self.clone_flags |= unsafe { CloneFlags::from_bits_unchecked(flags) };
+        self
+    }
+
+    /// Creates the child in the cgroup v2 directory at `path`.
+    ///
+    /// The child is created directly in the cgroup with `CLONE_INTO_CGROUP`, so it is subject to
+    /// the controllers of the cgroup from the start. On kernels older than 5.7, the parent moves
+    /// the child into the cgroup before it performs any setup instead.
+    #[cfg(target_os = "linux")]
+    pub fn cgroup(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.cgroup = Some(Cgroup::Path(path.as_ref().to_owned()));
+        self
+    }
+
+    /// Creates the child in the cgroup v2 directory referred to by `dir`.
+    ///
+    /// See [`cgroup`](Self::cgroup).
+    #[cfg(target_os = "linux")]
+    pub fn cgroup_fd(&mut self, dir: OwnedFd) -> &mut Self {
+        self.cgroup = Some(Cgroup::Fd(dir));
+        self
+    }
+
+    /// Arranges for the child to receive `signal` when the parent dies.
+    ///
+    /// This uses `PR_SET_PDEATHSIG`, which fires when the thread that forked the child exits. If
+    /// the parent has already died by the time this is set up, the signal is raised immediately.
+    /// This check is not possible in a new PID namespace, where the parent is not visible.
+    #[cfg(target_os = "linux")]
+    pub fn pdeathsig(&mut self, signal: i32) -> &mut Self {
+        self.pdeathsig = Some(signal);
+        self
+    }
+
+    /// Sets the OOM score adjustment of the child, ranging from -1000 to 1000.
+    ///
+    /// Higher values make the child more likely to be killed by the OOM killer. Lowering the
+    /// value below that of the parent requires `CAP_SYS_RESOURCE`. This is applied early in the
+    /// setup, while `/proc` is still accessible and before any privileges are dropped.
+    #[cfg(target_os = "linux")]
+    pub fn oom_score_adj(&mut self, score: i32) -> &mut Self {
+        self.oom_score_adj = Some(score);
+        self
+    }
+
+    /// Enables core dumps of the child, written to `dir`.
+    ///
+    /// The soft `RLIMIT_CORE` limit is raised to the hard one, unless set with
+    /// [`rlimits`](Self::rlimits), and on Linux, the child is made [`dumpable`](Self::dumpable).
+    /// The kernel writes core files to the working directory of the crashing process if the core
+    /// pattern (`/proc/sys/kernel/core_pattern` on Linux) is a relative path, so this sets the
+    /// working directory to `dir` as with [`chdir`](Self::chdir). Patterns that pipe the core to a
+    /// program, as with `systemd-coredump`, are not affected.
+    ///
+    /// Whether a core was dumped is reported by [`ChildStatus::core_dumped`](crate::ChildStatus::core_dumped).
+    pub fn core_dumps(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.core_dumps = true;
+        #[cfg(target_os = "linux")]
+        {
+            self.dumpable = Some(true);
+        }
+        self.chdir(dir)
+    }
+
+    /// Applies resource limits to the child process.
+    pub fn rlimits(&mut self, limits: Rlimits) -> &mut Self {
+        self.rlimits = limits;
+        self
+    }
+
+    /// Limits the CPU time of the child process to `limit`, rounded up to whole seconds.
+    ///
+    /// The child receives `SIGXCPU` once the limit is reached, and `SIGKILL` a second later if it
+    /// handles or ignores `SIGXCPU`. This overrides the CPU limit set with
+    /// [`rlimits`](Self::rlimits). Use [`Child::join_checked`] to tell a child that exceeded the
+    /// limit apart from one that crashed.
+    pub fn cpu_limit(&mut self, limit: Duration) -> &mut Self {
+        self.cpu_limit = Some(limit);
+        self
+    }
+
+    /// Limits the memory of the child process to `bytes`.
+    ///
+    /// If the child is created in a cgroup with [`cgroup`](Self::cgroup), this sets `memory.max`
+    /// of the cgroup, which limits the memory used by all processes in it, and disables swap with
+    /// `memory.swap.max`. The OOM killer then kills the child once the limit is reached.
+    /// Otherwise, this limits the address space of the child with `RLIMIT_AS`, which overrides the
+    /// limit set with [`rlimits`](Self::rlimits), and allocations fail once the limit is reached.
+    ///
+    /// Use [`Child::join_checked`] to tell a child that exceeded the limit apart from one that
+    /// crashed. With a cgroup, this is based on the OOM kills recorded in `memory.events`.
+    /// Otherwise, it only covers closures aborting with `SIGABRT` after a failed allocation, as
+    /// Rust does by default, and is based on the error number left behind by the allocator. This
+    /// is not reliable if `RUST_BACKTRACE` is set, as printing the backtrace may overwrite it.
+    pub fn memory_limit(&mut self, bytes: u64) -> &mut Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Limit of the address space of the child, if the memory limit is enforced with `RLIMIT_AS`.
+    fn address_space_limit(&self) -> Option<u64> {
+        #[cfg(target_os = "linux")]
+        if self.cgroup.is_some() {
+            return None;
+        }
+        self.memory_limit
+    }
+
+    /// Sets the nice value of the child process.
+    ///
+    /// Lowering the nice value below that of the parent requires privilege.
+    pub fn nice(&mut self, value: i32) -> &mut Self {
+        self.nice = Some(value);
+        self
+    }
+
+    /// Sets the scheduling policy of the child process.
+    #[cfg(target_os = "linux")]
+    pub fn sched_policy(&mut self, policy: SchedPolicy) -> &mut Self {
+        self.sched_policy = Some(policy);
+        self
+    }
+
+    /// Restricts the child process to run on the given CPUs.
+    ///
+    /// CPUs are identified by their index, as in `sched_setaffinity`.
+    #[cfg(target_os = "linux")]
+    pub fn cpu_affinity(&mut self, cpus: &[usize]) -> &mut Self {
+        self.cpu_affinity = Some(cpus.to_owned());
+        self
+    }
+
+    /// Sets the I/O scheduling class and priority level of the child process.
+    ///
+    /// `level` ranges from 0 (highest) to 7 (lowest).
+    #[cfg(target_os = "linux")]
+    pub fn io_priority(&mut self, class: IoPriorityClass, level: u8) -> &mut Self {
+        self.io_priority = Some((class, level));
+        self
+    }
+
+    /// Sets the user ID of the child process.
+    ///
+    /// If supplementary groups are not set with [`groups`](Self::groups) and the parent is root,
+    /// the supplementary groups of the child are cleared.
+    pub fn uid(&mut self, id: u32) -> &mut Self {
+        self.uid = Some(id);
+        self
+    }
+
+    /// Sets the group ID of the child process.
+    pub fn gid(&mut self, id: u32) -> &mut Self {
+        self.gid = Some(id);
+        self
+    }
+
+    /// Sets the supplementary groups of the child process.
+    pub fn groups(&mut self, groups: &[u32]) -> &mut Self {
+        self.groups = Some(groups.to_owned());
+        self
+    }
+
+    /// Sets `no_new_privs` in the child process, so that it and its descendants cannot gain
+    /// privileges, e.g. by executing set-user-ID programs.
+    #[cfg(target_os = "linux")]
+    pub fn no_new_privs(&mut self) -> &mut Self {
+        self.no_new_privs = true;
+        self
+    }
+
+    /// Drops the capabilities from all capability sets of the child process, including the
+    /// bounding and ambient sets, so that they cannot be regained.
+    ///
+    /// Dropping capabilities from the bounding set requires `CAP_SETPCAP`, which is available to
+    /// root and in a new user namespace.
+    #[cfg(target_os = "linux")]
+    pub fn drop_capabilities(&mut self, caps: &[Capability]) -> &mut Self {
+        self.drop_caps.caps.extend_from_slice(caps);
+        self
+    }
+
+    /// Drops all capabilities of the child process.
+    ///
+    /// See [`drop_capabilities`](Self::drop_capabilities).
+    #[cfg(target_os = "linux")]
+    pub fn clear_capabilities(&mut self) -> &mut Self {
+        self.drop_caps.all = true;
+        self
+    }
+
+    /// Allows the child process to read and execute files beneath `path`.
+    ///
+    /// Once any path is allowed, the child is restricted with Landlock, so that it can only access
+    /// the allowed paths; this requires the kernel to support Landlock, and the child fails to start
+    /// otherwise. Files that are already open are not affected. This also sets `no_new_privs`.
+    #[cfg(all(target_os = "linux", feature = "landlock"))]
+    pub fn allow_read(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.landlock.allow(path.as_ref(), false);
+        self
+    }
+
+    /// Allows the child process to read, execute, write, create and remove files beneath `path`.
+    ///
+    /// See [`allow_read`](Self::allow_read).
+    #[cfg(all(target_os = "linux", feature = "landlock"))]
+    pub fn allow_write(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.landlock.allow(path.as_ref(), true);
+        self
+    }
+
+    /// Installs a seccomp filter in the child process.
+    ///
+    /// The filter is installed as the last setup step, so it only needs to allow the system calls
+    /// made by the closure, or `execve` and the system calls made by the program for
+    /// [`exec`](Self::exec). This also sets `no_new_privs`, which is required to install filters
+    /// without privilege.
+    #[cfg(all(target_os = "linux", feature = "seccomp"))]
+    pub fn seccomp(&mut self, filter: SeccompFilter) -> &mut Self {
+        self.seccomp = Some(filter);
+        self
+    }
+
+    /// Closes all inherited file descriptors other than the standard I/O in the child process.
+    ///
+    /// File descriptors used by the closure must be whitelisted with [`keep_fds`](Self::keep_fds).
+    pub fn close_fds(&mut self) -> &mut Self {
+        self.keep_fds.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Closes all inherited file descriptors other than the standard I/O and the given ones in the
+    /// child process.
+    pub fn keep_fds(&mut self, fds: &[RawFd]) -> &mut Self {
+        self.keep_fds
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(fds);
+        self
+    }
+
+    /// Installs the file descriptor `fd` of the parent as `target` in the child process.
+    ///
+    /// This implies [`close_fds`](Self::close_fds), so only the standard I/O, the targets of the
+    /// mappings and the file descriptors given to [`keep_fds`](Self::keep_fds) stay open. The
+    /// targets are not close-on-exec, and mappings may swap file descriptors. They are applied
+    /// after the standard I/O is configured, so a target of 0, 1 or 2 replaces that stream.
+    pub fn inherit_fd(&mut self, fd: RawFd, target: RawFd) -> &mut Self {
+        self.keep_fds.get_or_insert_with(Vec::new);
+        self.fd_mappings.push((fd, target));
+        self
+    }
+
+    /// Sets the exit code of the child process if the closure panics. Defaults to 101.
+    pub fn panic_exit_code(&mut self, code: i32) -> &mut Self {
+        self.panic_exit_code = Some(code);
+        self
+    }
+
+    /// Sends the panic message and backtrace to the parent if the closure panics.
+    ///
+    /// Waiting for such a child then fails with a [`ChildPanicked`](crate::ChildPanicked) error,
+    /// instead of returning the exit status.
+    pub fn capture_panics(&mut self) -> &mut Self {
+        self.capture_panics = true;
+        self
+    }
+
+    /// Traces the child with `ptrace`, so that its events can be waited for with
+    /// [`Child::next_trace_event`].
+    ///
+    /// The parent attaches with `PTRACE_SEIZE` before the setup steps, so the child is traced when
+    /// its closure or program starts. Waiting for the child other than with
+    /// [`Child::next_trace_event`], e.g. with [`Child::join`], resumes it at every event until it
+    /// exits, and dropping the handle kills the child. By default, signal-delivery and exec events
+    /// are reported; group-stops are not, so stop signals are delivered but do not stop the child.
+    #[cfg(target_os = "linux")]
+    pub fn traced(&mut self) -> &mut Self {
+        self.trace.get_or_insert(false);
+        self
+    }
+
+    /// Traces the child with `ptrace` as with [`traced`](Self::traced), reporting entries into and
+    /// exits from system calls as well.
+    ///
+    /// System calls are traced from shortly after the setup steps complete, so the first few system
+    /// calls of the closure, or `execve` of the program, may not be reported.
+    #[cfg(target_os = "linux")]
+    pub fn trace_syscalls(&mut self) -> &mut Self {
+        self.trace = Some(true);
+        self
+    }
+
+    /// Sets how [`exec`](Self::exec) creates the child process.
+    pub fn spawn_backend(&mut self, backend: SpawnBackend) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Fork the current process, and execute the provided closure within the configured child
+    /// process.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn spawn<T: Termination>(
+        &mut self,
+        f: impl FnOnce() -> T,
+    ) -> std::result::Result<Child, ForkError> {
+        self.spawn_keeping(&[], || (), f)
+    }
+
+    /// Fork the current process as with [`spawn`](Self::spawn), keeping `internal` open in the
+    /// child even if other file descriptors are closed.
+    ///
+    /// The file descriptors must have been moved out of the way of the mappings with
+    /// [`shelter`](Self::shelter). `close_parent_ends` runs in the child before setup, and must
+    /// close the file descriptors only used by the parent, so that setup does not close them first.
+    pub(crate) fn spawn_keeping<T: Termination>(
+        &mut self,
+        internal: &[RawFd],
+        close_parent_ends: impl FnOnce(),
+        f: impl FnOnce() -> T,
+    ) -> std::result::Result<Child, ForkError> {
+        let (mut panic_reader, panic_writer) = match self.capture_panics {
+            true => {
+                let (reader, writer) = crate::pipe()?;
+                (Some(reader), Some(self.shelter(writer)?))
+            }
+            false => (None, None),
+        };
+        let (mut abort_reader, abort_writer) = match self.address_space_limit() {
+            Some(_) => {
+                let (reader, writer) = crate::pipe()?;
+                (Some(reader), Some(self.shelter(writer)?))
+            }
+            None => (None, None),
+        };
+        let panic_code = self
+            .panic_exit_code
+            .unwrap_or(crate::panic::PANIC_EXIT_CODE);
+
+        let mut internal = internal.to_vec();
+        internal.extend(
+            panic_writer
+                .iter()
+                .chain(&abort_writer)
+                .map(File::as_raw_fd),
+        );
+        let close_parent_ends = || {
+            drop((panic_reader.take(), abort_reader.take()));
+            close_parent_ends();
+        };
+        let mut child = self.spawn_with(false, internal, close_parent_ends, |writer| {
+            drop(writer);
+            if let Some(writer) = abort_writer {
+                crate::memory::report_alloc_failure(writer);
+            }
+            crate::panic::run_child(f, panic_code, panic_writer)
+        })?;
+        if let Some(reader) = &panic_reader {
+            // The pipe is only read after the child exits, when the message is complete.
+            crate::set_nonblocking(reader.as_raw_fd())?;
+        }
+        child.panic = panic_reader;
+        if let Some(reader) = abort_reader {
+            crate::set_nonblocking(reader.as_raw_fd())?;
+            child.memory = Some(crate::memory::MemoryWatch::Abort(reader));
+        }
+        Ok(child)
+    }
+
+    /// Fork the current process, and serve requests with `handler` within the configured child
+    /// process.
+    ///
+    /// See [`fork_service`](crate::fork_service).
+    #[cfg(feature = "serde")]
+    pub fn spawn_service<Req, Resp>(
+        &mut self,
+        handler: impl FnMut(Req) -> Resp,
+    ) -> Result<crate::ServiceHandle<Req, Resp>>
+    where
+        Req: serde::Serialize + serde::de::DeserializeOwned,
+        Resp: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        crate::service::spawn(self, handler)
+    }
+
+    /// Fork the current process, and serve requests with `handler` within the configured child
+    /// process, serializing the requests and the responses with the codec `C`.
+    ///
+    /// See [`fork_service_codec`](crate::fork_service_codec).
+    #[cfg(feature = "serde")]
+    pub fn spawn_service_codec<C, Req, Resp>(
+        &mut self,
+        handler: impl FnMut(Req) -> Resp,
+    ) -> Result<crate::ServiceHandle<Req, Resp, C>>
+    where
+        C: crate::Codec,
+        Req: serde::Serialize + serde::de::DeserializeOwned,
+        Resp: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        crate::service::spawn(self, handler)
+    }
+
+    /// Fork the current process, and execute `program` within the configured child process.
+    ///
+    /// `program` is searched in `PATH` if it does not contain a slash, and is passed to it as
+    /// `argv[0]`, followed by `args`. The program inherits the environment as changed by
+    /// [`env`](Self::env), [`env_remove`](Self::env_remove) and [`env_clear`](Self::env_clear).
+    /// File descriptors given to [`keep_fds`](Self::keep_fds) are
+    /// inherited by the program even if they are close-on-exec.
+    ///
+    /// Failure to execute the program is reported as an error from this call.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail. If no setup is
+    /// needed, [`fork_exec`](crate::fork_exec) avoids both the fork and this restriction. See
+    /// [`spawn_backend`](Self::spawn_backend) for using `posix_spawn` instead of forking.
+    pub fn exec<S: AsRef<OsStr>>(
+        &mut self,
+        program: impl AsRef<OsStr>,
+        args: impl IntoIterator<Item = S>,
+    ) -> std::result::Result<Child, ForkError> {
+        // Allocate everything before forking, so that invalid arguments can be reported directly.
+        let argv = CStringArray::argv(program.as_ref(), args)?;
+        let child = match self.backend {
+            SpawnBackend::PosixSpawn => self.posix_spawn(&argv)?,
+            backend => match self.fork_exec(&argv) {
+                Err(ForkError::ForkFailed(err))
+                    if backend == SpawnBackend::Auto
+                        && matches!(err.raw_os_error(), Some(libc::ENOMEM | libc::EAGAIN))
+                        && self.posix_spawn_supported() =>
+                {
+                    trace!(debug, error = %err, "failed to fork, falling back to posix_spawn");
+                    self.posix_spawn(&argv)?
+                }
+                result => result?,
+            },
+        };
+        trace!(debug, pid = child.pid(), program = ?program.as_ref(), "executed program");
+        Ok(child)
+    }
+
+    /// Fork the current process, and execute the program in `argv` after setup.
+    fn fork_exec(&self, argv: &CStringArray) -> std::result::Result<Child, ForkError> {
+        let inherit = self.keep_fds.clone().unwrap_or_default();
+        self.spawn_with(
+            true,
+            Vec::new(),
+            || (),
+            |mut writer| {
+                let err = (|| {
+                    for &fd in &inherit {
+                        // SAFETY: `fcntl` does not have special safety requirements.
+                        if unsafe { libc::fcntl(fd, libc::F_SETFD, 0) } < 0 {
+                            return Error::last_os_error();
+                        }
+                    }
+                    // SAFETY: `argv` is a null-terminated array of valid C strings. The report pipe is
+                    // close-on-exec, so the parent sees EOF if this succeeds.
+                    unsafe { libc::execvp(argv.program(), argv.as_ptr()) };
+                    Error::last_os_error()
+                })();
+                report(&mut writer, Stage::Exec, err)
+            },
+        )
+    }
+
+    /// Spawn the program in `argv` with `posix_spawn`, applying the configuration through its
+    /// attributes and file actions.
+    fn posix_spawn(&self, argv: &CStringArray) -> std::result::Result<Child, ForkError> {
+        if !self.posix_spawn_supported() {
+            return Err(ForkError::Io(Error::new(
+                ErrorKind::Unsupported,
+                "configuration is not supported by posix_spawn",
+            )));
+        }
+        let mut env: Vec<(OsString, OsString)> = if self.env_clear {
+            Vec::new()
+        } else {
+            std::env::vars_os().collect()
+        };
+        for (key, val) in &self.env {
+            env.retain(|(k, _)| k != key);
+            if let Some(val) = val {
+                env.push((key.clone(), val.clone()));
+            }
+        }
+        let envp = CStringArray::new(env.into_iter().map(|(mut var, val)| {
+            var.push("=");
+            var.push(val);
+            var
+        }))?;
+        let (stdio, parents) = self.prepare_stdio()?;
+
+        let mut config = SpawnConfig::new()?;
+        if self.session {
+            #[cfg(target_os = "linux")]
+            config.new_session()?;
+        } else if self.process_group {
+            config.new_process_group()?;
+        }
+        if self.reset_signal_handlers {
+            config.signal_default(SigSet::full().as_raw())?;
+        }
+        if let Some(mask) = &self.signal_mask {
+            config.signal_mask(mask.as_raw())?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(cwd) = &self.cwd {
+            config.chdir(cwd.as_os_str())?;
+        }
+        for (fd, target) in stdio.iter().zip(0..) {
+            if let Some(fd) = fd {
+                config.dup2(fd.as_raw_fd(), target)?;
+            }
+        }
+        if self.stderr_to_stdout {
+            config.dup2(1, 2)?;
+        }
+
+        let mut child = crate::exec::posix_spawn(argv, &envp, Some(&config)).map_err(|err| {
+            match err.raw_os_error() {
+                Some(libc::ENOMEM | libc::EAGAIN) => ForkError::ForkFailed(err),
+                _ => ForkError::ExecFailed(err),
+            }
+        })?;
+        self.attach_stdio(&mut child, parents);
+        Ok(child)
+    }
+
+    /// Whether the configuration can be applied by `posix_spawn`, see [`SpawnBackend::PosixSpawn`].
+    fn posix_spawn_supported(&self) -> bool {
+        let supported = self.chroot.is_none()
+            && self.umask.is_none()
+            && !self.core_dumps
+            && !self.sanitize
+            && self.rlimits.is_empty()
+            && self.cpu_limit.is_none()
+            && self.memory_limit.is_none()
+            && self.nice.is_none()
+            && self.uid.is_none()
+            && self.gid.is_none()
+            && self.groups.is_none()
+            && self.keep_fds.is_none()
+            && self.fd_mappings.is_empty();
+        #[cfg(target_os = "linux")]
+        let supported = supported
+            && self.dumpable.is_none()
+            && self.name.is_none()
+            && self.clone_flags == CloneFlags::empty()
+            && self.pdeathsig.is_none()
+            && self.oom_score_adj.is_none()
+            && self.cgroup.is_none()
+            && self.id_maps.is_empty()
+            && self.mounts.is_empty()
+            && !self.loopback_up
+            && self.hostname.is_none()
+            && self.time_offsets.is_none()
+            && !self.no_new_privs
+            && self.drop_caps.is_empty()
+            && self.sched_policy.is_none()
+            && self.cpu_affinity.is_none()
+            && self.io_priority.is_none()
+            && self.trace.is_none();
+        #[cfg(not(target_os = "linux"))]
+        let supported = supported && self.cwd.is_none() && !self.session;
+        #[cfg(all(target_os = "linux", feature = "landlock"))]
+        let supported = supported && self.landlock.is_empty();
+        #[cfg(all(target_os = "linux", feature = "seccomp"))]
+        let supported = supported && self.seccomp.is_none();
+        supported
+    }
+
+    /// Prepare the standard I/O streams of the child.
+    ///
+    /// Returns the file descriptors to be installed in the child, and the parent ends of the pipes.
+    #[allow(clippy::type_complexity)]
+    fn prepare_stdio(&self) -> Result<([Option<OwnedFd>; 3], [Option<File>; 3])> {
+        let (stdin, stdin_parent) = self.stdin.prepare(true)?;
+        let (stdout, stdout_parent) = self.stdout.prepare(false)?;
+        let (stderr, stderr_parent) = if self.stderr_to_stdout {
+            (None, None)
+        } else {
+            self.stderr.prepare(false)?
+        };
+        Ok((
+            [stdin, stdout, stderr],
+            [stdin_parent, stdout_parent, stderr_parent],
+        ))
+    }
+
+    /// Move a file descriptor that the crate uses in the child out of the way of the mappings, so
+    /// that installing them does not overwrite it.
+    pub(crate) fn shelter<F: From<OwnedFd> + Into<OwnedFd>>(&self, fd: F) -> Result<F> {
+        match self.fd_mappings.iter().map(|&(_, target)| target).max() {
+            Some(max) => {
+                let fd: OwnedFd = fd.into();
+                Ok(dup_above(fd.as_fd(), max + 1)?.into())
+            }
+            None => Ok(fd),
+        }
+    }
+
+    /// Hand the parent ends of the pipes over to the child handle.
+    fn attach_stdio(&self, child: &mut Child, parents: [Option<File>; 3]) {
+        let [stdin, stdout, stderr] = parents;
+        child.stdin = stdin.map(ChildStdin);
+        child.stdout = stdout.map(ChildStdout);
+        child.stderr = stderr.map(ChildStderr);
+        if self.process_group || self.session {
+            child.tree = Some(Tree::ProcessGroup);
+        }
+        child.cpu_limit = self.cpu_limit;
+    }
+
+    /// Fork the current process, and run `f` after setup within the configured child process.
+    ///
+    /// `f` receives the write end of the error report pipe. The parent waits until it is closed,
+    /// and treats any error reported into it as a failure of the child to start. The `internal`
+    /// file descriptors, used by `f`, are kept open if other file descriptors are closed, while
+    /// `close_parent_ends` runs in the child before setup. `exec` tells whether `f` only executes a
+    /// program.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn spawn_with(
+        &self,
+        exec: bool,
+        internal: Vec<RawFd>,
+        close_parent_ends: impl FnOnce(),
+        f: impl FnOnce(File) -> i32,
+    ) -> std::result::Result<Child, ForkError> {
+        let (stdio, parents) = self.prepare_stdio()?;
+        let (mut reader, writer) = crate::pipe()?;
+        let mut writer = self.shelter(writer)?;
+        #[cfg(target_os = "linux")]
+        let cgroup = match &self.cgroup {
+            None => None,
+            Some(Cgroup::Path(path)) => Some(OwnedFd::from(File::open(path)?)),
+            Some(Cgroup::Fd(fd)) => Some(fd.try_clone()?),
+        };
+        #[cfg(target_os = "linux")]
+        let oom_kills = match (&cgroup, self.memory_limit) {
+            (Some(cgroup), Some(bytes)) => {
+                crate::cgroup::set_memory_max(cgroup.as_fd(), bytes)?;
+                Some(crate::cgroup::oom_kills(cgroup.as_fd())?)
+            }
+            _ => None,
+        };
+        #[cfg(target_os = "linux")]
+        let (parent_setup_done, mut parent_setup_writer) =
+            match self.id_maps.is_empty() && cgroup.is_none() && self.trace.is_none() {
+                true => (None, None),
+                false => {
+                    let (reader, writer) = crate::pipe()?;
+                    (Some(reader), Some(writer))
+                }
+            };
+        let prepared = Prepared {
+            stdio,
+            #[cfg(target_os = "linux")]
+            parent: std::process::id() as libc::pid_t,
+            #[cfg(target_os = "linux")]
+            parent_setup_done,
+            report: writer.as_raw_fd(),
+            internal,
+        };
+
+        #[cfg(target_os = "linux")]
+        let (child, in_cgroup) = if self.clone_flags == CloneFlags::empty() && cgroup.is_none() {
+            (crate::fork()?.into_parent(), false)
+        } else {
+            let cgroup = cgroup.as_ref().map(|fd| fd.as_fd());
+            crate::clone::fork_with_flags(self.clone_flags, cgroup, exec)?
+        };
+        #[cfg(not(target_os = "linux"))]
+        let child = crate::fork()?.into_parent();
+        let Some(mut child) = child else {
+            drop((reader, parents));
+            close_parent_ends();
+            #[cfg(target_os = "linux")]
+            drop((parent_setup_writer, cgroup));
+            if let Err(err) = self.setup(prepared) {
+                report(&mut writer, Stage::Setup, err);
+            }
+            std::process::exit(f(writer));
+        };
+
+        // The child closes its write end once setup is complete, so this either receives an
+        // error report or hits EOF.
+        drop((writer, prepared));
+        #[cfg(target_os = "linux")]
+        if let Some(mut parent_setup_writer) = parent_setup_writer.take() {
+            // The child waits for the parent before any other setup, so it is killed on failure.
+            let result = (|| {
+                if let Some(syscalls) = self.trace {
+                    child.trace = Some(crate::ptrace::Trace::seize(child.pid() as _, syscalls)?);
+                }
+                if !self.id_maps.is_empty() {
+                    self.id_maps.write(child.pid())?;
+                }
+                if let (Some(cgroup), false) = (&cgroup, in_cgroup) {
+                    crate::cgroup::move_into(cgroup.as_fd(), child.pid())?;
+                }
+                parent_setup_writer.write_all(&[0])
+            })();
+            if let Err(err) = result {
+                trace!(debug, pid = child.pid(), error = %err, "failed to set up child");
+                let _ = child.kill();
+                child.join().map_err(ForkError::from_wait)?;
+                return Err(ForkError::SetupFailed(err));
+            }
+        }
+        let mut buf = [0; 5];
+        match reader.read_exact(&mut buf) {
+            Ok(()) => {
+                let err =
+                    Error::from_raw_os_error(i32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]));
+                trace!(
+                    debug,
+                    pid = child.pid(),
+                    error = %err,
+                    exec = buf[4] == STAGE_EXEC,
+                    "child failed to start"
+                );
+                child.join().map_err(ForkError::from_wait)?;
+                return Err(match buf[4] {
+                    STAGE_EXEC => ForkError::ExecFailed(err),
+                    _ => ForkError::SetupFailed(err),
+                });
+            }
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => (),
+            Err(err) => return Err(ForkError::Io(err)),
+        }
+
+        self.attach_stdio(&mut child, parents);
+        #[cfg(target_os = "linux")]
+        if let Some(trace) = &child.trace {
+            trace.start(child.pid() as _)?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(cgroup) = cgroup {
+            if let Some(before) = oom_kills {
+                let dir = cgroup.try_clone()?;
+                child.memory = Some(crate::memory::MemoryWatch::Cgroup { dir, before });
+            }
+            child.tree = Some(Tree::Cgroup(cgroup));
+        }
+        trace!(debug, pid = child.pid(), "set up child");
+        Ok(child)
+    }
+
+    /// Perform the setup steps within the child process.
+    fn setup(&self, prepared: Prepared) -> Result<()> {
+        self.scrub.run();
+
+        #[cfg(target_os = "linux")]
+        if let Some(mut parent_setup_done) = prepared.parent_setup_done {
+            parent_setup_done.read_exact(&mut [0])?;
+        }
+
+        if self.reset_signal_handlers {
+            crate::signal::reset_handlers();
+        }
+        if let Some(mask) = &self.signal_mask {
+            mask.set_mask()?;
+        }
+        if self.sanitize {
+            // SAFETY: restoring the default disposition is always sound.
+            unsafe { libc::signal(libc::SIGPIPE, libc::SIG_DFL) };
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(offsets) = &self.time_offsets {
+            offsets.apply()?;
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(score) = self.oom_score_adj {
+            std::fs::write("/proc/self/oom_score_adj", score.to_string())?;
+        }
+
+        #[cfg(target_os = "linux")]
+        self.mounts.apply()?;
+
+        if let Some(root) = &self.chroot {
+            std::os::unix::fs::chroot(root)?;
+            std::env::set_current_dir("/")?;
+        }
+
+        #[cfg(target_os = "linux")]
+        if self.loopback_up {
+            loopback_up()?;
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(name) = &self.hostname {
+            use std::os::unix::ffi::OsStrExt;
+
+            let name = name.as_bytes();
+            // SAFETY: `name` is valid for the duration of the call.
+            if unsafe { libc::sethostname(name.as_ptr().cast(), name.len()) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(name) = &self.name {
+            use std::os::unix::ffi::OsStrExt;
+
+            let name = name.as_bytes();
+            if name.contains(&0) {
+                return Err(ErrorKind::InvalidInput.into());
+            }
+            let mut buf = [0u8; 16];
+            let len = name.len().min(buf.len() - 1);
+            buf[..len].copy_from_slice(&name[..len]);
+            // SAFETY: `buf` is a NUL-terminated string valid for the duration of the call.
+            if unsafe { libc::prctl(libc::PR_SET_NAME, buf.as_ptr()) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(signal) = self.pdeathsig {
+            // SAFETY: `prctl` with `PR_SET_PDEATHSIG` does not have special safety requirements.
+            if unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, signal as libc::c_ulong) } < 0 {
+                return Err(Error::last_os_error());
+            }
+            // The parent may have died before the `prctl` call, in which case no signal would be
+            // delivered. In a new PID namespace, the parent PID always reads as 0.
+            // SAFETY: `getppid` and `raise` do not have special safety requirements.
+            unsafe {
+                let ppid = libc::getppid();
+                if ppid != 0 && ppid != prepared.parent {
+                    libc::raise(signal);
+                }
+            }
+        }
+
+        // A session leader is also the leader of a new process group, and cannot call `setpgid`.
+        let ret = if self.session {
+            // SAFETY: `setsid` does not have special safety requirements.
+            unsafe { libc::setsid() }
+        } else if self.process_group {
+            // SAFETY: `setpgid` does not have special safety requirements.
+            unsafe { libc::setpgid(0, 0) }
+        } else {
+            0
+        };
+        if ret < 0 {
+            return Err(Error::last_os_error());
+        }
+
+        if let Some(mask) = self.umask {
+            // SAFETY: `umask` does not have special safety requirements.
+            unsafe { libc::umask(mask) };
+        }
+
+        if let Some(cwd) = &self.cwd {
+            std::env::set_current_dir(cwd)?;
+        }
+
+        for (fd, target) in prepared.stdio.into_iter().zip(0..) {
+            if let Some(fd) = fd {
+                install_fd(fd, target)?;
+            }
+        }
+        // SAFETY: `dup2` does not have special safety requirements.
+        if self.stderr_to_stdout && unsafe { libc::dup2(1, 2) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        install_mappings(&self.fd_mappings)?;
+
+        if let Some(keep) = &self.keep_fds {
+            let mut keep = keep.clone();
+            keep.extend([0, 1, 2, prepared.report]);
+            keep.extend(&prepared.internal);
+            keep.extend(self.fd_mappings.iter().map(|&(_, target)| target));
+            close_fds_except(&mut keep)?;
+        }
+
+        #[cfg(target_os = "linux")]
+        if self.sanitize {
+            close_event_fds()?;
+        }
+
+        if self.core_dumps {
+            raise_core_limit()?;
+        }
+        self.rlimits.apply()?;
+        if let Some(limit) = self.cpu_limit {
+            let seconds = (limit.as_secs() + (limit.subsec_nanos() > 0) as u64).max(1);
+            Rlimits::new()
+                .set(Resource::Cpu, seconds, seconds + 1)
+                .apply()?;
+        }
+        if let Some(bytes) = self.address_space_limit() {
+            Rlimits::new().address_space(bytes).apply()?;
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(cpus) = &self.cpu_affinity {
+            crate::sched::set_affinity(cpus)?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(policy) = self.sched_policy {
+            policy.apply()?;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some((class, level)) = self.io_priority {
+            crate::sched::set_io_priority(class, level)?;
+        }
+        if let Some(value) = self.nice {
+            // SAFETY: `setpriority` does not have special safety requirements.
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS as _, 0, value) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        if self.env_clear {
+            for (key, _) in std::env::vars_os() {
+                std::env::remove_var(key);
+            }
+        }
+        for (key, val) in &self.env {
+            match val {
+                Some(val) => std::env::set_var(key, val),
+                None => std::env::remove_var(key),
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        self.drop_caps.drop_bounding()?;
+
+        self.drop_privileges()?;
+
+        #[cfg(target_os = "linux")]
+        {
+            self.drop_caps.drop_sets()?;
+            // SAFETY: `PR_SET_NO_NEW_PRIVS` does not have special safety requirements.
+            if self.no_new_privs
+                && unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } < 0
+            {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(dumpable) = self.dumpable {
+            // SAFETY: `PR_SET_DUMPABLE` does not have special safety requirements.
+            if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, dumpable as libc::c_ulong) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        #[cfg(all(target_os = "linux", feature = "landlock"))]
+        self.landlock.apply()?;
+
+        #[cfg(all(target_os = "linux", feature = "seccomp"))]
+        if let Some(filter) = &self.seccomp {
+            filter.install()?;
+        }
+
+        Ok(())
+    }
+
+    /// Change the credentials of the child process.
+    ///
+    /// Supplementary groups and the group ID must be changed before the user ID, as the privilege
+    /// to do so is lost afterwards.
+    fn drop_privileges(&self) -> Result<()> {
+        // SAFETY: `getuid` does not have special safety requirements.
+        let groups = match &self.groups {
+            Some(groups) => Some(groups.as_slice()),
+            None if self.uid.is_some() && unsafe { libc::getuid() } == 0 => Some(&[][..]),
+            None => None,
+        };
+        if let Some(groups) = groups {
+            // SAFETY: `groups` is valid for the duration of the call.
+            if unsafe { libc::setgroups(groups.len() as _, groups.as_ptr()) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        if let Some(gid) = self.gid {
+            // SAFETY: `setgid` does not have special safety requirements.
+            if unsafe { libc::setgid(gid) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        if let Some(uid) = self.uid {
+            // SAFETY: `setuid` does not have special safety requirements.
+            if unsafe { libc::setuid(uid) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Stage at which the child fails to start, as reported to the parent.
+#[derive(Clone, Copy)]
+#[repr(u8)]
+enum Stage {
+    Setup,
+    Exec,
+}
+
+const STAGE_EXEC: u8 = Stage::Exec as u8;
+
+/// Report an error that prevents the child from starting to the parent, and exit.
+fn report(writer: &mut File, stage: Stage, err: Error) -> ! {
+    let errno = err.raw_os_error().unwrap_or(libc::EINVAL);
+    let mut buf = [0; 5];
+    buf[..4].copy_from_slice(&errno.to_ne_bytes());
+    buf[4] = stage as u8;
+    let _ = writer.write_all(&buf);
+    // SAFETY: `_exit` does not have special safety requirements.
+    unsafe { libc::_exit(127) };
+}
+
+/// Raise the soft limit of core dump sizes to the hard limit.
+fn raise_core_limit() -> Result<()> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is valid for the duration of the calls.
+    unsafe {
+        if libc::getrlimit(libc::RLIMIT_CORE, &mut limit) < 0 {
+            return Err(Error::last_os_error());
+        }
+        limit.rlim_cur = limit.rlim_max;
+        if libc::setrlimit(libc::RLIMIT_CORE, &limit) < 0 {
+            return Err(Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Bring up the loopback interface of the current network namespace.
+#[cfg(target_os = "linux")]
+fn loopback_up() -> Result<()> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: `socket` does not have special safety requirements.
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: the file descriptor is freshly created and exclusively owned.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: all-zero is a valid `ifreq`.
+    let mut req: libc::ifreq = unsafe { std::mem::zeroed() };
+    for (dst, &src) in req.ifr_name.iter_mut().zip(b"lo") {
+        *dst = src as _;
+    }
+    // SAFETY: `req` is valid for the duration of the calls, and `ifru_flags` is the member used by
+    // `SIOCGIFFLAGS` and `SIOCSIFFLAGS`.
+    unsafe {
+        if libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS as _, &mut req) < 0 {
+            return Err(Error::last_os_error());
+        }
+        req.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        if libc::ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS as _, &req) < 0 {
+            return Err(Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Install the file descriptor as `target`, without the close-on-exec flag.
+fn install_fd(fd: OwnedFd, target: RawFd) -> Result<()> {
+    if fd.as_raw_fd() == target {
+        // `dup2` would be a no-op, so clear the close-on-exec flag manually.
+        let fd = fd.into_raw_fd();
+        // SAFETY: `fcntl` does not have special safety requirements.
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, 0) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        return Ok(());
+    }
+
+    // SAFETY: `dup2` does not have special safety requirements.
+    if unsafe { libc::dup2(fd.as_raw_fd(), target) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Duplicate `fd` to the lowest available file descriptor that is at least `min`.
+///
+/// The new file descriptor is close-on-exec.
+fn dup_above(fd: BorrowedFd<'_>, min: RawFd) -> Result<OwnedFd> {
+    // SAFETY: `fcntl` with `F_DUPFD_CLOEXEC` does not have special safety requirements.
+    let fd = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_DUPFD_CLOEXEC, min) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: `fd` is a newly created file descriptor owned by nobody else.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Install the parent file descriptors of the mappings as their targets.
+fn install_mappings(mappings: &[(RawFd, RawFd)]) -> Result<()> {
+    let Some(max) = mappings.iter().map(|&(_, target)| target).max() else {
+        return Ok(());
+    };
+    // Move all sources above the targets first, so that no source is overwritten by installing
+    // an earlier mapping.
+    let sources = mappings
+        .iter()
+        .map(|&(fd, _)| {
+            // SAFETY: the file descriptor is only borrowed for the duration of the call, and a
+            // closed one is reported as an error.
+            dup_above(unsafe { BorrowedFd::borrow_raw(fd) }, max.max(2) + 1)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for (fd, &(_, target)) in sources.into_iter().zip(mappings) {
+        install_fd(fd, target)?;
+    }
+    Ok(())
+}
+
+/// Close all file descriptors not in `keep`.
+///
+/// On Linux, `close_range` is used if available, otherwise falls back to enumerating
+/// `/proc/self/fd`. Other platforms enumerate `/dev/fd`.
+fn close_fds_except(keep: &mut Vec<RawFd>) -> Result<()> {
+    keep.retain(|&fd| fd >= 0);
+    keep.sort_unstable();
+    keep.dedup();
+
+    #[cfg(target_os = "linux")]
+    match close_gaps(keep) {
+        Err(err) if err.raw_os_error() == Some(libc::ENOSYS) => (),
+        result => return result,
+    }
+
+    let dir = if cfg!(target_os = "linux") {
+        "/proc/self/fd"
+    } else {
+        "/dev/fd"
+    };
+    // Collect first, as the directory being iterated holds a file descriptor.
+    let fds = std::fs::read_dir(dir)?
+        .map(|entry| {
+            let name = entry?.file_name();
+            name.to_str()
+                .and_then(|name| name.parse::<RawFd>().ok())
+                .ok_or_else(|| Error::from(ErrorKind::InvalidData))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    for fd in fds {
+        if keep.binary_search(&fd).is_err() {
+            // SAFETY: `close` does not have special safety requirements. The file descriptor
+            // closed is not used afterwards. The descriptor of the directory is already closed,
+            // in which case this fails harmlessly.
+            unsafe { libc::close(fd) };
+        }
+    }
+    Ok(())
+}
+
+/// Close the inherited file descriptors that refer to event sources shared with the parent.
+#[cfg(target_os = "linux")]
+fn close_event_fds() -> Result<()> {
+    const SHARED: &[&str] = &[
+        "anon_inode:[eventpoll]",
+        "anon_inode:[io_uring]",
+        "anon_inode:[timerfd]",
+        "anon_inode:[signalfd]",
+        "anon_inode:[eventfd]",
+        "anon_inode:inotify",
+    ];
+
+    // Collect first, as the directory being iterated holds a file descriptor.
+    let entries = std::fs::read_dir("/proc/self/fd")?.collect::<Result<Vec<_>>>()?;
+    for entry in entries {
+        let Some(fd) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        else {
+            continue;
+        };
+        // The descriptor of the directory is already closed, so it fails to resolve.
+        let Ok(target) = std::fs::read_link(entry.path()) else {
+            continue;
+        };
+        if SHARED.iter().any(|shared| target == Path::new(shared)) {
+            // SAFETY: `close` does not have special safety requirements. The file descriptor
+            // closed is not used afterwards.
+            unsafe { libc::close(fd) };
+        }
+    }
+    Ok(())
+}
+
+/// Close the ranges of file descriptors between the sorted file descriptors in `keep`.
+#[cfg(target_os = "linux")]
+fn close_gaps(keep: &[RawFd]) -> Result<()> {
+    let close_range = |first: libc::c_uint, last: libc::c_uint| {
+        // SAFETY: `close_range` does not have special safety requirements. The file descriptors
+        // closed are not used afterwards.
+        if unsafe { libc::syscall(libc::SYS_close_range, first, last, 0) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    };
+
+    let mut first = 0;
+    for &fd in keep {
+        let fd = fd as libc::c_uint;
+        if first < fd {
+            close_range(first, fd - 1)?;
+        }
+        first = fd + 1;
+    }
+    close_range(first, libc::c_uint::MAX)
+}
@@ -0,0 +1,206 @@
+use std::io::{Error, Result};
+
+/// A Linux capability.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capability(u32);
+
+impl Capability {
+    /// `CAP_CHOWN`.
+    pub const CHOWN: Self = Self(0);
+    /// `CAP_DAC_OVERRIDE`.
+    pub const DAC_OVERRIDE: Self = Self(1);
+    /// `CAP_DAC_READ_SEARCH`.
+    pub const DAC_READ_SEARCH: Self = Self(2);
+    /// `CAP_FOWNER`.
+    pub const FOWNER: Self = Self(3);
+    /// `CAP_FSETID`.
+    pub const FSETID: Self = Self(4);
+    /// `CAP_KILL`.
+    pub const KILL: Self = Self(5);
+    /// `CAP_SETGID`.
+    pub const SETGID: Self = Self(6);
+    /// `CAP_SETUID`.
+    pub const SETUID: Self = Self(7);
+    /// `CAP_SETPCAP`.
+    pub const SETPCAP: Self = Self(8);
+    /// `CAP_LINUX_IMMUTABLE`.
+    pub const LINUX_IMMUTABLE: Self = Self(9);
+    /// `CAP_NET_BIND_SERVICE`.
+    pub const NET_BIND_SERVICE: Self = Self(10);
+    /// `CAP_NET_BROADCAST`.
+    pub const NET_BROADCAST: Self = Self(11);
+    /// `CAP_NET_ADMIN`.
+    pub const NET_ADMIN: Self = Self(12);
+    /// `CAP_NET_RAW`.
+    pub const NET_RAW: Self = Self(13);
+    /// `CAP_IPC_LOCK`.
+    pub const IPC_LOCK: Self = Self(14);
+    /// `CAP_IPC_OWNER`.
+    pub const IPC_OWNER: Self = Self(15);
+    /// `CAP_SYS_MODULE`.
+    pub const SYS_MODULE: Self = Self(16);
+    /// `CAP_SYS_RAWIO`.
+    pub const SYS_RAWIO: Self = Self(17);
+    /// `CAP_SYS_CHROOT`.
+    pub const SYS_CHROOT: Self = Self(18);
+    /// `CAP_SYS_PTRACE`.
+    pub const SYS_PTRACE: Self = Self(19);
+    /// `CAP_SYS_PACCT`.
+    pub const SYS_PACCT: Self = Self(20);
+    /// `CAP_SYS_ADMIN`.
+    pub const SYS_ADMIN: Self = Self(21);
+    /// `CAP_SYS_BOOT`.
+    pub const SYS_BOOT: Self = Self(22);
+    /// `CAP_SYS_NICE`.
+    pub const SYS_NICE: Self = Self(23);
+    /// `CAP_SYS_RESOURCE`.
+    pub const SYS_RESOURCE: Self = Self(24);
+    /// `CAP_SYS_TIME`.
+    pub const SYS_TIME: Self = Self(25);
+    /// `CAP_SYS_TTY_CONFIG`.
+    pub const SYS_TTY_CONFIG: Self = Self(26);
+    /// `CAP_MKNOD`.
+    pub const MKNOD: Self = Self(27);
+    /// `CAP_LEASE`.
+    pub const LEASE: Self = Self(28);
+    /// `CAP_AUDIT_WRITE`.
+    pub const AUDIT_WRITE: Self = Self(29);
+    /// `CAP_AUDIT_CONTROL`.
+    pub const AUDIT_CONTROL: Self = Self(30);
+    /// `CAP_SETFCAP`.
+    pub const SETFCAP: Self = Self(31);
+    /// `CAP_MAC_OVERRIDE`.
+    pub const MAC_OVERRIDE: Self = Self(32);
+    /// `CAP_MAC_ADMIN`.
+    pub const MAC_ADMIN: Self = Self(33);
+    /// `CAP_SYSLOG`.
+    pub const SYSLOG: Self = Self(34);
+    /// `CAP_WAKE_ALARM`.
+    pub const WAKE_ALARM: Self = Self(35);
+    /// `CAP_BLOCK_SUSPEND`.
+    pub const BLOCK_SUSPEND: Self = Self(36);
+    /// `CAP_AUDIT_READ`.
+    pub const AUDIT_READ: Self = Self(37);
+    /// `CAP_PERFMON`.
+    pub const PERFMON: Self = Self(38);
+    /// `CAP_BPF`.
+    pub const BPF: Self = Self(39);
+    /// `CAP_CHECKPOINT_RESTORE`.
+    pub const CHECKPOINT_RESTORE: Self = Self(40);
+
+    /// Creates a capability from its number, for capabilities not named here.
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    /// Returns the number of the capability.
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+}
+
+/// Capabilities to drop in the child process.
+#[derive(Debug, Default)]
+pub(crate) struct CapabilityDrop {
+    pub(crate) all: bool,
+    pub(crate) caps: Vec<Capability>,
+}
+
+impl CapabilityDrop {
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.all && self.caps.is_empty()
+    }
+
+    /// The capabilities to drop.
+    fn caps(&self) -> Vec<Capability> {
+        if !self.all {
+            return self.caps.clone();
+        }
+        // Capabilities unknown to the kernel cannot be read from the bounding set.
+        (0..64)
+            .map(Capability)
+            // SAFETY: `PR_CAPBSET_READ` does not have special safety requirements.
+            .take_while(
+                |cap| unsafe { libc::prctl(libc::PR_CAPBSET_READ, cap.0 as libc::c_ulong) } >= 0,
+            )
+            .collect()
+    }
+
+    /// Drop the capabilities from the bounding and ambient sets.
+    ///
+    /// This must happen before changing the user ID, as `CAP_SETPCAP` is needed.
+    pub(crate) fn drop_bounding(&self) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        for cap in self.caps() {
+            let cap = cap.0 as libc::c_ulong;
+            // SAFETY: `PR_CAP_AMBIENT` and `PR_CAPBSET_*` do not have special safety requirements.
+            unsafe {
+                // Lowering an ambient capability only fails if ambient capabilities are not
+                // supported, in which case there is nothing to lower.
+                libc::prctl(
+                    libc::PR_CAP_AMBIENT,
+                    libc::PR_CAP_AMBIENT_LOWER as libc::c_ulong,
+                    cap,
+                    0,
+                    0,
+                );
+                if libc::prctl(libc::PR_CAPBSET_READ, cap) > 0
+                    && libc::prctl(libc::PR_CAPBSET_DROP, cap) < 0
+                {
+                    return Err(Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the capabilities from the effective, permitted and inheritable sets.
+    pub(crate) fn drop_sets(&self) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut header = CapHeader {
+            version: LINUX_CAPABILITY_VERSION_3,
+            pid: 0,
+        };
+        let mut data = [CapData::default(); 2];
+        // SAFETY: `header` and `data` are valid for the duration of the call.
+        if unsafe { libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        for cap in self.caps() {
+            let Some(data) = data.get_mut(cap.0 as usize / 32) else {
+                continue;
+            };
+            let mask = !(1 << (cap.0 % 32));
+            data.effective &= mask;
+            data.permitted &= mask;
+            data.inheritable &= mask;
+        }
+        // SAFETY: `header` and `data` are valid for the duration of the call.
+        if unsafe { libc::syscall(libc::SYS_capset, &header, data.as_ptr()) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// `struct __user_cap_header_struct`.
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+/// `struct __user_cap_data_struct`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
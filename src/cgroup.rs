@@ -0,0 +1,68 @@
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::{Error, Read, Result, Write};
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd};
+
+/// Open a file within the cgroup directory.
+fn open(dir: BorrowedFd<'_>, name: &CStr, flags: libc::c_int) -> Result<File> {
+    // SAFETY: `name` is a valid C string.
+    let fd = unsafe { libc::openat(dir.as_raw_fd(), name.as_ptr(), flags | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: the file descriptor is freshly created and exclusively owned.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Move the process into the cgroup.
+pub(crate) fn move_into(dir: BorrowedFd<'_>, pid: u32) -> Result<()> {
+    open(dir, c"cgroup.procs", libc::O_WRONLY)?.write_all(pid.to_string().as_bytes())
+}
+
+/// Set the hard memory limit of the cgroup, with `memory.max`, and disable swapping so that the
+/// limit cannot be evaded.
+pub(crate) fn set_memory_max(dir: BorrowedFd<'_>, bytes: u64) -> Result<()> {
+    open(dir, c"memory.max", libc::O_WRONLY)?.write_all(bytes.to_string().as_bytes())?;
+    match open(dir, c"memory.swap.max", libc::O_WRONLY) {
+        // Swap accounting may be disabled.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result?.write_all(b"0"),
+    }
+}
+
+/// Number of processes in the cgroup and its descendants killed by the OOM killer, as reported in
+/// `memory.events`.
+pub(crate) fn oom_kills(dir: BorrowedFd<'_>) -> Result<u64> {
+    let mut events = String::new();
+    open(dir, c"memory.events", libc::O_RDONLY)?.read_to_string(&mut events)?;
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| Error::from(std::io::ErrorKind::InvalidData))
+}
+
+/// Kill all processes in the cgroup and its descendants.
+///
+/// `cgroup.kill` is used if available. Otherwise, the processes directly in the cgroup are killed
+/// one by one, which may miss the ones forked in the meantime.
+pub(crate) fn kill(dir: BorrowedFd<'_>) -> Result<()> {
+    match open(dir, c"cgroup.kill", libc::O_WRONLY) {
+        // `cgroup.kill` is only supported since Linux 5.14.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+        result => return result?.write_all(b"1"),
+    }
+
+    let mut procs = String::new();
+    open(dir, c"cgroup.procs", libc::O_RDONLY)?.read_to_string(&mut procs)?;
+    for pid in procs.lines() {
+        let pid = pid
+            .parse()
+            .map_err(|_| Error::from(std::io::ErrorKind::InvalidData))?;
+        match crate::sys::kill(pid, libc::SIGKILL) {
+            Err(err) if err.raw_os_error() == Some(libc::ESRCH) => (),
+            result => result?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,359 @@
+use std::io::{Error, ErrorKind, Read, Result};
+use std::marker::PhantomData;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::time::Instant;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Bincode, Child, Codec, ForkResult};
+
+/// Sending half of a channel between parent and child.
+///
+/// Values are serialized with the codec `C` and sent as length-prefixed frames. If the other
+/// process has closed its end of the channel, sending fails with [`ErrorKind::BrokenPipe`] instead
+/// of raising `SIGPIPE`.
+#[derive(Debug)]
+pub struct Sender<T, C = Bincode> {
+    stream: UnixStream,
+    _marker: PhantomData<fn(T) -> C>,
+}
+
+impl<T: Serialize, C: Codec> Sender<T, C> {
+    /// Sends a value to the other process.
+    pub fn send(&self, value: &T) -> Result<()> {
+        let payload = C::encode(value)?;
+        let mut frame = Vec::with_capacity(8 + payload.len());
+        frame.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        frame.extend_from_slice(&payload);
+
+        let mut buf = &frame[..];
+        while !buf.is_empty() {
+            // SAFETY: `buf` is valid for reads for the duration of the call.
+            let len = unsafe {
+                libc::send(
+                    self.stream.as_raw_fd(),
+                    buf.as_ptr() as *const libc::c_void,
+                    buf.len(),
+                    SEND_FLAGS,
+                )
+            };
+            if len < 0 {
+                let err = Error::last_os_error();
+                if err.kind() != ErrorKind::Interrupted {
+                    return Err(err);
+                }
+                continue;
+            }
+            buf = &buf[len as usize..];
+        }
+        Ok(())
+    }
+}
+
+impl<T, C> AsFd for Sender<T, C> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+
+impl<T, C> Sender<T, C> {
+    /// Replaces the socket of the sender with the one returned by `f`.
+    pub(crate) fn map_stream(
+        self,
+        f: impl FnOnce(UnixStream) -> Result<UnixStream>,
+    ) -> Result<Self> {
+        Ok(Self {
+            stream: f(self.stream)?,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Sends a file descriptor to the other process with `SCM_RIGHTS`.
+    ///
+    /// File descriptors and values must be received in the same order that they are sent.
+    pub fn send_fd(&self, fd: OwnedFd) -> Result<()> {
+        let mut byte = 0u8;
+        let mut iov = libc::iovec {
+            iov_base: &mut byte as *mut u8 as *mut libc::c_void,
+            iov_len: 1,
+        };
+        let mut cmsg_buf = CmsgBuffer([0; CMSG_SPACE]);
+        let msg = cmsg_buf.msghdr(&mut iov);
+
+        // SAFETY: `msg_control` points to a buffer large enough for one file descriptor.
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<libc::c_int>() as _) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut libc::c_int, fd.as_raw_fd());
+        }
+
+        loop {
+            // SAFETY: `msg` and the buffers it points to are valid for the duration of the call.
+            if unsafe { libc::sendmsg(self.stream.as_raw_fd(), &msg, SEND_FLAGS) } >= 0 {
+                // The file descriptor is duplicated into the other process, ours can be closed.
+                return Ok(());
+            }
+            let err = Error::last_os_error();
+            if err.kind() != ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Receiving half of a channel between parent and child.
+#[derive(Debug)]
+pub struct Receiver<T, C = Bincode> {
+    stream: UnixStream,
+    _marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: DeserializeOwned, C: Codec> Receiver<T, C> {
+    /// Receives a value from the other process, blocking until one is available.
+    ///
+    /// If the other process has closed its end of the channel, an error of kind
+    /// [`ErrorKind::UnexpectedEof`] is returned.
+    pub fn recv(&self) -> Result<T> {
+        read_frame::<T, C>(&self.stream)
+    }
+
+    /// Receives a value like [`recv`](Self::recv), but fails with [`ErrorKind::TimedOut`] if the
+    /// whole value has not arrived by `deadline`.
+    pub(crate) fn recv_deadline(&self, deadline: Instant) -> Result<T> {
+        let result = read_frame::<T, C>(DeadlineReader {
+            stream: &self.stream,
+            deadline,
+        });
+        self.stream.set_read_timeout(None)?;
+        result
+    }
+}
+
+/// Read a length-prefixed frame, and deserialize the value in it.
+fn read_frame<T: DeserializeOwned, C: Codec>(mut reader: impl Read) -> Result<T> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+
+    // Read incrementally instead of trusting the length to pre-allocate.
+    let mut payload = Vec::new();
+    reader.take(len).read_to_end(&mut payload)?;
+    if payload.len() as u64 != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    C::decode(&payload)
+}
+
+/// Reader of a socket that fails with [`ErrorKind::TimedOut`] once the deadline has passed.
+///
+/// The remaining time is set as the receive timeout of the socket before each read, so that a
+/// frame arriving in many pieces cannot extend the deadline.
+struct DeadlineReader<'a> {
+    stream: &'a UnixStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::new(ErrorKind::TimedOut, "receive timed out"));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        match self.stream.read(buf) {
+            // The receive timeout elapsed.
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                Err(Error::new(ErrorKind::TimedOut, "receive timed out"))
+            }
+            result => result,
+        }
+    }
+}
+
+impl<T, C> AsFd for Receiver<T, C> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+
+impl<T, C> Receiver<T, C> {
+    /// Replaces the socket of the receiver with the one returned by `f`.
+    pub(crate) fn map_stream(
+        self,
+        f: impl FnOnce(UnixStream) -> Result<UnixStream>,
+    ) -> Result<Self> {
+        Ok(Self {
+            stream: f(self.stream)?,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Receives a file descriptor sent with [`Sender::send_fd`] from the other process.
+    pub fn recv_fd(&self) -> Result<OwnedFd> {
+        let mut byte = 0u8;
+        let mut iov = libc::iovec {
+            iov_base: &mut byte as *mut u8 as *mut libc::c_void,
+            iov_len: 1,
+        };
+        let mut cmsg_buf = CmsgBuffer([0; CMSG_SPACE]);
+        let mut msg = cmsg_buf.msghdr(&mut iov);
+
+        let len = loop {
+            // SAFETY: `msg` and the buffers it points to are valid for the duration of the call.
+            let len = unsafe { libc::recvmsg(self.stream.as_raw_fd(), &mut msg, RECV_FLAGS) };
+            if len >= 0 {
+                break len;
+            }
+            let err = Error::last_os_error();
+            if err.kind() != ErrorKind::Interrupted {
+                return Err(err);
+            }
+        };
+        if len == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+
+        // SAFETY: the kernel has filled in the control buffer.
+        unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            if cmsg.is_null()
+                || (*cmsg).cmsg_level != libc::SOL_SOCKET
+                || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+            {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "expected a file descriptor",
+                ));
+            }
+            let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+            // The received file descriptor is freshly created and exclusively owned.
+            let fd = OwnedFd::from_raw_fd(fd);
+            // The other process sent more than fits in the buffer, so this is not a message sent by
+            // `send_fd`. The descriptor that was received is closed.
+            if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "control message truncated",
+                ));
+            }
+            #[cfg(target_vendor = "apple")]
+            if libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+                return Err(Error::last_os_error());
+            }
+            Ok(fd)
+        }
+    }
+}
+
+/// Flags for sending, which suppress `SIGPIPE` on a closed channel where supported. On Apple
+/// platforms, `SO_NOSIGPIPE` is set on the socket instead.
+#[cfg(not(target_vendor = "apple"))]
+const SEND_FLAGS: libc::c_int = libc::MSG_NOSIGNAL;
+#[cfg(target_vendor = "apple")]
+const SEND_FLAGS: libc::c_int = 0;
+
+/// Flags for receiving file descriptors, which are made close-on-exec atomically if supported.
+#[cfg(not(target_vendor = "apple"))]
+const RECV_FLAGS: libc::c_int = libc::MSG_CMSG_CLOEXEC;
+#[cfg(target_vendor = "apple")]
+const RECV_FLAGS: libc::c_int = 0;
+
+// SAFETY: `CMSG_SPACE` does not have special safety requirements.
+const CMSG_SPACE: usize =
+    unsafe { libc::CMSG_SPACE(std::mem::size_of::<libc::c_int>() as _) } as usize;
+
+/// Control message buffer with space for a single file descriptor.
+#[repr(C, align(8))]
+struct CmsgBuffer([u8; CMSG_SPACE]);
+
+impl CmsgBuffer {
+    /// Create a `msghdr` with a single I/O vector and this buffer as the control buffer.
+    fn msghdr(&mut self, iov: &mut libc::iovec) -> libc::msghdr {
+        // SAFETY: all-zero is a valid `msghdr`.
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = self.0.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = self.0.len() as _;
+        msg
+    }
+}
+
+/// One endpoint of a bidirectional channel, sending `S` and receiving `R`.
+pub(crate) type Endpoint<S, R, C> = (Sender<S, C>, Receiver<R, C>);
+
+/// The two endpoints of a bidirectional channel, the first sending `A` and the second sending `B`.
+type Endpoints<A, B, C> = (Endpoint<A, B, C>, Endpoint<B, A, C>);
+
+/// Create a bidirectional channel, returning the two endpoints.
+///
+/// Each direction uses its own socket pair, so dropping a sender signals EOF to the receiver even
+/// if the receiver of the same endpoint is still alive.
+pub(crate) fn channel<A, B, C>() -> Result<Endpoints<A, B, C>> {
+    let (a_tx, b_rx) = unidirectional()?;
+    let (b_tx, a_rx) = unidirectional()?;
+    Ok(((a_tx, a_rx), (b_tx, b_rx)))
+}
+
+fn unidirectional<T, C>() -> Result<(Sender<T, C>, Receiver<T, C>)> {
+    let (tx, rx) = UnixStream::pair()?;
+    #[cfg(target_vendor = "apple")]
+    {
+        let on: libc::c_int = 1;
+        // SAFETY: `on` is valid for reads for the duration of the call.
+        let ret = unsafe {
+            libc::setsockopt(
+                tx.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_NOSIGPIPE,
+                &on as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::last_os_error());
+        }
+    }
+    Ok((
+        Sender {
+            stream: tx,
+            _marker: PhantomData,
+        },
+        Receiver {
+            stream: rx,
+            _marker: PhantomData,
+        },
+    ))
+}
+
+/// Result of [`fork_with_channel`].
+#[must_use]
+#[derive(Debug)]
+pub enum ForkChannel<T, C = Bincode> {
+    /// Returned in the parent process, with the handle of the child and the parent's endpoint.
+    Parent(Child, Sender<T, C>, Receiver<T, C>),
+    /// Returned in the child process, with the child's endpoint.
+    Child(Sender<T, C>, Receiver<T, C>),
+}
+
+/// Fork the current process, with a typed channel connecting the parent and the child.
+///
+/// The forking process must be single-threaded. Otherwise, this call will fail.
+pub fn fork_with_channel<T>() -> Result<ForkChannel<T>> {
+    fork_with_channel_codec()
+}
+
+/// Fork the current process, with a typed channel serializing values with the codec `C`.
+///
+/// See [`fork_with_channel`].
+pub fn fork_with_channel_codec<C: Codec, T>() -> Result<ForkChannel<T, C>> {
+    let ((parent_tx, parent_rx), (child_tx, child_rx)) = channel()?;
+    Ok(match crate::fork()? {
+        ForkResult::Parent(child) => ForkChannel::Parent(child, parent_tx, parent_rx),
+        ForkResult::Child => ForkChannel::Child(child_tx, child_rx),
+    })
+}
@@ -0,0 +1,727 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use std::process::Output;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    ChildStatus, ChildStderr, ChildStdin, ChildStdout, ForkError, JoinReport, ResourceUsage,
+};
+
+/// Representation of a forked child process.
+///
+/// This is a thin wrapper of the raw PID to provide the `join` helper function.
+///
+/// If the handle is dropped without being joined, the child is reaped in a best-effort manner: an
+/// attempt is made immediately, and children still running are reaped by subsequent calls to
+/// [`fork`](crate::fork). Use [`Child::detach`] to opt out of this.
+///
+/// On Linux 5.3+, a pidfd referring to the child is also held. It is used for signalling, so that
+/// signals cannot be delivered to an unrelated process, and is exposed via [`Child::pidfd`] so that
+/// the child can be registered with event loops.
+#[derive(Debug)]
+pub struct Child {
+    pid: libc::pid_t,
+    pidfd: Option<OwnedFd>,
+    /// Handle for writing to the standard input of the child, if it is piped.
+    pub stdin: Option<ChildStdin>,
+    /// Handle for reading from the standard output of the child, if it is piped.
+    pub stdout: Option<ChildStdout>,
+    /// Handle for reading from the standard error of the child, if it is piped.
+    pub stderr: Option<ChildStderr>,
+    /// Read end of the pipe that receives the panic message, if panics are captured.
+    pub(crate) panic: Option<File>,
+    /// Means of reaching the descendants of the child, used by [`Child::kill_tree`].
+    pub(crate) tree: Option<Tree>,
+    /// CPU time limit set with [`ForkBuilder::cpu_limit`](crate::ForkBuilder::cpu_limit).
+    pub(crate) cpu_limit: Option<Duration>,
+    /// Means of telling whether the child exceeded the limit set with
+    /// [`ForkBuilder::memory_limit`](crate::ForkBuilder::memory_limit).
+    pub(crate) memory: Option<crate::memory::MemoryWatch>,
+    /// Time at which the child was forked.
+    spawned_at: Instant,
+    /// Exit status, duration and resource usage, if the child has already been reaped.
+    status: Option<JoinReport>,
+    /// State of tracing, if the child is traced with `ptrace`.
+    #[cfg(target_os = "linux")]
+    pub(crate) trace: Option<crate::ptrace::Trace>,
+}
+
+impl Child {
+    pub(crate) fn new(pid: libc::pid_t) -> Self {
+        // The child cannot be reaped before we call `waitpid`, so there is no race here.
+        Self::with_pidfd(pid, pidfd_open(pid).ok())
+    }
+
+    pub(crate) fn with_pidfd(pid: libc::pid_t, pidfd: Option<OwnedFd>) -> Self {
+        crate::registry::register(pid as u32);
+        Self {
+            pid,
+            pidfd,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            panic: None,
+            tree: None,
+            cpu_limit: None,
+            memory: None,
+            spawned_at: Instant::now(),
+            status: None,
+            #[cfg(target_os = "linux")]
+            trace: None,
+        }
+    }
+
+    /// Returns the OS-assigned process identifier associated with this child.
+    pub fn pid(&self) -> u32 {
+        self.pid as _
+    }
+
+    /// Returns whether the child has not exited yet, without reaping it.
+    pub fn is_alive(&self) -> Result<bool> {
+        self.peek_status().map(|status| status.is_none())
+    }
+
+    /// Returns the time at which the child was forked.
+    pub fn spawned_at(&self) -> Instant {
+        self.spawned_at
+    }
+
+    /// Returns the process group ID of the child.
+    ///
+    /// This is the PID of the child if it is spawned with
+    /// [`ForkBuilder::new_process_group`](crate::ForkBuilder::new_process_group) or
+    /// [`ForkBuilder::new_session`](crate::ForkBuilder::new_session).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the child has already been reaped.
+    pub fn pgid(&self) -> Result<u32> {
+        if self.status.is_some() {
+            return Err(Error::from_raw_os_error(libc::ESRCH));
+        }
+        // SAFETY: `getpgid` does not have special safety requirements.
+        let pgid = unsafe { libc::getpgid(self.pid) };
+        if pgid < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(pgid as u32)
+    }
+
+    /// Returns the pidfd associated with this child, if pidfd is supported by the kernel.
+    pub fn pidfd(&self) -> Option<BorrowedFd<'_>> {
+        self.pidfd.as_ref().map(|fd| fd.as_fd())
+    }
+
+    /// Creates a handle from the PID of a child process.
+    ///
+    /// This is the counterpart of [`into_raw_pid`](Self::into_raw_pid). The handle takes over the
+    /// responsibility of reaping the child. [`spawned_at`](Self::spawned_at) returns the time that
+    /// the handle is created.
+    ///
+    /// # Safety
+    ///
+    /// `pid` must refer to a child of the current process that has not been reaped yet, and
+    /// nothing else may reap it.
+    pub unsafe fn from_raw_pid(pid: u32) -> Self {
+        Self::new(pid as _)
+    }
+
+    /// Consumes the handle, returning the PID of the child.
+    ///
+    /// It becomes the caller's responsibility to reap the child, e.g. by calling `waitpid` with the
+    /// PID or by passing it to [`from_raw_pid`](Self::from_raw_pid). Unlike with
+    /// [`detach`](Self::detach), an installed [`Reaper`](crate::Reaper) does not reap the child.
+    ///
+    /// If the child has already been reaped through this handle, the PID may have been reused.
+    pub fn into_raw_pid(self) -> u32 {
+        let (pid, _) = self.release();
+        crate::registry::unregister(pid as u32);
+        pid as u32
+    }
+
+    /// Creates a handle from a pidfd referring to a child process.
+    ///
+    /// This is the counterpart of [`into_pidfd`](Self::into_pidfd). The PID of the child is looked
+    /// up from `/proc`, and fails with [`ErrorKind::InvalidInput`] if the process referred to by
+    /// `pidfd` has already been reaped.
+    ///
+    /// # Safety
+    ///
+    /// `pidfd` must refer to a child of the current process that has not been reaped yet, and
+    /// nothing else may reap it.
+    #[cfg(target_os = "linux")]
+    pub unsafe fn from_pidfd(pidfd: OwnedFd) -> Result<Self> {
+        let fdinfo = std::fs::read_to_string(format!("/proc/self/fdinfo/{}", pidfd.as_raw_fd()))?;
+        let pid = fdinfo
+            .lines()
+            .find_map(|line| line.strip_prefix("Pid:"))
+            .and_then(|pid| pid.trim().parse::<libc::pid_t>().ok())
+            .filter(|&pid| pid > 0)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "not a pidfd of a live process"))?;
+        Ok(Self::with_pidfd(pid, Some(pidfd)))
+    }
+
+    /// Consumes the handle, returning the pidfd of the child.
+    ///
+    /// It becomes the caller's responsibility to reap the child, e.g. by calling `waitid` with
+    /// `P_PIDFD` or by passing it to [`from_pidfd`](Self::from_pidfd). If pidfd is not supported
+    /// by the kernel, the handle is returned back instead.
+    // The handle is returned as is, so that the child can still be reaped through it.
+    #[allow(clippy::result_large_err)]
+    pub fn into_pidfd(self) -> std::result::Result<OwnedFd, Self> {
+        if self.pidfd.is_none() {
+            return Err(self);
+        }
+        let (pid, pidfd) = self.release();
+        crate::registry::unregister(pid as u32);
+        Ok(pidfd.unwrap())
+    }
+
+    /// Consumes the handle without reaping the child, closing the pipes to it.
+    fn release(self) -> (libc::pid_t, Option<OwnedFd>) {
+        let mut this = std::mem::ManuallyDrop::new(self);
+        this.stdin.take();
+        this.stdout.take();
+        this.stderr.take();
+        this.panic.take();
+        this.tree.take();
+        this.memory.take();
+        (this.pid, this.pidfd.take())
+    }
+
+    /// Waits for the child to exit completely, returning the status that it
+    /// exited with.
+    ///
+    /// If the child is spawned with [`ForkBuilder::capture_panics`](crate::ForkBuilder::capture_panics)
+    /// and it panicked, this fails with a [`ChildPanicked`](crate::ChildPanicked) error instead.
+    /// The same applies to the other methods that reap the child.
+    pub fn join(self) -> Result<ChildStatus> {
+        self.join_with_rusage().map(|(status, _)| status)
+    }
+
+    /// Returns the status of the child if it has exited, without reaping it.
+    ///
+    /// This does not block. The child stays a zombie until it is reaped, so another component,
+    /// e.g. an init-like reaper calling `waitpid`, can still collect its status; use
+    /// [`detach`](Self::detach) to hand over the reaping. If the child has already been reaped
+    /// through this handle, the status collected then is returned.
+    pub fn peek_status(&self) -> Result<Option<ChildStatus>> {
+        if let Some(report) = self.status {
+            return Ok(Some(report.status));
+        }
+        // SAFETY: all-zero is a valid `siginfo_t`.
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        // SAFETY: `info` is valid for the duration of the call.
+        let ret = unsafe {
+            libc::waitid(
+                libc::P_PID,
+                self.pid as _,
+                &mut info,
+                libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::last_os_error());
+        }
+        // SAFETY: `waitid` fills in the PID for `WEXITED`, or leaves it zero if the child is
+        // running.
+        if unsafe { info.si_pid() } == 0 {
+            return Ok(None);
+        }
+        Ok(Some(ChildStatus::from_siginfo(&info)))
+    }
+
+    /// Waits for the child to exit like [`join`](Self::join), but gives up if a signal handler runs
+    /// in the meantime.
+    ///
+    /// Other ways of waiting retry when interrupted by a signal handler. This instead fails with
+    /// an error of kind [`Interrupted`](ErrorKind::Interrupted), so that the caller can react to
+    /// its own signals and wait again later. Signal handlers installed with `SA_RESTART` do not
+    /// interrupt the wait.
+    pub fn join_interruptible(&mut self) -> Result<ChildStatus> {
+        match self.status {
+            Some(report) => Ok(report.status),
+            None => self
+                .wait_interruptible(0)
+                .map(|report| report.unwrap().status),
+        }
+    }
+
+    /// Waits for the child to exit completely, returning the status that it exited with and the
+    /// resources that it and its reaped descendants used.
+    pub fn join_with_rusage(self) -> Result<(ChildStatus, ResourceUsage)> {
+        self.join_with_report()
+            .map(|report| (report.status, report.rusage))
+    }
+
+    /// Waits for the child to exit completely, returning the status that it exited with, how long
+    /// it ran for and the resources that it and its reaped descendants used.
+    pub fn join_with_report(mut self) -> Result<JoinReport> {
+        match self.status {
+            Some(report) => Ok(report),
+            None => self.wait(0).map(Option::unwrap),
+        }
+    }
+
+    /// Waits for the child to exit completely, classifying a child that exceeded its resource
+    /// limits as an error.
+    ///
+    /// This fails with [`ForkError::CpuLimitExceeded`] if the child was spawned with
+    /// [`ForkBuilder::cpu_limit`](crate::ForkBuilder::cpu_limit) and was terminated for exceeding
+    /// it, see [`JoinReport::cpu_limit_exceeded`]. Likewise, it fails with
+    /// [`ForkError::MemoryLimitExceeded`] for a child that exceeded the limit set with
+    /// [`ForkBuilder::memory_limit`](crate::ForkBuilder::memory_limit). Other statuses, including
+    /// other terminations by signals, are returned as is. A panic captured with
+    /// [`ForkBuilder::capture_panics`](crate::ForkBuilder::capture_panics) fails with
+    /// [`ForkError::Panicked`].
+    ///
+    /// Without a cgroup, the memory limit check is a heuristic: any abort that happens while the
+    /// error number is `ENOMEM` is classified as exceeding the limit, even if the abort has another
+    /// cause, e.g. a call to [`std::process::abort`] after a system call fails with `ENOMEM`.
+    pub fn join_checked(mut self) -> std::result::Result<ChildStatus, ForkError> {
+        let report = match self.status {
+            Some(report) => report,
+            None => self.wait(0).map_err(ForkError::from_wait)?.unwrap(),
+        };
+        let signal = report.status.signal();
+        if self
+            .memory
+            .as_ref()
+            .is_some_and(|memory| memory.exceeded(signal))
+        {
+            return Err(ForkError::MemoryLimitExceeded);
+        }
+        if self
+            .cpu_limit
+            .is_some_and(|limit| report.cpu_limit_exceeded(limit))
+        {
+            return Err(ForkError::CpuLimitExceeded);
+        }
+        Ok(report.status)
+    }
+
+    /// Waits for the child to exit, collecting all remaining output on piped stdout and stderr.
+    ///
+    /// The stdin pipe, if any, is closed before waiting, so that a child reading from it sees
+    /// end-of-file. Both pipes are drained at the same time with `poll`, so the child cannot block
+    /// on a full pipe while the other one is read. Streams that are not piped, or whose handles
+    /// have been taken out of [`Child::stdout`] and [`Child::stderr`], are collected as empty.
+    pub fn wait_with_output(mut self) -> Result<Output> {
+        drop(self.stdin.take());
+        let mut pipes = [
+            self.stdout.take().map(|stdout| stdout.0),
+            self.stderr.take().map(|stderr| stderr.0),
+        ];
+        let mut bufs = [Vec::new(), Vec::new()];
+        crate::read_all(&mut pipes, &mut bufs)?;
+        let [stdout, stderr] = bufs;
+        let status = self.join()?;
+        Ok(Output {
+            status: status.into(),
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Attempts to collect the exit status of the child if it has already exited.
+    ///
+    /// This function will not block the calling thread. If the child has exited, then `Ok(Some(status))`
+    /// is returned. If the child is still running, then `Ok(None)` is returned.
+    pub fn try_join(&mut self) -> Result<Option<ChildStatus>> {
+        let status = match self.status {
+            Some(status) => Some(status),
+            None => self.wait(libc::WNOHANG)?,
+        };
+        Ok(status.map(|report| report.status))
+    }
+
+    /// Waits for the child to exit for at most `timeout`.
+    ///
+    /// If the child exits in time, its exit status is returned. Otherwise the child is handed back
+    /// as `Ok(Err(child))` so that the caller may wait again or take other actions.
+    pub fn join_timeout(
+        mut self,
+        timeout: Duration,
+    ) -> Result<std::result::Result<ChildStatus, Child>> {
+        if let Some(status) = self.try_join()? {
+            return Ok(Ok(status));
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match &self.pidfd {
+                Some(pidfd) => {
+                    if !poll_readable(pidfd, remaining)? {
+                        return Ok(Err(self));
+                    }
+                }
+                // Without pidfd support, fall back to polling at a fixed interval.
+                None => std::thread::sleep(remaining.min(Duration::from_millis(10))),
+            }
+
+            if let Some(status) = self.try_join()? {
+                return Ok(Ok(status));
+            }
+            if remaining.is_zero() {
+                return Ok(Err(self));
+            }
+        }
+    }
+
+    /// Waits for the child to exit completely without blocking the async runtime.
+    ///
+    /// This requires pidfd support from the kernel, and fails with
+    /// [`ErrorKind::Unsupported`](std::io::ErrorKind::Unsupported) otherwise.
+    #[cfg(feature = "tokio")]
+    pub async fn join_async(mut self) -> Result<ChildStatus> {
+        if let Some(status) = self.try_join()? {
+            return Ok(status);
+        }
+        let pidfd = self.pidfd.take().ok_or(std::io::ErrorKind::Unsupported)?;
+        let pidfd = tokio::io::unix::AsyncFd::with_interest(pidfd, tokio::io::Interest::READABLE)?;
+        // The pidfd becomes readable once the child exits, and stays readable afterwards.
+        let _guard = pidfd.readable().await?;
+        self.join()
+    }
+
+    /// Waits for the child to exit, stop or continue, returning the corresponding status.
+    ///
+    /// Unlike [`join`](Self::join), this also reports [`ChildStatus::Stopped`] and
+    /// [`ChildStatus::Continued`], using `WUNTRACED` and `WCONTINUED`. The child is only reaped if
+    /// it exits, so this can be called repeatedly to follow state changes of the child.
+    pub fn wait_event(&mut self) -> Result<ChildStatus> {
+        match self.status {
+            Some(report) => Ok(report.status),
+            None => self
+                .wait(libc::WUNTRACED | libc::WCONTINUED)
+                .map(|report| report.unwrap().status),
+        }
+    }
+
+    /// Attempts to collect a state change of the child without blocking.
+    ///
+    /// This is the non-blocking version of [`wait_event`](Self::wait_event), returning
+    /// `Ok(None)` if the child has not changed state.
+    pub fn try_wait_event(&mut self) -> Result<Option<ChildStatus>> {
+        let status = match self.status {
+            Some(status) => Some(status),
+            None => self.wait(libc::WUNTRACED | libc::WCONTINUED | libc::WNOHANG)?,
+        };
+        Ok(status.map(|report| report.status))
+    }
+
+    /// Detaches the child, so it will not be reaped when dropped.
+    ///
+    /// It becomes the caller's responsibility to reap the child, e.g. by calling `waitpid` with the
+    /// PID, otherwise it remains a zombie after it exits. If a [`Reaper`](crate::Reaper) is
+    /// installed, the child is reaped by it instead.
+    pub fn detach(self) {
+        let reaped = self.status.is_some();
+        let (pid, _) = self.release();
+        if !reaped && crate::reaper::is_installed() {
+            ORPHANS.lock().unwrap().push(pid);
+            crate::registry::mark_orphaned(pid as u32);
+        } else {
+            crate::registry::unregister(pid as u32);
+        }
+    }
+
+    /// Sends the signal `sig` to the child.
+    ///
+    /// If the child has already been reaped, this is a no-op.
+    pub fn signal(&self, sig: i32) -> Result<()> {
+        if self.status.is_some() {
+            return Ok(());
+        }
+        trace!(debug, pid = self.pid, signal = sig, "signalling child");
+        match &self.pidfd {
+            #[cfg(target_os = "linux")]
+            Some(pidfd) => crate::sys::pidfd_send_signal(pidfd.as_fd(), sig),
+            // The child is not yet reaped so the PID cannot have been reused.
+            _ => crate::sys::kill(self.pid, sig),
+        }
+    }
+
+    /// Forces the child to exit by sending `SIGKILL`.
+    pub fn kill(&self) -> Result<()> {
+        self.signal(libc::SIGKILL)
+    }
+
+    /// Forces the child and all its descendants to exit.
+    ///
+    /// If the child is created in a cgroup with [`ForkBuilder::cgroup`](crate::ForkBuilder::cgroup),
+    /// all processes in the cgroup are killed, using `cgroup.kill` if available. Otherwise, if the
+    /// child is put in its own process group with
+    /// [`ForkBuilder::new_process_group`](crate::ForkBuilder::new_process_group) or
+    /// [`ForkBuilder::new_session`](crate::ForkBuilder::new_session), `SIGKILL` is
+    /// sent to the process group. Descendants that moved to another process group escape this.
+    ///
+    /// This works even if the child itself has already been reaped.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`](std::io::ErrorKind::InvalidInput) if the child is
+    /// neither in its own cgroup nor in its own process group.
+    pub fn kill_tree(&self) -> Result<()> {
+        trace!(debug, pid = self.pid, "killing child tree");
+        match &self.tree {
+            #[cfg(target_os = "linux")]
+            Some(Tree::Cgroup(dir)) => crate::cgroup::kill(dir.as_fd()),
+            Some(Tree::ProcessGroup) => {
+                match crate::sys::kill_process_group(self.pid, libc::SIGKILL) {
+                    // All processes in the group have already exited.
+                    Err(err) if err.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+                    result => result,
+                }
+            }
+            None => Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "child is neither in its own cgroup nor in its own process group",
+            )),
+        }
+    }
+
+    /// Asks the child to exit by sending `SIGTERM`.
+    pub fn terminate(&self) -> Result<()> {
+        self.signal(libc::SIGTERM)
+    }
+
+    /// Shuts the child down gracefully, escalating to `SIGKILL` if needed.
+    ///
+    /// `SIGTERM` is sent first, and the child is given up to `grace` to exit. If it is still
+    /// running by then, it is killed with `SIGKILL`. Either way, the child is reaped and its exit
+    /// status is returned.
+    pub fn shutdown(self, grace: Duration) -> Result<ChildStatus> {
+        self.terminate()?;
+        match self.join_timeout(grace)? {
+            Ok(status) => Ok(status),
+            Err(child) => {
+                child.kill()?;
+                child.join()
+            }
+        }
+    }
+
+    /// Stops the child by sending `SIGSTOP`.
+    ///
+    /// Use [`wait_event`](Self::wait_event) to wait until the child has actually stopped.
+    pub fn suspend(&self) -> Result<()> {
+        self.signal(libc::SIGSTOP)
+    }
+
+    /// Resumes a stopped child by sending `SIGCONT`.
+    pub fn resume(&self) -> Result<()> {
+        self.signal(libc::SIGCONT)
+    }
+
+    /// Wait for a state change of the child, retrying if interrupted by a signal handler.
+    fn wait(&mut self, options: libc::c_int) -> Result<Option<JoinReport>> {
+        loop {
+            match self.wait_interruptible(options) {
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                result => return result,
+            }
+        }
+    }
+
+    /// Calls `wait4` with the given options, caching the exit status and resource usage if the
+    /// child is reaped.
+    ///
+    /// The resource usage returned alongside stop and continue events is meaningless.
+    fn wait_interruptible(&mut self, options: libc::c_int) -> Result<Option<JoinReport>> {
+        // Traced children stop at every event. Unless stops are waited for, keep resuming them.
+        #[cfg(target_os = "linux")]
+        if let Some(trace) = &mut self.trace {
+            if options & libc::WUNTRACED == 0 {
+                trace.resume(self.pid)?;
+                loop {
+                    match self.wait_raw(options)? {
+                        Some((status, _)) if libc::WIFSTOPPED(status) => {
+                            let trace = self.trace.as_mut().unwrap();
+                            trace.stopped(status);
+                            trace.resume(self.pid)?;
+                        }
+                        result => return Ok(result.map(|(_, report)| report)),
+                    }
+                }
+            }
+        }
+        Ok(self.wait_raw(options)?.map(|(_, report)| report))
+    }
+
+    /// Returns the status of the child if it has already been reaped through this handle.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn reaped_status(&self) -> Option<ChildStatus> {
+        self.status.map(|report| report.status)
+    }
+
+    /// Wait for the child with `wait4`, returning the raw wait status along with the report.
+    pub(crate) fn wait_raw(
+        &mut self,
+        options: libc::c_int,
+    ) -> Result<Option<(libc::c_int, JoinReport)>> {
+        let mut status = 0;
+        // SAFETY: all-zero is a valid `rusage`.
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        // SAFETY: `status` and `usage` are valid for the duration of the call.
+        let ret = unsafe { libc::wait4(self.pid, &mut status, options, &mut usage) };
+        if ret < 0 {
+            return Err(Error::last_os_error());
+        }
+        if ret == 0 {
+            return Ok(None);
+        }
+        let report = JoinReport {
+            status: ChildStatus::from_raw(status),
+            duration: self.spawned_at.elapsed(),
+            rusage: usage.into(),
+        };
+        if let ChildStatus::Stopped(_) | ChildStatus::Continued = report.status {
+            return Ok(Some((status, report)));
+        }
+        self.status = Some(report);
+        trace!(debug, pid = self.pid, status = ?report.status, "reaped child");
+        crate::registry::reaped(self.pid as u32, report.status);
+
+        if let Some(report) = self.panic.take() {
+            if let Some(panicked) = crate::panic::read_report(report)? {
+                return Err(Error::other(panicked));
+            }
+        }
+        Ok(Some((status, report)))
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        // Traced children would otherwise stay stopped at their next event.
+        #[cfg(target_os = "linux")]
+        if self.trace.is_some() && self.status.is_none() {
+            let _ = self.kill();
+            let _ = self.wait(0);
+            return;
+        }
+        match self.try_join() {
+            Ok(None) => {
+                ORPHANS.lock().unwrap().push(self.pid);
+                crate::registry::mark_orphaned(self.pid as u32);
+            }
+            // The child may have been reaped by other means, e.g. `reap_children`.
+            Err(_) => crate::registry::unregister(self.pid as u32),
+            Ok(Some(_)) => (),
+        }
+    }
+}
+
+/// Means of reaching all descendants of a child.
+#[derive(Debug)]
+pub(crate) enum Tree {
+    /// The child leads its own process group.
+    ProcessGroup,
+    /// The child is created in the cgroup referred to by the directory.
+    #[cfg(target_os = "linux")]
+    Cgroup(OwnedFd),
+}
+
+/// Waits for all children to exit, returning their exit statuses in order.
+pub fn join_all(children: impl IntoIterator<Item = Child>) -> Result<Vec<ChildStatus>> {
+    children.into_iter().map(Child::join).collect()
+}
+
+/// Waits for any of the children to exit, returning its PID and exit status.
+///
+/// The child that exits is removed from `children`; the rest are left in place. Only the given
+/// children are waited for, so children forked by other means are never reaped by this call.
+///
+/// # Errors
+///
+/// Fails with [`ErrorKind::InvalidInput`](std::io::ErrorKind::InvalidInput) if `children` is
+/// empty.
+pub fn wait_any(children: &mut Vec<Child>) -> Result<(u32, ChildStatus)> {
+    if children.is_empty() {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "no children to wait for",
+        ));
+    }
+
+    loop {
+        for index in 0..children.len() {
+            if let Some(status) = children[index].try_join()? {
+                let child = children.remove(index);
+                return Ok((child.pid(), status));
+            }
+        }
+
+        if children.iter().all(|child| child.pidfd.is_some()) {
+            let mut pollfds: Vec<_> = children
+                .iter()
+                .map(|child| libc::pollfd {
+                    fd: child.pidfd.as_ref().unwrap().as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+            crate::poll(&mut pollfds, None)?;
+        } else {
+            // Without pidfd support, fall back to polling at a fixed interval.
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// PIDs of children whose handles are dropped before they exit.
+static ORPHANS: Mutex<Vec<libc::pid_t>> = Mutex::new(Vec::new());
+
+/// Reap all orphaned children that have exited.
+///
+/// Their exit statuses are handed to the reaper, if one is installed, and returned.
+pub(crate) fn reap_orphans() -> Vec<(u32, ChildStatus)> {
+    let mut exited = Vec::new();
+    ORPHANS.lock().unwrap().retain(|&pid| {
+        let mut status = 0;
+        // SAFETY: `status` is valid for the duration of the call.
+        let ret = unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) };
+        if ret == pid {
+            let status = ChildStatus::from_raw(status);
+            trace!(debug, pid, status = ?status, "reaped orphaned child");
+            crate::registry::reaped(pid as u32, status);
+            exited.push((pid as u32, status));
+        } else if ret != 0 {
+            crate::registry::unregister(pid as u32);
+        }
+        ret == 0
+    });
+    crate::reaper::record(exited.clone());
+    exited
+}
+
+/// Forget about orphaned children. Used in a newly forked child, as they are not children of it.
+pub(crate) fn forget_orphans() {
+    ORPHANS.lock().unwrap().clear();
+    crate::registry::clear();
+}
+
+/// Obtain a file descriptor that refers to the process.
+#[cfg(not(target_os = "linux"))]
+fn pidfd_open(_pid: libc::pid_t) -> Result<OwnedFd> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(target_os = "linux")]
+use crate::sys::pidfd_open;
+
+/// Wait until the file descriptor becomes readable, or until the timeout elapses.
+///
+/// Returns whether the file descriptor is readable.
+fn poll_readable(fd: &OwnedFd, timeout: Duration) -> Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    Ok(crate::poll(std::slice::from_mut(&mut pollfd), Some(timeout))? != 0)
+}
@@ -0,0 +1,256 @@
+use std::io::{Error, ErrorKind, Read, Write};
+use std::ops::{BitOr, BitOrAssign};
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+
+use crate::{Child, ForkError, ForkResult};
+
+/// Flags for creating the child process with `clone3`.
+///
+/// Only flags that keep the `fork` semantics are provided. Other flags can be passed with
+/// [`ForkBuilder::raw_clone_flags`](crate::ForkBuilder::raw_clone_flags).
+///
+/// Unless the child executes a program right away, it is forked by the C library and applies the
+/// flags with `unshare`. The flags that `unshare` cannot apply to the calling process,
+/// [`NEWPID`](Self::NEWPID), [`NEWTIME`](Self::NEWTIME), [`CLEAR_SIGHAND`](Self::CLEAR_SIGHAND)
+/// and raw flags, require creating the child with `clone3` directly. This bypasses the C library:
+/// its `pthread_atfork` handlers do not run, and its per-thread state, such as the thread ID
+/// cached for `pthread` mutexes, still describes the parent's thread. Code in such a child must
+/// not depend on that state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CloneFlags(u64);
+
+impl CloneFlags {
+    /// Run the child in a new user namespace.
+    pub const NEWUSER: Self = Self(libc::CLONE_NEWUSER as u64);
+    /// Run the child in a new mount namespace.
+    pub const NEWNS: Self = Self(libc::CLONE_NEWNS as u64);
+    /// Run the child in a new PID namespace.
+    pub const NEWPID: Self = Self(libc::CLONE_NEWPID as u64);
+    /// Run the child in a new network namespace.
+    pub const NEWNET: Self = Self(libc::CLONE_NEWNET as u64);
+    /// Run the child in a new UTS namespace.
+    pub const NEWUTS: Self = Self(libc::CLONE_NEWUTS as u64);
+    /// Run the child in a new IPC namespace.
+    pub const NEWIPC: Self = Self(libc::CLONE_NEWIPC as u64);
+    /// Run the child in a new cgroup namespace.
+    pub const NEWCGROUP: Self = Self(libc::CLONE_NEWCGROUP as u64);
+    /// Run the child in a new time namespace. Requires `clone3`.
+    pub const NEWTIME: Self = Self(0x80);
+    /// Reset all signal handlers to the default in the child. Requires `clone3`.
+    pub const CLEAR_SIGHAND: Self = Self(0x1_0000_0000);
+
+    /// Returns an empty set of flags.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Returns the raw value of the flags.
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Returns whether all flags in `other` are set.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Creates flags from raw `clone3` flags.
+    ///
+    /// # Safety
+    ///
+    /// The flags must not break the `fork` semantics; for example, `CLONE_VM`, `CLONE_THREAD`,
+    /// `CLONE_SETTLS` and flags that require additional `clone_args` fields are not allowed.
+    pub const unsafe fn from_bits_unchecked(bits: u64) -> Self {
+        Self(bits)
+    }
+}
+
+impl BitOr for CloneFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for CloneFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// `CLONE_INTO_CGROUP`, which is set when a cgroup is given rather than being a public flag.
+const CLONE_INTO_CGROUP: u64 = 0x2_0000_0000;
+
+/// Flags that cannot be passed to `clone`, either because they do not fit or because they overlap
+/// with the exit signal.
+const CLONE3_ONLY: u64 = !0xffff_ff00;
+
+/// Flags that `unshare` applies to the calling process itself, rather than only to its children.
+const UNSHAREABLE: u64 = (libc::CLONE_NEWUSER
+    | libc::CLONE_NEWNS
+    | libc::CLONE_NEWNET
+    | libc::CLONE_NEWUTS
+    | libc::CLONE_NEWIPC
+    | libc::CLONE_NEWCGROUP) as u64;
+
+/// Mirror of the kernel's `struct clone_args`.
+#[repr(C)]
+#[derive(Default)]
+struct CloneArgs {
+    flags: u64,
+    pidfd: u64,
+    child_tid: u64,
+    parent_tid: u64,
+    exit_signal: u64,
+    stack: u64,
+    stack_size: u64,
+    tls: u64,
+    set_tid: u64,
+    set_tid_size: u64,
+    cgroup: u64,
+}
+
+/// Fork the current process with the given `clone3` flags.
+///
+/// If `cgroup` is given, the child is created in that cgroup if the kernel supports
+/// `CLONE_INTO_CGROUP`. Returns whether it is, along with the child as in [`fork`](crate::fork).
+///
+/// If `exec` is false and `unshare` can apply the flags, the child is forked by the C library and
+/// unshares the namespaces itself, outside of the cgroup. Otherwise the child is created by the
+/// `clone3` system call, falling back to `clone` if it is unavailable and the flags permit. The C
+/// library does not know about such a child, so it must either execute a program without relying
+/// on the C library's fork bookkeeping, or have flags documented on [`CloneFlags`] as needing
+/// `clone3`.
+pub(crate) fn fork_with_flags(
+    flags: CloneFlags,
+    cgroup: Option<BorrowedFd<'_>>,
+    exec: bool,
+) -> Result<(Option<Child>, bool), ForkError> {
+    if !exec && flags.0 & !UNSHAREABLE == 0 {
+        return Ok((fork_unshare(flags)?, false));
+    }
+    let hooks = crate::prepare_fork()?;
+
+    let mut pidfd: libc::c_int = -1;
+    let mut args = CloneArgs {
+        flags: flags.0 | libc::CLONE_PIDFD as u64,
+        pidfd: &mut pidfd as *mut _ as u64,
+        exit_signal: libc::SIGCHLD as u64,
+        ..Default::default()
+    };
+    if let Some(cgroup) = cgroup {
+        args.flags |= CLONE_INTO_CGROUP;
+        args.cgroup = cgroup.as_raw_fd() as u64;
+    }
+    let clone3 = |args: &mut CloneArgs| {
+        // SAFETY: without `CLONE_VM` and a new stack, `clone3` copies the process like fork, which
+        // is safe for single-threaded process. The child does not rely on the C library's fork
+        // bookkeeping, as documented above. `args` is valid for the duration of the call.
+        unsafe {
+            libc::syscall(
+                libc::SYS_clone3,
+                args as *mut CloneArgs,
+                std::mem::size_of::<CloneArgs>(),
+            ) as libc::pid_t
+        }
+    };
+    crate::metrics::fork_attempted();
+    let mut pid = clone3(&mut args);
+
+    let mut into_cgroup = cgroup.is_some();
+    if pid < 0
+        && into_cgroup
+        && matches!(
+            Error::last_os_error().raw_os_error(),
+            Some(libc::EINVAL | libc::E2BIG)
+        )
+    {
+        // `CLONE_INTO_CGROUP` is only supported since Linux 5.7.
+        into_cgroup = false;
+        args.flags &= !CLONE_INTO_CGROUP;
+        args.cgroup = 0;
+        pid = clone3(&mut args);
+    }
+
+    if pid < 0 {
+        let err = Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ENOSYS) || flags.0 & CLONE3_ONLY != 0 {
+            hooks.parent();
+            trace!(warn, error = %err, "failed to clone");
+            crate::metrics::fork_failed(err.raw_os_error().unwrap_or(0));
+            return Err(ForkError::ForkFailed(err));
+        }
+        into_cgroup = false;
+        // SAFETY: same as above.
+        pid = unsafe {
+            libc::syscall(
+                libc::SYS_clone,
+                flags.0 as libc::c_ulong | libc::SIGCHLD as libc::c_ulong,
+                0,
+                0,
+                0,
+                0,
+            ) as libc::pid_t
+        };
+    }
+
+    let child = match pid {
+        -1 => {
+            let err = Error::last_os_error();
+            hooks.parent();
+            trace!(warn, error = %err, "failed to clone");
+            crate::metrics::fork_failed(err.raw_os_error().unwrap_or(0));
+            return Err(ForkError::ForkFailed(err));
+        }
+        0 => {
+            crate::child::forget_orphans();
+            hooks.child();
+            return Ok((None, into_cgroup));
+        }
+        pid if pidfd >= 0 => {
+            // SAFETY: the pidfd is freshly created by `clone3` and exclusively owned.
+            let pidfd = unsafe { OwnedFd::from_raw_fd(pidfd) };
+            Some(Child::with_pidfd(pid, Some(pidfd)))
+        }
+        pid => Some(Child::new(pid)),
+    };
+    hooks.parent();
+    trace!(debug, pid, flags = flags.0, "cloned child");
+    Ok((child, into_cgroup))
+}
+
+/// Fork the current process with the C library, and unshare the namespaces in `flags` in the child.
+///
+/// The child exits if it fails to unshare them, which is reported as a failure to fork. Returns
+/// only once the child has unshared them, so that e.g. its ID mappings can be written.
+fn fork_unshare(flags: CloneFlags) -> Result<Option<Child>, ForkError> {
+    let (mut reader, mut writer) = crate::pipe()?;
+    match crate::fork()? {
+        ForkResult::Child => {
+            drop(reader);
+            // SAFETY: `unshare` does not have special safety requirements.
+            if unsafe { libc::unshare(flags.0 as _) } < 0 {
+                let errno = Error::last_os_error().raw_os_error().unwrap_or(0);
+                let _ = writer.write_all(&errno.to_ne_bytes());
+                // SAFETY: `_exit` does not have special safety requirements.
+                unsafe { libc::_exit(127) };
+            }
+            Ok(None)
+        }
+        ForkResult::Parent(child) => {
+            drop(writer);
+            let mut buf = [0; 4];
+            match reader.read_exact(&mut buf) {
+                Ok(()) => {
+                    let err = Error::from_raw_os_error(i32::from_ne_bytes(buf));
+                    trace!(warn, error = %err, "failed to unshare");
+                    child.join().map_err(ForkError::from_wait)?;
+                    Err(ForkError::ForkFailed(err))
+                }
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(Some(child)),
+                Err(err) => Err(ForkError::Io(err)),
+            }
+        }
+    }
+}
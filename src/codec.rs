@@ -0,0 +1,101 @@
+use std::io::{Error, ErrorKind, Result};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Serialization format of the values sent between processes.
+///
+/// Channels, services, pools, and [`fork_join_value`](crate::fork_join_value) and its relatives
+/// serialize values with [`Bincode`] by default. The variants of these APIs taking a codec as a
+/// type parameter, e.g. [`fork_join_value_codec`](crate::fork_join_value_codec), use another
+/// format instead. Implement this trait to use a format not provided by this crate.
+///
+/// Both processes must use the same codec, which is guaranteed as they are forked from the same
+/// program.
+///
+/// # Example
+///
+/// ```
+/// use std::io::{Error, ErrorKind, Result};
+///
+/// use safe_fork::Codec;
+/// use serde::{de::DeserializeOwned, Serialize};
+///
+/// /// Bincode, but with the payload reversed, as an example.
+/// struct Reversed;
+///
+/// impl Codec for Reversed {
+///     fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+///         let mut bytes = safe_fork::Bincode::encode(value)?;
+///         bytes.reverse();
+///         Ok(bytes)
+///     }
+///
+///     fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+///         let bytes: Vec<u8> = bytes.iter().rev().copied().collect();
+///         safe_fork::Bincode::decode(&bytes)
+///     }
+/// }
+///
+/// let value = safe_fork::fork_join_value_codec::<Reversed, _>(|| String::from("hi")).unwrap();
+/// assert_eq!(value, "hi");
+/// ```
+pub trait Codec {
+    /// Serializes `value` into bytes.
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>>;
+
+    /// Deserializes a value from `bytes`, which hold exactly one serialized value.
+    ///
+    /// Fails with [`ErrorKind::InvalidData`] if the bytes are not a valid value.
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T>;
+}
+
+/// The [`bincode`](https://docs.rs/bincode/1) format, used by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bincode;
+
+impl Codec for Bincode {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        bincode::serialize(value).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        bincode::deserialize(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+/// The [`postcard`](https://docs.rs/postcard/1) format.
+///
+/// This requires the `postcard` feature.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Postcard;
+
+#[cfg(feature = "postcard")]
+impl Codec for Postcard {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        postcard::to_stdvec(value).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        postcard::from_bytes(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
+
+/// The JSON format, with [`serde_json`](https://docs.rs/serde_json/1).
+///
+/// This requires the `json` feature.
+#[cfg(feature = "json")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+        serde_json::to_vec(value).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+        serde_json::from_slice(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{ErrorKind, Result};
+use std::process::{ExitStatus, Output};
+
+use crate::{Child, ChildStderr, ChildStdin, ChildStdout};
+
+/// A child handle with the same interface as [`std::process::Child`].
+///
+/// This allows code written against the standard library to work with children spawned by this
+/// crate, typically with [`fork_exec`](crate::fork_exec) or
+/// [`ForkBuilder::exec`](crate::ForkBuilder::exec), by changing only the type that it takes.
+/// Create it from a [`Child`] with [`From`].
+///
+/// Like [`std::process::Child`], dropping the handle does not wait for the child. Unlike it, the
+/// child is still reaped in the background, as with [`Child`].
+///
+/// # Example
+///
+/// ```
+/// use std::io::Read;
+///
+/// use safe_fork::{ForkBuilder, StdChild, Stdio};
+///
+/// let mut child = StdChild::from(
+///     ForkBuilder::new()
+///         .stdout(Stdio::Piped)
+///         .exec("echo", ["hello"])
+///         .unwrap(),
+/// );
+/// let mut output = String::new();
+/// child.stdout.take().unwrap().read_to_string(&mut output).unwrap();
+/// assert!(child.wait().unwrap().success());
+/// assert_eq!(output, "hello\n");
+/// ```
+pub struct StdChild {
+    /// Handle for writing to the standard input of the child, if it is piped.
+    pub stdin: Option<ChildStdin>,
+    /// Handle for reading from the standard output of the child, if it is piped.
+    pub stdout: Option<ChildStdout>,
+    /// Handle for reading from the standard error of the child, if it is piped.
+    pub stderr: Option<ChildStderr>,
+    inner: Child,
+}
+
+impl StdChild {
+    /// Returns the OS-assigned process identifier associated with this child.
+    pub fn id(&self) -> u32 {
+        self.inner.pid()
+    }
+
+    /// Forces the child to exit by sending `SIGKILL`.
+    ///
+    /// If the child has already exited, this is a no-op.
+    pub fn kill(&mut self) -> Result<()> {
+        self.inner.kill()
+    }
+
+    /// Waits for the child to exit completely, returning the status that it exited with.
+    ///
+    /// The stdin handle, if any, is closed before waiting, so that a child reading from it sees
+    /// end-of-file.
+    pub fn wait(&mut self) -> Result<ExitStatus> {
+        drop(self.stdin.take());
+        loop {
+            match self.inner.join_interruptible() {
+                Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                result => return result.map(ExitStatus::from),
+            }
+        }
+    }
+
+    /// Returns the status of the child if it has exited, without blocking.
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        Ok(self.inner.try_join()?.map(ExitStatus::from))
+    }
+
+    /// Waits for the child to exit, collecting all remaining output on piped stdout and stderr.
+    ///
+    /// See [`Child::wait_with_output`].
+    pub fn wait_with_output(self) -> Result<Output> {
+        self.into_inner().wait_with_output()
+    }
+
+    /// Returns the underlying [`Child`], with the stdio handles that have not been taken.
+    pub fn into_inner(self) -> Child {
+        let mut inner = self.inner;
+        inner.stdin = self.stdin;
+        inner.stdout = self.stdout;
+        inner.stderr = self.stderr;
+        inner
+    }
+}
+
+impl From<Child> for StdChild {
+    fn from(mut child: Child) -> Self {
+        Self {
+            stdin: child.stdin.take(),
+            stdout: child.stdout.take(),
+            stderr: child.stderr.take(),
+            inner: child,
+        }
+    }
+}
+
+impl From<StdChild> for Child {
+    fn from(child: StdChild) -> Self {
+        child.into_inner()
+    }
+}
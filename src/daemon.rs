@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{ForkBuilder, ForkResult, Stdio, Termination};
+
+/// Builder for spawning a daemon process.
+///
+/// The classic double fork is performed: the intermediate child starts a new session and forks
+/// again, so the daemon is not a session leader and cannot acquire a controlling terminal. The
+/// daemon runs with `/` as its working directory and with standard I/O redirected to `/dev/null`.
+#[derive(Debug, Default)]
+pub struct Daemon {
+    pidfile: Option<PathBuf>,
+}
+
+impl Daemon {
+    /// Creates a new builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the PID of the daemon to the given file before the closure runs.
+    pub fn pidfile(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.pidfile = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Spawn the provided closure in a daemon process, returning the PID of the daemon.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn spawn<T: Termination>(&mut self, f: impl FnOnce() -> T) -> Result<u32> {
+        // The daemon changes its working directory, so resolve relative paths beforehand.
+        let pidfile = self
+            .pidfile
+            .as_deref()
+            .map(std::path::absolute)
+            .transpose()?;
+        let (reader, mut writer) = crate::pipe()?;
+        let mut reader = Some(reader);
+
+        let child = ForkBuilder::new()
+            .chdir("/")
+            .stdin(Stdio::Null)
+            .stdout(Stdio::Null)
+            .stderr(Stdio::Null)
+            .spawn(|| {
+                reader.take();
+                // SAFETY: `setsid` does not have special safety requirements.
+                let forked = match unsafe { libc::setsid() } {
+                    -1 => Err(Error::last_os_error()),
+                    _ => crate::fork().map_err(Error::from),
+                };
+                match forked {
+                    Ok(ForkResult::Parent(daemon)) => {
+                        // The daemon is reparented once we exit, so leave it alone.
+                        daemon.detach();
+                        0
+                    }
+                    Ok(ForkResult::Child) => {
+                        let pid = std::process::id();
+                        if let Some(path) = &pidfile {
+                            if let Err(err) = std::fs::write(path, format!("{pid}\n")) {
+                                report(&mut writer, Err(err));
+                                return 1;
+                            }
+                        }
+                        report(&mut writer, Ok(pid));
+                        drop(writer);
+                        std::process::exit(f().report());
+                    }
+                    Err(err) => {
+                        report(&mut writer, Err(err));
+                        1
+                    }
+                }
+            })?;
+
+        // The write end is moved into the closure and has been dropped along with it.
+        let mut buf = [0; 4];
+        let read = reader.unwrap().read_exact(&mut buf);
+        child.join()?;
+
+        match read {
+            Ok(()) => match i32::from_ne_bytes(buf) {
+                pid if pid > 0 => Ok(pid as u32),
+                errno => Err(Error::from_raw_os_error(-errno)),
+            },
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                Err(Error::other("daemon exited before reporting its PID"))
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Report either the PID of the daemon, or the error that prevents it from starting.
+fn report(writer: &mut File, result: Result<u32>) {
+    let value = match result {
+        Ok(pid) => pid as i32,
+        Err(err) => -err.raw_os_error().unwrap_or(libc::EINVAL),
+    };
+    let _ = writer.write_all(&value.to_ne_bytes());
+}
+
+/// Spawn the provided closure in a daemon process, returning the PID of the daemon.
+///
+/// See [`Daemon`] for details.
+pub fn daemonize<T: Termination>(f: impl FnOnce() -> T) -> Result<u32> {
+    Daemon::new().spawn(f)
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+use crate::{ChildPanicked, ChildStatus};
+
+/// Error returned when a child process cannot be forked or waited for.
+///
+/// It can be converted into an [`std::io::Error`]; errors wrapping an I/O error convert into the
+/// wrapped error, so the error kind and OS error code are preserved.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ForkError {
+    /// The process has multiple threads, so forking is not safe.
+    MultiThreaded,
+    /// The `fork` or `clone` system call failed, e.g. due to resource limits.
+    ForkFailed(Error),
+    /// Setting up the child process before running the closure failed.
+    SetupFailed(Error),
+    /// Executing the program in the child process failed.
+    ExecFailed(Error),
+    /// Waiting for the child process failed.
+    WaitFailed(Error),
+    /// The child process did not finish before its deadline, and was killed.
+    TimedOut,
+    /// The child process exceeded its CPU time limit, and was killed.
+    CpuLimitExceeded,
+    /// The child process exceeded its memory limit, and failed to allocate or was killed.
+    MemoryLimitExceeded,
+    /// The child process exited unsuccessfully or was terminated by a signal before sending back
+    /// its result.
+    ChildFailed(ChildStatus),
+    /// The child process panicked, and the panic was captured as with
+    /// [`ForkBuilder::capture_panics`](crate::ForkBuilder::capture_panics).
+    Panicked(ChildPanicked),
+    /// Other I/O errors, e.g. failure to create pipes for the child process.
+    Io(Error),
+}
+
+impl fmt::Display for ForkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ForkError::MultiThreaded => write!(f, "cannot fork a multi-threaded process"),
+            ForkError::ForkFailed(err) => write!(f, "failed to fork: {err}"),
+            ForkError::SetupFailed(err) => write!(f, "failed to set up child process: {err}"),
+            ForkError::ExecFailed(err) => write!(f, "failed to execute program: {err}"),
+            ForkError::WaitFailed(err) => write!(f, "failed to wait for child process: {err}"),
+            ForkError::TimedOut => write!(f, "child process timed out"),
+            ForkError::CpuLimitExceeded => write!(f, "child process exceeded its CPU time limit"),
+            ForkError::MemoryLimitExceeded => write!(f, "child process exceeded its memory limit"),
+            ForkError::ChildFailed(status) => write!(f, "child process failed: {status}"),
+            ForkError::Panicked(panicked) => panicked.fmt(f),
+            ForkError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ForkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ForkError::MultiThreaded
+            | ForkError::TimedOut
+            | ForkError::CpuLimitExceeded
+            | ForkError::MemoryLimitExceeded
+            | ForkError::ChildFailed(_)
+            | ForkError::Panicked(_) => None,
+            ForkError::ForkFailed(err)
+            | ForkError::SetupFailed(err)
+            | ForkError::ExecFailed(err)
+            | ForkError::WaitFailed(err) => Some(err),
+            ForkError::Io(err) => err.source(),
+        }
+    }
+}
+
+impl ForkError {
+    /// Classify an error from waiting for a child, which reports a captured panic as an I/O error
+    /// wrapping [`ChildPanicked`].
+    pub(crate) fn from_wait(err: Error) -> Self {
+        match err.get_ref().and_then(|err| err.downcast_ref()) {
+            Some(panicked) => ForkError::Panicked(ChildPanicked::clone(panicked)),
+            None => ForkError::WaitFailed(err),
+        }
+    }
+}
+
+impl From<Error> for ForkError {
+    fn from(err: Error) -> Self {
+        ForkError::Io(err)
+    }
+}
+
+impl From<ForkError> for Error {
+    fn from(err: ForkError) -> Self {
+        match err {
+            ForkError::MultiThreaded => Error::other(ForkError::MultiThreaded),
+            ForkError::TimedOut => Error::new(ErrorKind::TimedOut, ForkError::TimedOut),
+            ForkError::CpuLimitExceeded => {
+                Error::new(ErrorKind::QuotaExceeded, ForkError::CpuLimitExceeded)
+            }
+            ForkError::MemoryLimitExceeded => {
+                Error::new(ErrorKind::OutOfMemory, ForkError::MemoryLimitExceeded)
+            }
+            ForkError::ChildFailed(status) => Error::other(ForkError::ChildFailed(status)),
+            // Waiting for a child through an I/O API reports the panic directly.
+            ForkError::Panicked(panicked) => Error::other(panicked),
+            ForkError::ForkFailed(err)
+            | ForkError::SetupFailed(err)
+            | ForkError::ExecFailed(err)
+            | ForkError::WaitFailed(err)
+            | ForkError::Io(err) => err,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::ffi::{CString, OsStr};
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::ffi::OsStrExt;
+
+use crate::Child;
+
+/// Null-terminated array of C strings, as taken by the `exec` family of functions.
+pub(crate) struct CStringArray {
+    strings: Vec<CString>,
+    ptrs: Vec<*const libc::c_char>,
+}
+
+impl CStringArray {
+    /// Create an array from the strings, failing if any of them contains a NUL byte.
+    pub(crate) fn new<S: AsRef<OsStr>>(strings: impl IntoIterator<Item = S>) -> Result<Self> {
+        let strings = strings
+            .into_iter()
+            .map(|s| {
+                CString::new(s.as_ref().as_bytes())
+                    .map_err(|err| Error::new(ErrorKind::InvalidInput, err))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // The pointers remain valid when the vector is moved, as the strings are heap-allocated.
+        let ptrs = strings
+            .iter()
+            .map(|s| s.as_ptr())
+            .chain([std::ptr::null()])
+            .collect();
+        Ok(Self { strings, ptrs })
+    }
+
+    /// Create an argument vector, with `program` as `argv[0]`.
+    pub(crate) fn argv<S: AsRef<OsStr>>(
+        program: &OsStr,
+        args: impl IntoIterator<Item = S>,
+    ) -> Result<Self> {
+        let mut strings = vec![program.to_owned()];
+        strings.extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        Self::new(strings)
+    }
+
+    /// Returns the first string, which is the program for an argument vector.
+    pub(crate) fn program(&self) -> *const libc::c_char {
+        self.strings[0].as_ptr()
+    }
+
+    pub(crate) fn as_ptr(&self) -> *const *const libc::c_char {
+        self.ptrs.as_ptr()
+    }
+}
+
+/// Spawn a child process executing `program` with `args` and exactly the environment variables in
+/// `env`.
+///
+/// `program` is searched in `PATH` if it does not contain a slash. Use [`ForkBuilder::exec`] to
+/// configure the child process further before the program is executed.
+///
+/// As the child executes a new program immediately, this uses `posix_spawn` instead of forking:
+/// the child shares the memory of the parent until it calls `execve`, so the cost of copying the
+/// page tables of large processes is avoided. For the same reason, the calling process does not
+/// need to be single-threaded.
+///
+/// [`ForkBuilder::exec`]: crate::ForkBuilder::exec
+pub fn fork_exec<S, K, V>(
+    program: impl AsRef<OsStr>,
+    args: impl IntoIterator<Item = S>,
+    env: impl IntoIterator<Item = (K, V)>,
+) -> Result<Child>
+where
+    S: AsRef<OsStr>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let argv = CStringArray::argv(program.as_ref(), args)?;
+    let envp = CStringArray::new(env.into_iter().map(|(key, val)| {
+        let mut var = key.as_ref().to_owned();
+        var.push("=");
+        var.push(val);
+        var
+    }))?;
+    let child = posix_spawn(&argv, &envp, None)?;
+    trace!(debug, pid = child.pid(), program = ?program.as_ref(), "spawned program");
+    Ok(child)
+}
+
+/// Spawn a child process with `posix_spawnp`, executing the program in `argv` with the environment
+/// in `envp`.
+pub(crate) fn posix_spawn(
+    argv: &CStringArray,
+    envp: &CStringArray,
+    config: Option<&SpawnConfig>,
+) -> Result<Child> {
+    crate::child::reap_orphans();
+    crate::registry::acquire()?;
+
+    let (actions, attr) = match config {
+        Some(config) => (&raw const config.actions, &raw const config.attr),
+        None => (std::ptr::null(), std::ptr::null()),
+    };
+    let mut pid = 0;
+    crate::metrics::fork_attempted();
+    // SAFETY: `argv` and `envp` are null-terminated arrays of valid C strings, and `config` holds
+    // initialized file actions and attributes. glibc creates the child with
+    // `CLONE_VM | CLONE_VFORK` and takes care of signal handlers, which is safe even in
+    // multi-threaded processes.
+    let ret = unsafe {
+        libc::posix_spawnp(
+            &mut pid,
+            argv.program(),
+            actions,
+            attr,
+            argv.as_ptr() as *const *mut libc::c_char,
+            envp.as_ptr() as *const *mut libc::c_char,
+        )
+    };
+    if ret != 0 {
+        crate::metrics::fork_failed(ret);
+        return Err(Error::from_raw_os_error(ret));
+    }
+    Ok(Child::new(pid))
+}
+
+/// File actions and attributes passed to `posix_spawn`.
+pub(crate) struct SpawnConfig {
+    actions: libc::posix_spawn_file_actions_t,
+    attr: libc::posix_spawnattr_t,
+    flags: libc::c_int,
+}
+
+impl SpawnConfig {
+    pub(crate) fn new() -> Result<Self> {
+        // SAFETY: all-zero is a valid value for both, which are then initialized.
+        let mut config: Self = unsafe { std::mem::zeroed() };
+        // SAFETY: `config.actions` is valid for the duration of the call.
+        check(unsafe { libc::posix_spawn_file_actions_init(&mut config.actions) })?;
+        // SAFETY: `config.attr` is valid for the duration of the call.
+        let ret = unsafe { libc::posix_spawnattr_init(&mut config.attr) };
+        if ret != 0 {
+            // SAFETY: `config.actions` is initialized, and not used afterwards.
+            unsafe { libc::posix_spawn_file_actions_destroy(&mut config.actions) };
+            return Err(Error::from_raw_os_error(ret));
+        }
+        Ok(config)
+    }
+
+    fn add_flags(&mut self, flags: libc::c_int) -> Result<()> {
+        self.flags |= flags;
+        // SAFETY: `self.attr` is initialized.
+        check(unsafe { libc::posix_spawnattr_setflags(&mut self.attr, self.flags as _) })
+    }
+
+    /// Put the child in a new process group.
+    pub(crate) fn new_process_group(&mut self) -> Result<()> {
+        // SAFETY: `self.attr` is initialized.
+        check(unsafe { libc::posix_spawnattr_setpgroup(&mut self.attr, 0) })?;
+        self.add_flags(libc::POSIX_SPAWN_SETPGROUP)
+    }
+
+    /// Put the child in a new session.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn new_session(&mut self) -> Result<()> {
+        self.add_flags(libc::POSIX_SPAWN_SETSID as _)
+    }
+
+    /// Reset the signals in `set` to their default disposition in the child.
+    pub(crate) fn signal_default(&mut self, set: &libc::sigset_t) -> Result<()> {
+        // SAFETY: `self.attr` is initialized, and `set` is valid for the duration of the call.
+        check(unsafe { libc::posix_spawnattr_setsigdefault(&mut self.attr, set) })?;
+        self.add_flags(libc::POSIX_SPAWN_SETSIGDEF)
+    }
+
+    /// Set the signal mask of the child.
+    pub(crate) fn signal_mask(&mut self, set: &libc::sigset_t) -> Result<()> {
+        // SAFETY: `self.attr` is initialized, and `set` is valid for the duration of the call.
+        check(unsafe { libc::posix_spawnattr_setsigmask(&mut self.attr, set) })?;
+        self.add_flags(libc::POSIX_SPAWN_SETSIGMASK)
+    }
+
+    /// Change the working directory of the child.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn chdir(&mut self, dir: &OsStr) -> Result<()> {
+        let dir =
+            CString::new(dir.as_bytes()).map_err(|err| Error::new(ErrorKind::InvalidInput, err))?;
+        // SAFETY: `self.actions` is initialized, and the path is copied by the call.
+        check(unsafe {
+            libc::posix_spawn_file_actions_addchdir_np(&mut self.actions, dir.as_ptr())
+        })
+    }
+
+    /// Duplicate `fd` onto `target` in the child.
+    pub(crate) fn dup2(&mut self, fd: libc::c_int, target: libc::c_int) -> Result<()> {
+        // SAFETY: `self.actions` is initialized.
+        check(unsafe { libc::posix_spawn_file_actions_adddup2(&mut self.actions, fd, target) })
+    }
+}
+
+impl Drop for SpawnConfig {
+    fn drop(&mut self) {
+        // SAFETY: both are initialized, and not used afterwards.
+        unsafe {
+            libc::posix_spawn_file_actions_destroy(&mut self.actions);
+            libc::posix_spawnattr_destroy(&mut self.attr);
+        }
+    }
+}
+
+/// Convert an error number returned by the `posix_spawn` family of functions into a result.
+fn check(ret: libc::c_int) -> Result<()> {
+    match ret {
+        0 => Ok(()),
+        _ => Err(Error::from_raw_os_error(ret)),
+    }
+}
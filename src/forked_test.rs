@@ -0,0 +1,55 @@
+use std::ffi::OsStr;
+use std::process::Command;
+
+use crate::Termination;
+
+/// Environment variable naming the test to run, set when re-executing the test binary.
+const TEST_ENV: &str = "__SAFE_FORK_TEST";
+
+/// Run the body of a test annotated with `#[forked_test]` in a separate process, panicking if it
+/// fails.
+///
+/// `module` is the module path of the test, which includes the crate name, and `name` is the name
+/// of the test function.
+pub fn run_forked_test<T: Termination>(module: &str, name: &str, body: fn() -> T) {
+    // Test names as seen by the test harness do not include the crate name.
+    let path = match module.split_once("::") {
+        Some((_, module)) => format!("{module}::{name}"),
+        None => name.to_owned(),
+    };
+
+    // Within the re-executed test binary, this process is dedicated to the test. Exit right
+    // away, so that the outcome is not inverted by `#[should_panic]` in this process as well.
+    if std::env::var_os(TEST_ENV).as_deref() == Some(OsStr::new(&path)) {
+        crate::panic::run_child(body, crate::panic::PANIC_EXIT_CODE, None);
+    }
+
+    if crate::is_single_threaded() {
+        let code = crate::fork_join(body).expect("failed to fork test process");
+        assert_eq!(code, 0, "test process failed with code {code}");
+        return;
+    }
+
+    // The test harness runs tests on separate threads, so forking is not possible. Instead,
+    // execute the test binary again, running only this test.
+    let exe = std::env::current_exe().expect("failed to locate test binary");
+    let output = Command::new(exe)
+        .args([
+            &path,
+            "--exact",
+            "--test-threads=1",
+            "--nocapture",
+            "--include-ignored",
+        ])
+        .env(TEST_ENV, &path)
+        .output()
+        .expect("failed to execute test binary");
+    // Print through the harness, so the output is captured along with that of this test.
+    print!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    assert!(
+        output.status.success(),
+        "test process failed: {}",
+        output.status
+    );
+}
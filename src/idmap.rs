@@ -0,0 +1,98 @@
+use std::io::{Error, Result};
+use std::process::Command;
+
+/// A range of user or group IDs mapped into a user namespace.
+///
+/// IDs `inside..inside + count` in the namespace correspond to `outside..outside + count` in the
+/// parent namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IdMap {
+    /// First ID of the range in the new user namespace.
+    pub inside: u32,
+    /// First ID of the range in the parent user namespace.
+    pub outside: u32,
+    /// Number of IDs in the range.
+    pub count: u32,
+}
+
+impl IdMap {
+    /// Creates a mapping of `count` IDs starting at `inside` to IDs starting at `outside`.
+    pub const fn new(inside: u32, outside: u32, count: u32) -> Self {
+        Self {
+            inside,
+            outside,
+            count,
+        }
+    }
+}
+
+/// ID mappings of a new user namespace, written by the parent after the child is created.
+#[derive(Debug, Default)]
+pub(crate) struct IdMaps {
+    pub(crate) uid: Vec<IdMap>,
+    pub(crate) gid: Vec<IdMap>,
+    pub(crate) setgroups: Option<bool>,
+    /// Whether to use `newuidmap` and `newgidmap` instead of writing the maps directly.
+    pub(crate) helpers: bool,
+}
+
+impl IdMaps {
+    /// Whether there is anything to write.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.uid.is_empty() && self.gid.is_empty() && self.setgroups.is_none()
+    }
+
+    /// Write the mappings for the process.
+    pub(crate) fn write(&self, pid: u32) -> Result<()> {
+        if self.helpers {
+            run_helper("newuidmap", pid, &self.uid)?;
+            return run_helper("newgidmap", pid, &self.gid);
+        }
+
+        if !self.uid.is_empty() {
+            std::fs::write(format!("/proc/{pid}/uid_map"), format(&self.uid))?;
+        }
+        // Unprivileged processes can only write `gid_map` after denying `setgroups`.
+        let setgroups = match self.setgroups {
+            Some(allow) => Some(allow),
+            None if !self.gid.is_empty() => Some(false),
+            None => None,
+        };
+        if let Some(allow) = setgroups {
+            let value = if allow { "allow" } else { "deny" };
+            std::fs::write(format!("/proc/{pid}/setgroups"), value)?;
+        }
+        if !self.gid.is_empty() {
+            std::fs::write(format!("/proc/{pid}/gid_map"), format(&self.gid))?;
+        }
+        Ok(())
+    }
+}
+
+/// Format the mappings as expected by `uid_map` and `gid_map`, which must be written at once.
+fn format(maps: &[IdMap]) -> String {
+    maps.iter()
+        .map(|map| format!("{} {} {}\n", map.inside, map.outside, map.count))
+        .collect()
+}
+
+/// Run `newuidmap` or `newgidmap` to write the mappings.
+fn run_helper(program: &str, pid: u32, maps: &[IdMap]) -> Result<()> {
+    if maps.is_empty() {
+        return Ok(());
+    }
+    let mut command = Command::new(program);
+    command.arg(pid.to_string());
+    for map in maps {
+        command.args([
+            map.inside.to_string(),
+            map.outside.to_string(),
+            map.count.to_string(),
+        ]);
+    }
+    let status = command.status()?;
+    if !status.success() {
+        return Err(Error::other(format!("{program} failed: {status}")));
+    }
+    Ok(())
+}
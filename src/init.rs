@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::{ChildStatus, ForkError, Termination};
+
+/// Signals forwarded by [`pid1_init`].
+const FORWARDED: [libc::c_int; 6] = [
+    libc::SIGTERM,
+    libc::SIGINT,
+    libc::SIGHUP,
+    libc::SIGQUIT,
+    libc::SIGUSR1,
+    libc::SIGUSR2,
+];
+
+/// PID of the process that signals are forwarded to, or 0 if there is none.
+static FORWARD_TO: AtomicI32 = AtomicI32::new(0);
+
+/// Runs the closure in a child process, acting as its init process until it exits.
+///
+/// This is meant to be entered by the first process of a new PID namespace (see
+/// [`ForkBuilder::new_pid_ns`](crate::ForkBuilder::new_pid_ns)). Such a process has PID 1, so the
+/// kernel does not apply default signal actions to it, and orphaned processes in the namespace
+/// are reparented to it. While the child runs, `SIGTERM`, `SIGINT`, `SIGHUP`, `SIGQUIT`, `SIGUSR1`
+/// and `SIGUSR2` are forwarded to it, and all other children that exit are reaped.
+///
+/// Returns the exit status of the child. The previous signal handlers are restored before
+/// returning.
+///
+/// The forking process must be single-threaded. Otherwise, this call will fail.
+pub fn pid1_init<T: Termination>(f: impl FnOnce() -> T) -> Result<ChildStatus, ForkError> {
+    // Block the signals until the PID to forward to is known, so that none is lost.
+    // SAFETY: all-zero is a valid `sigset_t`.
+    let mut set: libc::sigset_t = unsafe { std::mem::zeroed() };
+    // SAFETY: all-zero is a valid `sigset_t`.
+    let mut old_set: libc::sigset_t = unsafe { std::mem::zeroed() };
+    // SAFETY: all-zero is a valid `sigaction`.
+    let mut previous: [libc::sigaction; FORWARDED.len()] = unsafe { std::mem::zeroed() };
+    // SAFETY: all-zero is a valid `sigaction`.
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = forward as extern "C" fn(libc::c_int) as _;
+    action.sa_flags = libc::SA_RESTART;
+    // SAFETY: the sets and actions are valid for the duration of the calls, and the handler only
+    // calls async-signal-safe functions.
+    unsafe {
+        libc::sigemptyset(&mut set);
+        for sig in FORWARDED {
+            libc::sigaddset(&mut set, sig);
+        }
+        libc::pthread_sigmask(libc::SIG_BLOCK, &set, &mut old_set);
+        for (sig, previous) in FORWARDED.into_iter().zip(&mut previous) {
+            libc::sigaction(sig, &action, previous);
+        }
+    }
+
+    let child = crate::fork_spawn(|| {
+        // SAFETY: restoring the default dispositions and the signal mask is always sound.
+        unsafe {
+            for sig in FORWARDED {
+                libc::signal(sig, libc::SIG_DFL);
+            }
+            libc::pthread_sigmask(libc::SIG_SETMASK, &old_set, std::ptr::null_mut());
+        }
+        f()
+    });
+
+    let status = child.and_then(|child| {
+        FORWARD_TO.store(child.pid() as _, Ordering::Release);
+        // SAFETY: restoring the signal mask is always sound.
+        unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &old_set, std::ptr::null_mut()) };
+        crate::join_reaping(child).map_err(ForkError::from_wait)
+    });
+
+    FORWARD_TO.store(0, Ordering::Release);
+    // SAFETY: `previous` holds the handlers that were installed before, and restoring the signal
+    // mask is always sound.
+    unsafe {
+        for (sig, previous) in FORWARDED.into_iter().zip(&previous) {
+            libc::sigaction(sig, previous, std::ptr::null_mut());
+        }
+        libc::pthread_sigmask(libc::SIG_SETMASK, &old_set, std::ptr::null_mut());
+    }
+    status
+}
+
+extern "C" fn forward(sig: libc::c_int) {
+    let pid = FORWARD_TO.load(Ordering::Acquire);
+    if pid > 0 {
+        // SAFETY: `errno` is thread-local, and `kill` is async-signal-safe. The errno is saved and
+        // restored so that the interrupted code is not affected.
+        unsafe {
+            let errno = *crate::sys::errno_location();
+            libc::kill(pid, sig);
+            *crate::sys::errno_location() = errno;
+        }
+    }
+}
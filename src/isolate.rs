@@ -0,0 +1,155 @@
+use std::time::Duration;
+
+use crate::{ChildPanicked, ChildStatus, ForkBuilder, ForkError, Rlimits, Termination};
+
+/// Outcome of a closure run with [`run_isolated`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IsolatedOutcome {
+    /// The closure returned, and the child exited with the given exit code.
+    Exited(i32),
+    /// The closure panicked.
+    Panicked(ChildPanicked),
+    /// The child was terminated by a signal, e.g. `SIGSEGV` or `SIGABRT`.
+    ///
+    /// This includes aborts due to allocation failures caused by a limit on the address space,
+    /// as they cannot be told apart from other aborts.
+    Crashed {
+        /// The signal that terminated the child.
+        signal: i32,
+        /// Whether a core dump was produced.
+        core_dumped: bool,
+    },
+    /// The child ran longer than the timeout, or exceeded its CPU time limit.
+    Timeout,
+    /// The child was killed by the kernel, typically by the OOM killer.
+    OutOfMemory,
+}
+
+impl IsolatedOutcome {
+    /// Returns whether the closure returned and the child exited with exit code 0.
+    pub fn success(&self) -> bool {
+        *self == IsolatedOutcome::Exited(0)
+    }
+}
+
+/// Configuration for running closures in isolated child processes, e.g. for fuzzing.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use safe_fork::{IsolatedOutcome, Isolation};
+///
+/// let outcome = Isolation::new()
+///     .timeout(Duration::from_secs(1))
+///     .run(|| "not a number".parse::<i32>().unwrap())
+///     .unwrap();
+/// assert!(matches!(outcome, IsolatedOutcome::Panicked(_)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Isolation {
+    timeout: Option<Duration>,
+    rlimits: Rlimits,
+}
+
+impl Default for Isolation {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            rlimits: Rlimits::new().core(0),
+        }
+    }
+}
+
+impl Isolation {
+    /// Creates a configuration without a timeout, where core dumps are disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Kills the child if it runs for longer than `timeout` in wall-clock time.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Applies resource limits to the child, replacing the default ones.
+    pub fn rlimits(mut self, limits: Rlimits) -> Self {
+        self.rlimits = limits;
+        self
+    }
+
+    /// Runs `f` in a child process, and classifies how it ends.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn run<T: Termination>(&self, f: impl FnOnce() -> T) -> Result<IsolatedOutcome, ForkError> {
+        let child = ForkBuilder::new()
+            .rlimits(self.rlimits.clone())
+            .capture_panics()
+            .spawn(f)?;
+
+        let status = match self.timeout {
+            None => child.join(),
+            Some(timeout) => match child.join_timeout(timeout) {
+                Ok(Ok(status)) => Ok(status),
+                Ok(Err(child)) => {
+                    let _ = child.kill();
+                    return match child.join().map_err(ForkError::from_wait) {
+                        Err(ForkError::Panicked(panicked)) => {
+                            Ok(IsolatedOutcome::Panicked(panicked))
+                        }
+                        Err(err) => Err(err),
+                        // The child may exit on its own before being killed.
+                        #[cfg(unix)]
+                        Ok(ChildStatus::Signaled {
+                            signal: libc::SIGKILL,
+                            ..
+                        }) => Ok(IsolatedOutcome::Timeout),
+                        Ok(status) => Ok(classify(status)),
+                    };
+                }
+                Err(err) => Err(err),
+            },
+        };
+
+        match status.map_err(ForkError::from_wait) {
+            Ok(status) => Ok(classify(status)),
+            Err(ForkError::Panicked(panicked)) => Ok(IsolatedOutcome::Panicked(panicked)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Runs `f` in a child process with the default [`Isolation`], and classifies how it ends.
+pub fn run_isolated<T: Termination>(f: impl FnOnce() -> T) -> Result<IsolatedOutcome, ForkError> {
+    Isolation::new().run(f)
+}
+
+/// Classify the exit status of a child that has not been killed by the parent.
+fn classify(status: ChildStatus) -> IsolatedOutcome {
+    match status {
+        ChildStatus::Exited(code) => IsolatedOutcome::Exited(code),
+        #[cfg(unix)]
+        ChildStatus::Signaled {
+            signal: libc::SIGXCPU,
+            ..
+        } => IsolatedOutcome::Timeout,
+        // Nothing else sends `SIGKILL` to the child, unless the user does so.
+        #[cfg(unix)]
+        ChildStatus::Signaled {
+            signal: libc::SIGKILL,
+            ..
+        } => IsolatedOutcome::OutOfMemory,
+        ChildStatus::Signaled {
+            signal,
+            core_dumped,
+        } => IsolatedOutcome::Crashed {
+            signal,
+            core_dumped,
+        },
+        // `join` only reports terminations.
+        ChildStatus::Stopped(_) | ChildStatus::Continued => unreachable!(),
+    }
+}
@@ -0,0 +1,157 @@
+use std::ffi::CString;
+use std::io::{Error, Result};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+
+/// Access rights that apply to files, rather than directories.
+const ACCESS_FILE: u64 = ACCESS_FS_EXECUTE
+    | ACCESS_FS_WRITE_FILE
+    | ACCESS_FS_READ_FILE
+    | ACCESS_FS_TRUNCATE
+    | ACCESS_FS_IOCTL_DEV;
+
+/// Access rights granted by [`ForkBuilder::allow_read`](crate::ForkBuilder::allow_read).
+const ACCESS_READ: u64 = ACCESS_FS_EXECUTE | ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR;
+
+const CREATE_RULESET_VERSION: u32 = 1 << 0;
+const RULE_PATH_BENEATH: libc::c_int = 1;
+
+/// `struct landlock_ruleset_attr`, limited to the fields of the first ABI version.
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+}
+
+/// `struct landlock_path_beneath_attr`.
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+/// Paths that the child process may access, enforced with Landlock.
+#[derive(Debug, Default)]
+pub(crate) struct Ruleset {
+    rules: Vec<(PathBuf, bool)>,
+}
+
+impl Ruleset {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub(crate) fn allow(&mut self, path: &Path, write: bool) {
+        self.rules.push((path.to_owned(), write));
+    }
+
+    /// Restrict the current process to the allowed paths.
+    pub(crate) fn apply(&self) -> Result<()> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+
+        // SAFETY: querying the ABI version does not have special safety requirements.
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 0 {
+            return Err(Error::last_os_error());
+        }
+        // Handle all access rights that the kernel knows about, so that they are denied unless
+        // allowed explicitly.
+        let handled = match abi {
+            1 => (1 << 13) - 1,
+            2 => (1 << 14) - 1,
+            3 | 4 => (1 << 15) - 1,
+            _ => (1 << 16) - 1,
+        };
+
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        // SAFETY: `attr` is valid for the duration of the call.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr,
+                std::mem::size_of::<RulesetAttr>(),
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(Error::last_os_error());
+        }
+        // SAFETY: the file descriptor is freshly created and exclusively owned.
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as _) };
+
+        for (path, write) in &self.rules {
+            let path = CString::new(path.as_os_str().as_bytes())
+                .map_err(|_| Error::from(std::io::ErrorKind::InvalidInput))?;
+            // SAFETY: `path` is a valid C string.
+            let fd = unsafe { libc::open(path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+            if fd < 0 {
+                return Err(Error::last_os_error());
+            }
+            // SAFETY: the file descriptor is freshly created and exclusively owned.
+            let parent = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            let mut access = if *write {
+                handled
+            } else {
+                ACCESS_READ & handled
+            };
+            // SAFETY: all-zero is a valid `stat`.
+            let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+            // SAFETY: `stat` is valid for the duration of the call.
+            if unsafe { libc::fstat(parent.as_raw_fd(), &mut stat) } < 0 {
+                return Err(Error::last_os_error());
+            }
+            if stat.st_mode & libc::S_IFMT != libc::S_IFDIR {
+                access &= ACCESS_FILE;
+            }
+
+            let attr = PathBeneathAttr {
+                allowed_access: access,
+                parent_fd: parent.as_raw_fd(),
+            };
+            // SAFETY: `attr` is valid for the duration of the call.
+            let ret = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_add_rule,
+                    ruleset.as_raw_fd(),
+                    RULE_PATH_BENEATH,
+                    &attr,
+                    0,
+                )
+            };
+            if ret < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        // SAFETY: `PR_SET_NO_NEW_PRIVS` and `landlock_restrict_self` do not have special safety
+        // requirements.
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+                return Err(Error::last_os_error());
+            }
+            if libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0) < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,77 +1,538 @@
-use std::io::{Error, Result};
-use std::os::unix::process::ExitStatusExt;
-use std::process::ExitStatus;
+#[cfg(unix)]
+use std::fs::File;
+#[cfg(unix)]
+use std::io::{Error, Read, Result};
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, OwnedFd};
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+
+/// Emits a `tracing` event at the given level, if the `tracing` feature is enabled.
+#[cfg(unix)]
+macro_rules! trace {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+)
+    };
+}
+
+#[cfg(unix)]
+mod atfork;
+#[cfg(unix)]
+mod builder;
+#[cfg(target_os = "linux")]
+mod caps;
+#[cfg(target_os = "linux")]
+mod cgroup;
+#[cfg(all(unix, feature = "serde"))]
+mod channel;
+#[cfg(unix)]
+mod child;
+#[cfg(target_os = "linux")]
+mod clone;
+#[cfg(feature = "serde")]
+mod codec;
+#[cfg(unix)]
+mod compat;
+#[cfg(unix)]
+mod daemon;
+mod error;
+#[cfg(unix)]
+mod exec;
+#[cfg(all(unix, feature = "macros"))]
+mod forked_test;
+#[cfg(target_os = "linux")]
+mod idmap;
+#[cfg(unix)]
+mod init;
+mod isolate;
+#[cfg(all(target_os = "linux", feature = "landlock"))]
+mod landlock;
+#[cfg(unix)]
+mod memory;
+mod metrics;
+#[cfg(target_os = "linux")]
+mod mount;
+#[cfg(unix)]
+mod panic;
+#[cfg(unix)]
+mod pid;
+#[cfg(all(unix, feature = "serde"))]
+mod pool;
+#[cfg(unix)]
+mod prefork;
+#[cfg(target_os = "linux")]
+mod ptrace;
+#[cfg(unix)]
+mod reaper;
+mod registry;
+mod retry;
+mod rlimit;
+#[cfg(target_os = "linux")]
+mod sched;
+mod scope;
+mod scrub;
+#[cfg(all(target_os = "linux", feature = "seccomp"))]
+mod seccomp;
+#[cfg(all(unix, feature = "serde"))]
+mod service;
+#[cfg(all(unix, any(target_os = "linux", feature = "bytemuck")))]
+mod shm;
+#[cfg(unix)]
+mod signal;
+mod status;
+#[cfg(unix)]
+mod stdio;
+#[cfg(unix)]
+mod subreaper;
+mod supervisor;
+#[cfg(target_os = "linux")]
+mod sync;
+#[cfg(unix)]
+mod sys;
+mod termination;
+mod threads;
+#[cfg(target_os = "linux")]
+mod timens;
+#[cfg(not(unix))]
+mod unsupported;
+#[cfg(all(unix, feature = "serde"))]
+mod value;
+
+#[cfg(unix)]
+pub use atfork::{register_atfork, AtForkHandle};
+#[cfg(unix)]
+pub use builder::{ForkBuilder, SpawnBackend, Stdio};
+#[cfg(target_os = "linux")]
+pub use caps::Capability;
+#[cfg(all(unix, feature = "serde"))]
+pub use channel::{fork_with_channel, fork_with_channel_codec, ForkChannel, Receiver, Sender};
+#[cfg(unix)]
+pub use child::{join_all, wait_any, Child};
+#[cfg(target_os = "linux")]
+pub use clone::CloneFlags;
+#[cfg(feature = "json")]
+pub use codec::Json;
+#[cfg(feature = "postcard")]
+pub use codec::Postcard;
+#[cfg(feature = "serde")]
+pub use codec::{Bincode, Codec};
+#[cfg(unix)]
+pub use compat::StdChild;
+#[cfg(unix)]
+pub use daemon::{daemonize, Daemon};
+pub use error::ForkError;
+#[cfg(unix)]
+pub use exec::fork_exec;
+#[cfg(target_os = "linux")]
+pub use idmap::IdMap;
+#[cfg(unix)]
+pub use init::pid1_init;
+pub use isolate::{run_isolated, IsolatedOutcome, Isolation};
+pub use metrics::{set_metrics_sink, MetricsSink};
+#[cfg(unix)]
+pub use pid::Pid;
+#[cfg(all(unix, feature = "serde"))]
+pub use pool::{ForkPool, TaskHandle};
+#[cfg(unix)]
+pub use prefork::{Accept, PreforkServer};
+#[cfg(target_os = "linux")]
+pub use ptrace::TraceEvent;
+#[cfg(unix)]
+pub use reaper::Reaper;
+#[cfg(unix)]
+pub use registry::reap_exited;
+pub use registry::{active_children, set_child_limit, ChildInfo, ChildLimit};
+pub use retry::{fork_retry, RetryPolicy};
+pub use rlimit::{Resource, Rlimits};
+/// Runs a test in a separate process, so that it can change process-global state, such as
+/// environment variables, signal handlers and the working directory, without affecting other
+/// tests.
+///
+/// Use it in place of `#[test]`. The test passes if its body returns normally, or returns a value
+/// that reports success as a [`Termination`]. Other attributes, such as `#[ignore]` and
+/// `#[should_panic]`, can be used as usual.
+///
+/// If the process is single-threaded, the body is run in a forked child, as with
+/// [`fork_join`]. The default test harness runs tests on separate threads, however, in which case
+/// the test binary is executed again to run only this test in a new process.
+///
+/// This requires the `macros` feature.
+///
+/// # Example
+///
+/// ```
+/// #[safe_fork::forked_test]
+/// fn changes_directory() {
+///     std::env::set_current_dir("/").unwrap();
+/// }
+/// ```
+#[cfg(all(unix, feature = "macros"))]
+pub use safe_fork_macros::forked_test;
+#[cfg(target_os = "linux")]
+pub use sched::{IoPriorityClass, SchedPolicy};
+pub use scope::{fork_scope, Scope, ScopedChild};
+pub use scrub::Scrubbable;
+#[cfg(all(target_os = "linux", feature = "seccomp"))]
+pub use seccomp::{SeccompAction, SeccompFilter};
+#[cfg(all(unix, feature = "serde"))]
+pub use service::{fork_service, fork_service_codec, ServiceHandle};
+#[cfg(all(unix, feature = "bytemuck"))]
+pub use shm::SharedMem;
+#[cfg(unix)]
+pub use signal::SigSet;
+pub use status::{ChildPanicked, ChildStatus, JoinReport, ResourceUsage};
+#[cfg(unix)]
+pub use stdio::{ChildStderr, ChildStdin, ChildStdout};
+#[cfg(unix)]
+pub use subreaper::{join_reaping, reap_children, set_child_subreaper};
+pub use supervisor::{Restart, RestartPolicy, Supervisor};
+#[cfg(target_os = "linux")]
+pub use sync::{Event, ProcessBarrier};
+#[cfg(all(target_os = "linux", feature = "bytemuck"))]
+pub use sync::{ProcessMutex, ProcessMutexGuard};
+pub use termination::Termination;
+pub use threads::thread_count;
+#[cfg(target_os = "linux")]
+pub use threads::{threads, ThreadInfo};
+#[cfg(target_os = "linux")]
+pub use timens::TimeOffsets;
+#[cfg(not(unix))]
+pub use unsupported::*;
+#[cfg(all(unix, feature = "serde"))]
+pub use value::{
+    fork_join_result, fork_join_result_codec, fork_join_value, fork_join_value_codec, fork_map,
+    fork_map_iter, fork_map_iter_codec, snapshot, snapshot_codec, ForkMap, ForkMapIter,
+    SnapshotResult,
+};
+
+#[cfg(all(unix, feature = "macros"))]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::forked_test::run_forked_test;
+}
+
+/// Ensures the current process is single-threaded.
+///
+/// Fails with [`ForkError::MultiThreaded`] if there are other threads.
+#[cfg(target_os = "linux")]
+pub fn ensure_single_threaded() -> std::result::Result<(), ForkError> {
+    match sys::unshare_vm() {
+        Ok(()) => Ok(()),
+        // Unsharing the address space is only rejected with `EINVAL` if it is shared with other
+        // threads.
+        Err(err) if err.raw_os_error() == Some(libc::EINVAL) => Err(ForkError::MultiThreaded),
+        Err(err) => Err(ForkError::Io(err)),
+    }
+}
 
 /// Ensures the current process is single-threaded.
-pub fn ensure_single_threaded() -> Result<()> {
-    // SAFETY: `unshare` does not have special safety requirements.
-    if unsafe { libc::unshare(libc::CLONE_VM) } == 0 {
-        Ok(())
-    } else {
-        Err(Error::last_os_error())
+///
+/// Fails with [`ForkError::MultiThreaded`] if there are other threads.
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn ensure_single_threaded() -> std::result::Result<(), ForkError> {
+    match thread_count()? {
+        1 => Ok(()),
+        _ => Err(ForkError::MultiThreaded),
     }
 }
 
 /// Check if the current process is single-threaded.
+#[cfg(unix)]
 pub fn is_single_threaded() -> bool {
     ensure_single_threaded().is_ok()
 }
 
-/// Representation of a forked child process.
-///
-/// This is a thin wrapper of the raw PID to provide the `join` helper function.
-pub struct Child {
-    pid: libc::pid_t,
+/// Result of [`fork`].
+#[cfg(unix)]
+#[must_use]
+#[derive(Debug)]
+pub enum ForkResult {
+    /// Returned in the parent process, with the handle of the child.
+    Parent(Child),
+    /// Returned in the child process.
+    Child,
 }
 
-impl Child {
-    /// Returns the OS-assigned process identifier associated with this child.
-    pub fn pid(&self) -> u32 {
-        self.pid as _
-    }
-
-    /// Waits for the child to exit completely, returning the status that it
-    /// exited with.
-    pub fn join(self) -> Result<ExitStatus> {
-        // SAFETY: `waitpid` does not have special safety requirements.
-        let mut status = 0;
-        let ret = unsafe { libc::waitpid(self.pid, &mut status, 0) };
-        if ret < 0 {
-            return Err(std::io::Error::last_os_error());
+#[cfg(unix)]
+impl ForkResult {
+    fn into_parent(self) -> Option<Child> {
+        match self {
+            ForkResult::Parent(child) => Some(child),
+            ForkResult::Child => None,
         }
-        Ok(ExitStatus::from_raw(status))
     }
 }
 
 /// Fork the current process.
 ///
 /// The forking process must be single-threaded. Otherwise, this call will fail.
-pub fn fork() -> Result<Option<Child>> {
-    ensure_single_threaded()?;
+///
+/// # Example
+///
+/// ```
+/// use safe_fork::ForkResult;
+///
+/// match safe_fork::fork().unwrap() {
+///     ForkResult::Parent(child) => assert!(child.join().unwrap().success()),
+///     ForkResult::Child => std::process::exit(0),
+/// }
+/// ```
+#[cfg(unix)]
+pub fn fork() -> std::result::Result<ForkResult, ForkError> {
+    let hooks = prepare_fork()?;
 
+    metrics::fork_attempted();
     // SAFETY: fork is safe for single-threaded process.
     match unsafe { libc::fork() } {
-        -1 => Err(std::io::Error::last_os_error()),
-        0 => Ok(None),
-        pid => Ok(Some(Child { pid })),
+        -1 => {
+            let err = Error::last_os_error();
+            hooks.parent();
+            metrics::fork_failed(err.raw_os_error().unwrap_or(0));
+            trace!(warn, error = %err, "failed to fork");
+            Err(ForkError::ForkFailed(err))
+        }
+        0 => {
+            child::forget_orphans();
+            hooks.child();
+            Ok(ForkResult::Child)
+        }
+        pid => {
+            hooks.parent();
+            trace!(debug, pid, "forked child");
+            Ok(ForkResult::Parent(Child::new(pid)))
+        }
     }
 }
 
+/// Common steps to perform before forking.
+///
+/// Returns the at-fork hooks to run once forked.
+#[cfg(unix)]
+fn prepare_fork() -> std::result::Result<atfork::Pending, ForkError> {
+    ensure_single_threaded()?;
+    child::reap_orphans();
+    registry::acquire().map_err(ForkError::ForkFailed)?;
+
+    // Flush buffered output so it is not written twice, once by each process.
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    Ok(atfork::prepare())
+}
+
 /// Fork the current process, and execute the provided closure within child process.
-pub fn fork_spawn(f: impl FnOnce() -> i32) -> Result<Child> {
+#[cfg(unix)]
+pub fn fork_spawn<T: Termination>(f: impl FnOnce() -> T) -> std::result::Result<Child, ForkError> {
     Ok(match fork()? {
-        Some(c) => c,
-        None => {
-            std::process::exit(f());
-        }
+        ForkResult::Parent(c) => c,
+        ForkResult::Child => panic::run_child(f, panic::PANIC_EXIT_CODE, None),
     })
 }
 
+/// Fork the current process, and execute `f` within the child process, exiting with the code that
+/// it returns.
+///
+/// Unlike [`fork_spawn`], nothing that allocates, takes locks or formats runs in the child before
+/// `f`: at-fork handlers registered with [`register_atfork`] are not run, and the state of this
+/// crate inherited from the parent is left untouched. The child then exits with `_exit`, without
+/// running `atexit` handlers or flushing buffered output. If `f` unwinds, the child aborts instead
+/// of returning into the code of the parent.
+///
+/// As the child does not rely on any other thread, the forking process does not need to be
+/// single-threaded.
+///
+/// # Safety
+///
+/// If the forking process is multi-threaded, `f` must only call async-signal-safe functions. In
+/// particular, it must not allocate, and it must not panic since the panic hook formats and
+/// allocates before unwinding starts. In any case, other functions of this crate must not be used
+/// in the child.
+///
+/// # Example
+///
+/// ```
+/// // SAFETY: `_exit` is async-signal-safe, and no other functions are called.
+/// let child = unsafe { safe_fork::fork_spawn_raw(|| 3) }.unwrap();
+/// assert_eq!(child.join().unwrap().code(), Some(3));
+/// ```
+#[cfg(unix)]
+pub unsafe fn fork_spawn_raw(f: impl FnOnce() -> i32) -> std::result::Result<Child, ForkError> {
+    child::reap_orphans();
+    registry::acquire().map_err(ForkError::ForkFailed)?;
+
+    metrics::fork_attempted();
+    // SAFETY: the child only runs `f`, which the caller guarantees to be safe after forking a
+    // multi-threaded process, and exits without returning.
+    match unsafe { libc::fork() } {
+        -1 => {
+            let err = Error::last_os_error();
+            metrics::fork_failed(err.raw_os_error().unwrap_or(0));
+            trace!(warn, error = %err, "failed to fork");
+            Err(ForkError::ForkFailed(err))
+        }
+        0 => {
+            /// Aborts the process when dropped, i.e. when unwinding out of `f`.
+            struct AbortOnDrop;
+
+            impl Drop for AbortOnDrop {
+                fn drop(&mut self) {
+                    // SAFETY: `abort` is async-signal-safe.
+                    unsafe { libc::abort() }
+                }
+            }
+
+            let guard = AbortOnDrop;
+            let code = f();
+            std::mem::forget(guard);
+            // SAFETY: `_exit` is async-signal-safe.
+            unsafe { libc::_exit(code) }
+        }
+        pid => {
+            trace!(debug, pid, "forked child");
+            Ok(Child::new(pid))
+        }
+    }
+}
+
 /// Fork the current process, and execute the provided closure within child process, and wait for it to complete.
-pub fn fork_join(f: impl FnOnce() -> i32) -> Result<i32> {
-    let exit = fork_spawn(f)?.join()?;
-    Ok(exit
-        .code()
-        .or_else(|| exit.signal().map(|x| x + 128))
-        .unwrap_or(1))
+#[cfg(unix)]
+pub fn fork_join<T: Termination>(f: impl FnOnce() -> T) -> std::result::Result<i32, ForkError> {
+    Ok(exit_code(
+        fork_spawn(f)?.join().map_err(ForkError::from_wait)?,
+    ))
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete for at most `deadline`.
+///
+/// The child is put in its own process group. If it does not complete in time, it is killed along
+/// with its descendants in the process group, as with [`Child::kill_tree`], and this fails with
+/// [`ForkError::TimedOut`]. Waiting does not need extra threads in the parent.
+#[cfg(unix)]
+pub fn fork_join_deadline<T: Termination>(
+    f: impl FnOnce() -> T,
+    deadline: Duration,
+) -> std::result::Result<i32, ForkError> {
+    let child = ForkBuilder::new().new_process_group().spawn(f)?;
+    match child.join_timeout(deadline).map_err(ForkError::from_wait)? {
+        Ok(status) => Ok(exit_code(status)),
+        Err(child) => {
+            child.kill_tree().map_err(ForkError::Io)?;
+            child.join().map_err(ForkError::from_wait)?;
+            Err(ForkError::TimedOut)
+        }
+    }
+}
+
+/// Convert the exit status of a child into an exit code, as done by shells.
+#[cfg(unix)]
+fn exit_code(status: ChildStatus) -> i32 {
+    match status {
+        ChildStatus::Exited(code) => code,
+        ChildStatus::Signaled { signal, .. } => signal + 128,
+        _ => 1,
+    }
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete, collecting its standard output and standard error.
+///
+/// The standard input of the child is redirected to `/dev/null`.
+#[cfg(unix)]
+pub fn fork_output<T: Termination>(
+    f: impl FnOnce() -> T,
+) -> std::result::Result<std::process::Output, ForkError> {
+    ForkBuilder::new()
+        .stdin(Stdio::Null)
+        .stdout(Stdio::Piped)
+        .stderr(Stdio::Piped)
+        .spawn(f)?
+        .wait_with_output()
+        .map_err(ForkError::from_wait)
+}
+
+/// Create a pipe, returning the read end and the write end.
+///
+/// Both ends are close-on-exec.
+#[cfg(unix)]
+fn pipe() -> Result<(File, File)> {
+    let (reader, writer) = std::io::pipe()?;
+    Ok((OwnedFd::from(reader).into(), OwnedFd::from(writer).into()))
+}
+
+/// Set the file descriptor to non-blocking mode.
+#[cfg(unix)]
+fn set_nonblocking(fd: std::os::fd::RawFd) -> Result<()> {
+    // SAFETY: `fcntl` does not have special safety requirements.
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Wait for events on the file descriptors, for at most `timeout` if given.
+///
+/// Returns the number of file descriptors with events, or 0 if the timeout elapses. Interruptions
+/// by signals are retried.
+#[cfg(unix)]
+fn poll(fds: &mut [libc::pollfd], timeout: Option<Duration>) -> Result<usize> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        let timeout = match deadline {
+            // Round up so that we never return before the timeout actually elapses.
+            Some(deadline) => deadline
+                .saturating_duration_since(Instant::now())
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .try_into()
+                .unwrap_or(libc::c_int::MAX),
+            None => -1,
+        };
+        // SAFETY: `fds` is valid for the duration of the call.
+        let ret = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout) };
+        if ret >= 0 {
+            return Ok(ret as usize);
+        }
+        let err = Error::last_os_error();
+        if err.kind() != std::io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Read all pipes to the end concurrently, so that no writer blocks on a full pipe.
+///
+/// Data read from each pipe is appended to the corresponding buffer, and pipes are set to `None`
+/// as they reach EOF.
+#[cfg(unix)]
+fn read_all(pipes: &mut [Option<File>], bufs: &mut [Vec<u8>]) -> Result<()> {
+    let mut chunk = [0; 8192];
+
+    while pipes.iter().any(Option::is_some) {
+        let mut pollfds: Vec<_> = pipes
+            .iter()
+            .map(|pipe| libc::pollfd {
+                fd: pipe.as_ref().map_or(-1, |pipe| pipe.as_raw_fd()),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        poll(&mut pollfds, None)?;
+
+        for ((pipe, buf), pollfd) in pipes.iter_mut().zip(bufs.iter_mut()).zip(&pollfds) {
+            if pollfd.revents == 0 {
+                continue;
+            }
+            // The poll result guarantees that this does not block.
+            match pipe.as_mut().unwrap().read(&mut chunk) {
+                Ok(0) => *pipe = None,
+                Ok(len) => buf.extend_from_slice(&chunk[..len]),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    Ok(())
 }
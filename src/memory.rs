@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::os::fd::IntoRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsFd, OwnedFd};
+
+/// Means of telling whether a child exceeded the limit set with
+/// [`ForkBuilder::memory_limit`](crate::ForkBuilder::memory_limit).
+#[derive(Debug)]
+pub(crate) enum MemoryWatch {
+    /// Read end of the pipe that the child writes to when it aborts after failing to allocate.
+    Abort(File),
+    /// The cgroup of the child, and its number of OOM kills before the child was spawned.
+    #[cfg(target_os = "linux")]
+    Cgroup { dir: OwnedFd, before: u64 },
+}
+
+impl MemoryWatch {
+    /// Returns whether the exited child exceeded its memory limit.
+    pub(crate) fn exceeded(&self, signal: Option<i32>) -> bool {
+        match self {
+            MemoryWatch::Abort(file) => {
+                if signal != Some(libc::SIGABRT) {
+                    return false;
+                }
+                // The pipe is non-blocking, as descendants of the child may hold the write end.
+                let mut buf = [0];
+                matches!((&*file).read(&mut buf), Ok(1))
+            }
+            #[cfg(target_os = "linux")]
+            MemoryWatch::Cgroup { dir, before } => {
+                crate::cgroup::oom_kills(dir.as_fd()).is_ok_and(|after| after > *before)
+            }
+        }
+    }
+}
+
+/// Write end of the pipe used by [`on_abort`].
+static ABORT_FD: AtomicI32 = AtomicI32::new(-1);
+
+/// Report aborts caused by failing to allocate into `writer`.
+///
+/// Rust aborts the process when an allocation fails, after the allocator fails with `ENOMEM`. The
+/// `SIGABRT` handler checks the error number left behind, and writes into `writer` if it matches.
+pub(crate) fn report_alloc_failure(writer: File) {
+    ABORT_FD.store(writer.into_raw_fd(), Ordering::Relaxed);
+    // SAFETY: all-zero is a valid `sigaction`.
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = on_abort as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESETHAND;
+    // SAFETY: the handler is async-signal-safe, and `action` is valid for the duration of the call.
+    // This cannot fail, as the signal and the flags are valid.
+    unsafe { libc::sigaction(libc::SIGABRT, &action, std::ptr::null_mut()) };
+}
+
+extern "C" fn on_abort(_: libc::c_int) {
+    // Reading the error number does not allocate.
+    if std::io::Error::last_os_error().kind() == ErrorKind::OutOfMemory {
+        // SAFETY: `write` is async-signal-safe, and the buffer is valid for the duration of the
+        // call. There is nothing to do if it fails.
+        unsafe { libc::write(ABORT_FD.load(Ordering::Relaxed), [1u8].as_ptr().cast(), 1) };
+    }
+    // `abort` raises `SIGABRT` again once the handler returns, now with the default disposition.
+}
@@ -0,0 +1,52 @@
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::ChildStatus;
+
+/// The installed metrics sink, if any.
+static SINK: RwLock<Option<Arc<dyn MetricsSink>>> = RwLock::new(None);
+
+/// Receiver of metrics about the children created by this crate, e.g. to export them to a
+/// monitoring system.
+///
+/// All methods do nothing by default, so implementations only need to override the ones they are
+/// interested in. They are called synchronously, so they should be cheap.
+pub trait MetricsSink: Send + Sync {
+    /// Called right before creating a child with `fork`, `clone` or `posix_spawn`.
+    fn fork_attempted(&self) {}
+
+    /// Called when creating a child fails with the given OS error code.
+    fn fork_failed(&self, _errno: i32) {}
+
+    /// Called when a child is reaped, with its exit status and the time since it was created.
+    fn child_reaped(&self, _status: ChildStatus, _lifetime: Duration) {}
+}
+
+/// Installs the sink that metrics are reported to, replacing the previous one.
+///
+/// Pass `None` to stop reporting metrics. Forked children inherit the sink.
+pub fn set_metrics_sink(sink: Option<Arc<dyn MetricsSink>>) {
+    *SINK.write().unwrap() = sink;
+}
+
+#[cfg(unix)]
+fn with_sink(f: impl FnOnce(&dyn MetricsSink)) {
+    if let Some(sink) = &*SINK.read().unwrap() {
+        f(&**sink);
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn fork_attempted() {
+    with_sink(|sink| sink.fork_attempted());
+}
+
+#[cfg(unix)]
+pub(crate) fn fork_failed(errno: i32) {
+    with_sink(|sink| sink.fork_failed(errno));
+}
+
+#[cfg(unix)]
+pub(crate) fn child_reaped(status: ChildStatus, lifetime: Duration) {
+    with_sink(|sink| sink.child_reaped(status, lifetime));
+}
@@ -0,0 +1,148 @@
+use std::ffi::CString;
+use std::io::{Error, Result};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+/// A mount to perform in the new mount namespace of the child.
+#[derive(Debug)]
+enum Mount {
+    Tmpfs(PathBuf),
+    Bind {
+        src: PathBuf,
+        dst: PathBuf,
+        readonly: bool,
+    },
+}
+
+/// Mounts to perform in the new mount namespace of the child.
+#[derive(Debug, Default)]
+pub(crate) struct Mounts {
+    readonly_root: bool,
+    mounts: Vec<Mount>,
+    pivot_root: Option<PathBuf>,
+}
+
+impl Mounts {
+    pub(crate) fn is_empty(&self) -> bool {
+        !self.readonly_root && self.mounts.is_empty() && self.pivot_root.is_none()
+    }
+
+    pub(crate) fn readonly_root(&mut self) {
+        self.readonly_root = true;
+    }
+
+    pub(crate) fn pivot_root(&mut self, path: &Path) {
+        self.pivot_root = Some(path.to_owned());
+    }
+
+    pub(crate) fn tmpfs(&mut self, path: &Path) {
+        self.mounts.push(Mount::Tmpfs(path.to_owned()));
+    }
+
+    pub(crate) fn bind(&mut self, src: &Path, dst: &Path, readonly: bool) {
+        self.mounts.push(Mount::Bind {
+            src: src.to_owned(),
+            dst: dst.to_owned(),
+            readonly,
+        });
+    }
+
+    /// Perform the mounts within the child process.
+    pub(crate) fn apply(&self) -> Result<()> {
+        if !self.readonly_root && self.mounts.is_empty() && self.pivot_root.is_none() {
+            return Ok(());
+        }
+
+        // Stop mount events from propagating back to the parent namespace.
+        mount(None, Path::new("/"), None, libc::MS_REC | libc::MS_PRIVATE)?;
+
+        if self.readonly_root {
+            remount_readonly(Path::new("/"))?;
+        }
+        for entry in &self.mounts {
+            match entry {
+                Mount::Tmpfs(path) => mount(Some(Path::new("tmpfs")), path, Some("tmpfs"), 0)?,
+                Mount::Bind { src, dst, readonly } => {
+                    mount(Some(src), dst, None, libc::MS_BIND | libc::MS_REC)?;
+                    if *readonly {
+                        remount_readonly(dst)?;
+                    }
+                }
+            }
+        }
+
+        if let Some(new_root) = &self.pivot_root {
+            // The new root must be a mount point.
+            mount(Some(new_root), new_root, None, libc::MS_BIND | libc::MS_REC)?;
+            std::env::set_current_dir(new_root)?;
+            // Stack the old root on top of the new one, and then detach it.
+            let dot = c".".as_ptr();
+            // SAFETY: `dot` is a valid C string.
+            unsafe {
+                if libc::syscall(libc::SYS_pivot_root, dot, dot) < 0 {
+                    return Err(Error::last_os_error());
+                }
+                if libc::umount2(dot, libc::MNT_DETACH) < 0 {
+                    return Err(Error::last_os_error());
+                }
+            }
+            std::env::set_current_dir("/")?;
+        }
+        Ok(())
+    }
+}
+
+/// Remount the mount at `path` as read-only.
+///
+/// The flags that are locked in a user namespace must be preserved, so they are carried over from
+/// the existing mount.
+fn remount_readonly(path: &Path) -> Result<()> {
+    let path_c = cstring(path)?;
+    // SAFETY: all-zero is a valid `statvfs`.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path_c` is a valid C string and `stat` is valid for the duration of the call.
+    if unsafe { libc::statvfs(path_c.as_ptr(), &mut stat) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    let mut flags = libc::MS_REMOUNT | libc::MS_BIND | libc::MS_RDONLY;
+    for (st, ms) in [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ] {
+        if stat.f_flag & st != 0 {
+            flags |= ms;
+        }
+    }
+    mount(None, path, None, flags)
+}
+
+fn mount(src: Option<&Path>, dst: &Path, fstype: Option<&str>, flags: libc::c_ulong) -> Result<()> {
+    let src = src.map(cstring).transpose()?;
+    let dst = cstring(dst)?;
+    let fstype = fstype.map(|fstype| CString::new(fstype).unwrap());
+    // SAFETY: all pointers are either null or valid C strings for the duration of the call.
+    let ret = unsafe {
+        libc::mount(
+            src.as_ref().map_or(ptr::null(), |src| src.as_ptr()),
+            dst.as_ptr(),
+            fstype
+                .as_ref()
+                .map_or(ptr::null(), |fstype| fstype.as_ptr()),
+            flags,
+            ptr::null(),
+        )
+    };
+    if ret < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn cstring(path: &Path) -> Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| std::io::ErrorKind::InvalidInput.into())
+}
@@ -0,0 +1,85 @@
+use std::backtrace::Backtrace;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::os::fd::AsRawFd;
+use std::sync::Mutex;
+
+use crate::{ChildPanicked, Termination};
+
+/// Exit code of a child process whose closure panics, same as a panicking Rust program.
+pub(crate) const PANIC_EXIT_CODE: i32 = 101;
+
+/// Backtrace captured by the panic hook, to be sent along with the panic message.
+static BACKTRACE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Run the closure within the child process and exit, with `panic_code` if the closure panics.
+///
+/// If `report` is given, the panic message and backtrace are written into it.
+pub(crate) fn run_child<T: Termination>(
+    f: impl FnOnce() -> T,
+    panic_code: i32,
+    report: Option<File>,
+) -> ! {
+    if report.is_some() {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            *BACKTRACE.lock().unwrap_or_else(|err| err.into_inner()) =
+                Some(Backtrace::force_capture().to_string());
+            hook(info);
+        }));
+    }
+
+    let code = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(value) => value.report(),
+        Err(payload) => {
+            if let Some(mut report) = report {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("Box<dyn Any>");
+                let backtrace = BACKTRACE
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .take()
+                    .unwrap_or_default();
+
+                let mut buf = Vec::new();
+                buf.extend_from_slice(&(message.len() as u64).to_le_bytes());
+                buf.extend_from_slice(message.as_bytes());
+                buf.extend_from_slice(backtrace.as_bytes());
+                // The parent only reads after we exit, so never block on a full pipe. If the
+                // report is truncated, the backtrace is cut short.
+                if crate::set_nonblocking(report.as_raw_fd()).is_ok() {
+                    let _ = report.write_all(&buf);
+                }
+            }
+            panic_code
+        }
+    };
+    std::process::exit(code)
+}
+
+/// Read the panic report of an exited child, if it panicked.
+pub(crate) fn read_report(mut report: File) -> Result<Option<ChildPanicked>> {
+    let mut buf = Vec::new();
+    match report.read_to_end(&mut buf) {
+        Ok(_) => (),
+        // Descendants of the child may still hold the write end.
+        Err(err) if err.kind() == ErrorKind::WouldBlock => (),
+        Err(err) => return Err(err),
+    }
+    if buf.is_empty() {
+        return Ok(None);
+    }
+
+    let invalid = || Error::new(ErrorKind::InvalidData, "malformed panic report");
+    let (len, rest) = buf.split_first_chunk::<8>().ok_or_else(invalid)?;
+    let len = usize::try_from(u64::from_le_bytes(*len)).map_err(|_| invalid())?;
+    // A message too long for the pipe is truncated.
+    let (message, backtrace) = rest.split_at(len.min(rest.len()));
+    Ok(Some(ChildPanicked {
+        message: String::from_utf8_lossy(message).into_owned(),
+        backtrace: String::from_utf8_lossy(backtrace).into_owned(),
+    }))
+}
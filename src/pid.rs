@@ -0,0 +1,119 @@
+use std::fmt;
+use std::io::{Error, ErrorKind, Result};
+
+use crate::Child;
+
+/// Identifier of a process.
+///
+/// Unlike a raw integer, a `Pid` always refers to a valid process ID: it is positive, so it can
+/// never be confused with the special values that `kill` and `waitpid` accept for process groups
+/// and "any process".
+///
+/// A PID may be reused once the process has been reaped, so prefer holding on to the [`Child`]
+/// handle where possible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pid(libc::pid_t);
+
+impl Pid {
+    /// Returns the PID of the current process.
+    pub fn current() -> Self {
+        // SAFETY: `getpid` does not have special safety requirements.
+        Pid(unsafe { libc::getpid() })
+    }
+
+    /// Returns the PID of the parent of the current process.
+    ///
+    /// Returns `None` if the parent is outside the PID namespace of the current process, e.g. for
+    /// the first process of a new PID namespace, in which case `getppid` returns 0.
+    pub fn parent() -> Option<Self> {
+        // SAFETY: `getppid` does not have special safety requirements.
+        let pid = unsafe { libc::getppid() };
+        (pid > 0).then_some(Pid(pid))
+    }
+
+    /// Returns the raw PID.
+    pub fn as_raw(self) -> libc::pid_t {
+        self.0
+    }
+
+    /// Checks whether a process with this PID exists, using `kill` with signal 0.
+    ///
+    /// Processes that have exited but are not reaped yet still exist. Processes that the current
+    /// process is not permitted to signal are reported as existing.
+    pub fn is_alive(self) -> Result<bool> {
+        // SAFETY: `kill` with signal 0 does not send a signal.
+        if unsafe { libc::kill(self.0, 0) } == 0 {
+            return Ok(true);
+        }
+        let err = Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::ESRCH) => Ok(false),
+            Some(libc::EPERM) => Ok(true),
+            _ => Err(err),
+        }
+    }
+}
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<&Child> for Pid {
+    fn from(child: &Child) -> Self {
+        Pid(child.pid() as _)
+    }
+}
+
+impl From<Pid> for u32 {
+    fn from(pid: Pid) -> Self {
+        pid.0 as u32
+    }
+}
+
+impl From<Pid> for i32 {
+    fn from(pid: Pid) -> Self {
+        pid.0
+    }
+}
+
+impl TryFrom<i32> for Pid {
+    type Error = Error;
+
+    /// Fails with [`ErrorKind::InvalidInput`] if `pid` is not positive.
+    fn try_from(pid: i32) -> Result<Self> {
+        if pid <= 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid process ID"));
+        }
+        Ok(Pid(pid))
+    }
+}
+
+impl TryFrom<u32> for Pid {
+    type Error = Error;
+
+    /// Fails with [`ErrorKind::InvalidInput`] if `pid` is zero or too large.
+    fn try_from(pid: u32) -> Result<Self> {
+        i32::try_from(pid)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid process ID"))?
+            .try_into()
+    }
+}
+
+#[cfg(feature = "nix")]
+impl From<Pid> for nix::unistd::Pid {
+    fn from(pid: Pid) -> Self {
+        nix::unistd::Pid::from_raw(pid.0)
+    }
+}
+
+#[cfg(feature = "nix")]
+impl TryFrom<nix::unistd::Pid> for Pid {
+    type Error = Error;
+
+    /// Fails with [`ErrorKind::InvalidInput`] if `pid` is not positive.
+    fn try_from(pid: nix::unistd::Pid) -> Result<Self> {
+        pid.as_raw().try_into()
+    }
+}
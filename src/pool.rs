@@ -0,0 +1,401 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Error, ErrorKind, Result};
+use std::os::fd::{AsFd, AsRawFd};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::channel::{Receiver, Sender};
+use crate::{Bincode, Child, Codec, ForkResult};
+
+/// A pool of pre-forked worker processes.
+///
+/// Each worker runs the handler on the tasks it receives and sends back the results. Tasks wait in
+/// a queue shared by all workers, and are sent to an idle worker, one at a time, in a round-robin
+/// manner; a slow task therefore does not hold up the tasks submitted after it. Workers
+/// that die are respawned automatically; the task that the worker was running fails with an
+/// error. Tasks that run for too long, or are cancelled with [`TaskHandle::cancel`], have their
+/// worker killed and respawned in the same way.
+///
+/// Workers are forked from the process creating the pool, so the handler may use any state that
+/// was available at that point. Dropping the pool kills the workers still running tasks, and waits
+/// for all workers to exit.
+///
+/// Tasks and results are serialized with the codec `C`, see [`with_codec`](Self::with_codec).
+pub struct ForkPool<Req, Resp, C = Bincode> {
+    inner: RefCell<Inner<Req, Resp, C>>,
+}
+
+struct Inner<Req, Resp, C> {
+    handler: Box<dyn Fn(Req) -> Resp>,
+    workers: Vec<Worker<Req, Resp, C>>,
+    /// Worker to consider first when sending a task.
+    next_worker: usize,
+    /// Tasks not yet sent to any worker, with their timeouts.
+    queue: VecDeque<(u64, Req, Option<Duration>)>,
+    next_id: u64,
+    /// Results of completed tasks that have not been claimed yet.
+    completed: HashMap<u64, Result<Resp>>,
+    /// Tasks whose handles are dropped, so their results are discarded.
+    discarded: HashSet<u64>,
+}
+
+struct Worker<Req, Resp, C> {
+    child: Child,
+    tx: Sender<Req, C>,
+    rx: Receiver<Resp, C>,
+    /// Task currently running on the worker.
+    running: Option<u64>,
+    /// Time by which the running task must complete.
+    deadline: Option<Instant>,
+}
+
+impl<Req, Resp> ForkPool<Req, Resp>
+where
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Spawns a pool with `workers` worker processes running `handler`.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn new(workers: usize, handler: impl Fn(Req) -> Resp + 'static) -> Result<Self> {
+        Self::with_codec(workers, handler)
+    }
+}
+
+impl<Req, Resp, C> ForkPool<Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Spawns a pool with `workers` worker processes running `handler`, serializing the tasks and
+    /// the results with the codec `C`.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use safe_fork::{Bincode, ForkPool};
+    ///
+    /// let pool = ForkPool::<_, _, Bincode>::with_codec(2, |x: u32| x + 1).unwrap();
+    /// assert_eq!(pool.call(41).unwrap(), 42);
+    /// ```
+    pub fn with_codec(workers: usize, handler: impl Fn(Req) -> Resp + 'static) -> Result<Self> {
+        if workers == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "pool must have at least one worker",
+            ));
+        }
+
+        let mut inner = Inner {
+            handler: Box::new(handler),
+            workers: Vec::with_capacity(workers),
+            next_worker: 0,
+            queue: VecDeque::new(),
+            next_id: 0,
+            completed: HashMap::new(),
+            discarded: HashSet::new(),
+        };
+        for _ in 0..workers {
+            let worker = inner.spawn_worker()?;
+            inner.workers.push(worker);
+        }
+        Ok(Self {
+            inner: RefCell::new(inner),
+        })
+    }
+
+    /// Returns the number of workers in the pool.
+    pub fn workers(&self) -> usize {
+        self.inner.borrow().workers.len()
+    }
+
+    /// Submits a task to the pool, returning a handle to wait for its result.
+    pub fn submit(&self, req: Req) -> Result<TaskHandle<'_, Req, Resp, C>> {
+        self.submit_inner(req, None)
+    }
+
+    /// Submits a task to the pool that may run for at most `timeout`.
+    ///
+    /// The timeout starts when a worker starts running the task, so time spent waiting for an idle
+    /// worker does not count. If it elapses, the worker is killed and respawned, and the task
+    /// fails with [`ErrorKind::TimedOut`].
+    pub fn submit_timeout(
+        &self,
+        req: Req,
+        timeout: Duration,
+    ) -> Result<TaskHandle<'_, Req, Resp, C>> {
+        self.submit_inner(req, Some(timeout))
+    }
+
+    fn submit_inner(
+        &self,
+        req: Req,
+        timeout: Option<Duration>,
+    ) -> Result<TaskHandle<'_, Req, Resp, C>> {
+        let mut inner = self.inner.borrow_mut();
+        let id = inner.next_id;
+        inner.next_id += 1;
+
+        inner.queue.push_back((id, req, timeout));
+        inner.dispatch()?;
+
+        Ok(TaskHandle { pool: self, id })
+    }
+
+    /// Runs a task on the pool and waits for its result.
+    pub fn call(&self, req: Req) -> Result<Resp> {
+        self.submit(req)?.join()
+    }
+
+    /// Runs all tasks on the pool, returning the results in order.
+    pub fn map(&self, reqs: impl IntoIterator<Item = Req>) -> Result<Vec<Resp>> {
+        let handles = reqs
+            .into_iter()
+            .map(|req| self.submit(req))
+            .collect::<Result<Vec<_>>>()?;
+        handles.into_iter().map(TaskHandle::join).collect()
+    }
+}
+
+impl<Req, Resp, C> Inner<Req, Resp, C> {
+    /// Record the result of a task.
+    fn complete(&mut self, id: u64, result: Result<Resp>) {
+        if !self.discarded.remove(&id) {
+            self.completed.insert(id, result);
+        }
+    }
+}
+
+impl<Req, Resp, C> Inner<Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    fn spawn_worker(&mut self) -> Result<Worker<Req, Resp, C>> {
+        let ((tx, rx), (child_tx, child_rx)) = crate::channel::channel::<Req, Resp, C>()?;
+        let ForkResult::Parent(child) = crate::fork()? else {
+            // Close the parent's endpoints to other workers, so they observe EOF once the parent
+            // closes them.
+            drop((tx, rx));
+            self.workers.clear();
+
+            let code = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| loop {
+                match child_rx.recv() {
+                    Ok(req) => {
+                        if child_tx.send(&(self.handler)(req)).is_err() {
+                            return 1;
+                        }
+                    }
+                    Err(err) if err.kind() == ErrorKind::UnexpectedEof => return 0,
+                    Err(_) => return 1,
+                }
+            }))
+            .unwrap_or(101);
+            std::process::exit(code);
+        };
+
+        Ok(Worker {
+            child,
+            tx,
+            rx,
+            running: None,
+            deadline: None,
+        })
+    }
+
+    /// Send queued tasks to idle workers, until either runs out.
+    fn dispatch(&mut self) -> Result<()> {
+        while !self.queue.is_empty() {
+            let len = self.workers.len();
+            let Some(index) = (0..len)
+                .map(|i| (self.next_worker + i) % len)
+                .find(|&index| self.workers[index].running.is_none())
+            else {
+                return Ok(());
+            };
+            self.next_worker = (index + 1) % len;
+
+            let (id, req, timeout) = self.queue.pop_front().unwrap();
+            let worker = &mut self.workers[index];
+            if worker.tx.send(&req).is_ok() {
+                worker.running = Some(id);
+                worker.deadline = timeout.map(|timeout| Instant::now() + timeout);
+            } else {
+                // The worker died while idle. The task has not run, so retry on a new worker.
+                self.queue.push_front((id, req, timeout));
+                self.respawn(index)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace a dead worker with a new one, failing the task it was running.
+    fn respawn(&mut self, index: usize) -> Result<()> {
+        let worker = self.spawn_worker()?;
+        let dead = std::mem::replace(&mut self.workers[index], worker);
+        drop((dead.tx, dead.rx));
+        let status = dead.child.join()?;
+        if let Some(id) = dead.running {
+            let err = Error::other(format!("worker process died: {status}"));
+            self.complete(id, Err(err));
+        }
+        Ok(())
+    }
+
+    /// Fail a task with `err`, killing and respawning the worker if the task is running.
+    fn abort(&mut self, id: u64, err: Error) -> Result<()> {
+        if let Some(index) = self.workers.iter().position(|w| w.running == Some(id)) {
+            let worker = &mut self.workers[index];
+            worker.running = None;
+            worker.deadline = None;
+            worker.child.kill()?;
+            self.complete(id, Err(err));
+            self.respawn(index)?;
+            return self.dispatch();
+        }
+        if let Some(pos) = self.queue.iter().position(|&(i, ..)| i == id) {
+            self.queue.remove(pos);
+            self.complete(id, Err(err));
+        }
+        Ok(())
+    }
+
+    /// Fail the running tasks whose deadlines have passed, returning whether there were any.
+    fn expire(&mut self) -> Result<bool> {
+        let now = Instant::now();
+        let expired: Vec<u64> = self
+            .workers
+            .iter()
+            .filter(|w| w.deadline.is_some_and(|deadline| deadline <= now))
+            .filter_map(|w| w.running)
+            .collect();
+        for &id in &expired {
+            self.abort(id, Error::new(ErrorKind::TimedOut, "task timed out"))?;
+        }
+        Ok(!expired.is_empty())
+    }
+
+    /// Wait for at least one running task to complete or time out.
+    fn poll(&mut self) -> Result<()> {
+        if self.expire()? {
+            return Ok(());
+        }
+
+        let busy: Vec<usize> = (0..self.workers.len())
+            .filter(|&index| self.workers[index].running.is_some())
+            .collect();
+        if busy.is_empty() {
+            return Err(Error::other("no task is running"));
+        }
+
+        let mut pollfds: Vec<libc::pollfd> = busy
+            .iter()
+            .map(|&index| libc::pollfd {
+                fd: self.workers[index].rx.as_fd().as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        let timeout = busy
+            .iter()
+            .filter_map(|&index| self.workers[index].deadline)
+            .min()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        if crate::poll(&mut pollfds, timeout)? == 0 {
+            self.expire()?;
+            return Ok(());
+        }
+
+        for (&index, pollfd) in busy.iter().zip(&pollfds) {
+            if pollfd.revents == 0 {
+                continue;
+            }
+            let worker = &mut self.workers[index];
+            match worker.rx.recv() {
+                Ok(resp) => {
+                    let id = worker.running.take().unwrap();
+                    worker.deadline = None;
+                    self.complete(id, Ok(resp));
+                }
+                Err(_) => self.respawn(index)?,
+            }
+        }
+        self.dispatch()
+    }
+}
+
+impl<Req, Resp, C> Drop for Inner<Req, Resp, C> {
+    fn drop(&mut self) {
+        // Closing the channels tells the workers to exit, once they finish their running tasks.
+        // The results of these tasks can no longer be claimed, so do not wait for them.
+        for worker in std::mem::take(&mut self.workers) {
+            if worker.running.is_some() {
+                let _ = worker.child.kill();
+            }
+            drop((worker.tx, worker.rx));
+            let _ = worker.child.join();
+        }
+    }
+}
+
+/// Handle to a task submitted to a [`ForkPool`].
+///
+/// If the handle is dropped, the task still runs but its result is discarded.
+pub struct TaskHandle<'a, Req, Resp, C = Bincode> {
+    pool: &'a ForkPool<Req, Resp, C>,
+    id: u64,
+}
+
+impl<Req, Resp, C> TaskHandle<'_, Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Waits for the task to complete, returning its result.
+    pub fn join(self) -> Result<Resp> {
+        let this = std::mem::ManuallyDrop::new(self);
+        let mut inner = this.pool.inner.borrow_mut();
+        loop {
+            if let Some(result) = inner.completed.remove(&this.id) {
+                return result;
+            }
+            if let Err(err) = inner.poll() {
+                // The handle is consumed, so the result can no longer be claimed.
+                inner.discarded.insert(this.id);
+                return Err(err);
+            }
+        }
+    }
+
+    /// Cancels the task.
+    ///
+    /// If the task is running, the worker running it is killed and respawned. If it has not
+    /// started yet, it is removed from the queue. If it has already completed, its result is
+    /// discarded.
+    pub fn cancel(self) -> Result<()> {
+        let this = std::mem::ManuallyDrop::new(self);
+        let mut inner = this.pool.inner.borrow_mut();
+        if inner.completed.remove(&this.id).is_some() {
+            return Ok(());
+        }
+        inner.discarded.insert(this.id);
+        inner.abort(this.id, ErrorKind::Interrupted.into())
+    }
+}
+
+impl<Req, Resp, C> Drop for TaskHandle<'_, Req, Resp, C> {
+    fn drop(&mut self) {
+        let mut inner = self.pool.inner.borrow_mut();
+        if inner.completed.remove(&self.id).is_none() {
+            inner.discarded.insert(self.id);
+        }
+    }
+}
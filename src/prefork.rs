@@ -0,0 +1,233 @@
+use std::cell::RefCell;
+#[cfg(target_os = "linux")]
+use std::io::Error;
+use std::io::{ErrorKind, Result};
+#[cfg(target_os = "linux")]
+use std::net::SocketAddr;
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::AsFd;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::rc::Rc;
+
+use crate::{ForkError, Restart, RestartPolicy, Supervisor};
+
+/// A listening socket that connections can be accepted from.
+pub trait Accept: AsFd {
+    /// Type of the accepted connections.
+    type Stream;
+
+    /// Accepts a new incoming connection.
+    fn accept_stream(&self) -> Result<Self::Stream>;
+}
+
+impl Accept for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept_stream(&self) -> Result<TcpStream> {
+        self.accept().map(|(stream, _)| stream)
+    }
+}
+
+impl Accept for UnixListener {
+    type Stream = UnixStream;
+
+    fn accept_stream(&self) -> Result<UnixStream> {
+        self.accept().map(|(stream, _)| stream)
+    }
+}
+
+/// A prefork server, where a fixed number of supervised children accept connections from a
+/// listening socket that they inherit.
+///
+/// Each child runs an accept loop and calls the handler for every connection. A child exits when
+/// accepting fails with an unexpected error, or when the handler panics; by default, it is then
+/// restarted.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::Write;
+/// use std::net::TcpListener;
+///
+/// use safe_fork::PreforkServer;
+///
+/// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+/// let mut supervisor = PreforkServer::new(listener, 4)
+///     .spawn(|mut stream| {
+///         let _ = stream.write_all(b"hello\n");
+///     })
+///     .unwrap();
+/// supervisor.run().unwrap();
+/// ```
+pub struct PreforkServer<L> {
+    listener: L,
+    workers: usize,
+    policy: RestartPolicy,
+    /// Creates a listener for each child other than the first, if `SO_REUSEPORT` is used.
+    #[cfg(target_os = "linux")]
+    rebind: Option<fn(&L) -> Result<L>>,
+}
+
+impl<L: Accept + 'static> PreforkServer<L> {
+    /// Creates a server that accepts connections from `listener` in `workers` children.
+    ///
+    /// The children are always restarted after they exit.
+    pub fn new(listener: L, workers: usize) -> Self {
+        Self {
+            listener,
+            workers,
+            policy: RestartPolicy::new(Restart::Always),
+            #[cfg(target_os = "linux")]
+            rebind: None,
+        }
+    }
+
+    /// Sets the policy that the children are restarted with.
+    pub fn restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Forks the children, and returns the supervisor that they are spawned in.
+    ///
+    /// Call [`Supervisor::run`] to keep the children running, and [`Supervisor::shutdown`] to stop
+    /// them. Each child calls `handler` with the connections that it accepts, one at a time.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn spawn(
+        self,
+        handler: impl FnMut(L::Stream) + 'static,
+    ) -> std::result::Result<Supervisor, ForkError> {
+        #[cfg(target_os = "linux")]
+        if self.rebind.is_some() {
+            set_reuse_port(self.listener.as_fd())?;
+        }
+
+        let listener = Rc::new(self.listener);
+        let handler = Rc::new(RefCell::new(handler));
+        let mut supervisor = Supervisor::new();
+        // The first child uses the original listener.
+        #[cfg(target_os = "linux")]
+        let mut rebind = None;
+        for _ in 0..self.workers {
+            let listener = listener.clone();
+            let handler = handler.clone();
+            #[cfg(target_os = "linux")]
+            let rebind = std::mem::replace(&mut rebind, self.rebind);
+            supervisor.spawn(self.policy.clone(), move || -> Result<()> {
+                #[cfg(target_os = "linux")]
+                if let Some(rebind) = rebind {
+                    let own = rebind(&*listener)?;
+                    return accept_loop(&own, &mut *handler.borrow_mut());
+                }
+                accept_loop(&*listener, &mut *handler.borrow_mut())
+            })?;
+        }
+        Ok(supervisor)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl PreforkServer<TcpListener> {
+    /// Gives each child its own listening socket bound to the same address with `SO_REUSEPORT`,
+    /// so that the kernel balances incoming connections between the children.
+    ///
+    /// The first child keeps using the original listener, so connections queued on it are still
+    /// accepted.
+    pub fn reuse_port(mut self, reuse: bool) -> Self {
+        self.rebind = reuse.then_some(rebind_tcp as fn(&TcpListener) -> Result<TcpListener>);
+        self
+    }
+}
+
+fn accept_loop<L: Accept>(listener: &L, handler: &mut impl FnMut(L::Stream)) -> Result<()> {
+    loop {
+        match listener.accept_stream() {
+            Ok(stream) => handler(stream),
+            // The connection may be reset before being accepted.
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::Interrupted | ErrorKind::ConnectionAborted
+                ) => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_reuse_port(fd: BorrowedFd<'_>) -> Result<()> {
+    let value: libc::c_int = 1;
+    // SAFETY: `value` is valid for the duration of the call.
+    let ret = unsafe {
+        libc::setsockopt(
+            fd.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_REUSEPORT,
+            (&raw const value).cast(),
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Creates a listener bound to the same address as `listener`, with `SO_REUSEPORT`.
+#[cfg(target_os = "linux")]
+fn rebind_tcp(listener: &TcpListener) -> Result<TcpListener> {
+    // SAFETY: all-zero is a valid `sockaddr_storage`.
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    let (family, len) = match listener.local_addr()? {
+        SocketAddr::V4(addr) => {
+            let sin = libc::sockaddr_in {
+                sin_family: libc::AF_INET as _,
+                sin_port: addr.port().to_be(),
+                sin_addr: libc::in_addr {
+                    s_addr: u32::from(*addr.ip()).to_be(),
+                },
+                sin_zero: [0; 8],
+            };
+            // SAFETY: `sockaddr_storage` is large enough and suitably aligned for any socket
+            // address.
+            unsafe { (&raw mut storage).cast::<libc::sockaddr_in>().write(sin) };
+            (libc::AF_INET, std::mem::size_of::<libc::sockaddr_in>())
+        }
+        SocketAddr::V6(addr) => {
+            let sin6 = libc::sockaddr_in6 {
+                sin6_family: libc::AF_INET6 as _,
+                sin6_port: addr.port().to_be(),
+                sin6_flowinfo: addr.flowinfo(),
+                sin6_addr: libc::in6_addr {
+                    s6_addr: addr.ip().octets(),
+                },
+                sin6_scope_id: addr.scope_id(),
+            };
+            // SAFETY: `sockaddr_storage` is large enough and suitably aligned for any socket
+            // address.
+            unsafe { (&raw mut storage).cast::<libc::sockaddr_in6>().write(sin6) };
+            (libc::AF_INET6, std::mem::size_of::<libc::sockaddr_in6>())
+        }
+    };
+
+    // SAFETY: `socket` does not have special safety requirements.
+    let fd = unsafe { libc::socket(family, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: `fd` is a newly created socket owned by nobody else.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    set_reuse_port(fd.as_fd())?;
+    // SAFETY: `storage` holds a socket address of `len` bytes.
+    if unsafe { libc::bind(fd.as_raw_fd(), (&raw const storage).cast(), len as _) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    // SAFETY: `listen` does not have special safety requirements.
+    if unsafe { libc::listen(fd.as_raw_fd(), libc::SOMAXCONN) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(TcpListener::from(fd))
+}
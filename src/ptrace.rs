@@ -0,0 +1,195 @@
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{Child, ChildStatus};
+
+/// `PTRACE_GET_SYSCALL_INFO`, which is not defined by all C libraries.
+const PTRACE_GET_SYSCALL_INFO: libc::c_uint = 0x420e;
+const PTRACE_SYSCALL_INFO_ENTRY: u8 = 1;
+const PTRACE_SYSCALL_INFO_EXIT: u8 = 2;
+
+/// `struct ptrace_syscall_info`, with the union flattened.
+#[repr(C)]
+struct SyscallInfo {
+    op: u8,
+    pad: [u8; 3],
+    arch: u32,
+    instruction_pointer: u64,
+    stack_pointer: u64,
+    data: [u64; 8],
+}
+
+/// An event of a child traced with [`ForkBuilder::traced`](crate::ForkBuilder::traced).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TraceEvent {
+    /// The child executed a new program.
+    Exec,
+    /// A signal is about to be delivered to the child.
+    ///
+    /// It is delivered when the child is resumed, unless suppressed with
+    /// [`Child::suppress_signal`].
+    Signal(i32),
+    /// The child entered a system call.
+    SyscallEnter {
+        /// Number of the system call.
+        nr: u64,
+        /// Arguments of the system call.
+        args: [u64; 6],
+    },
+    /// The child is returning from a system call.
+    SyscallExit {
+        /// Return value of the system call, which is a negated error number on failure.
+        ret: i64,
+        /// Whether the system call failed.
+        is_error: bool,
+    },
+    /// The child exited, and has been reaped.
+    Exited(ChildStatus),
+}
+
+/// State of a traced child.
+#[derive(Debug)]
+pub(crate) struct Trace {
+    syscalls: bool,
+    /// Whether the child is in a ptrace-stop, waiting to be resumed.
+    stopped: bool,
+    /// Signal to deliver when the child is resumed.
+    signal: libc::c_int,
+}
+
+impl Trace {
+    /// Attach to the child, which must not have started its setup yet.
+    pub(crate) fn seize(pid: libc::pid_t, syscalls: bool) -> Result<Self> {
+        let options = libc::PTRACE_O_TRACEEXEC | libc::PTRACE_O_TRACESYSGOOD;
+        // SAFETY: `PTRACE_SEIZE` does not have special safety requirements.
+        if unsafe { libc::ptrace(libc::PTRACE_SEIZE as _, pid, 0, options) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(Self {
+            syscalls,
+            stopped: false,
+            signal: 0,
+        })
+    }
+
+    /// Request a stop of the child, after which system calls are traced once it is resumed.
+    ///
+    /// System calls cannot be traced during setup, as the parent waits for the child at the same
+    /// time.
+    pub(crate) fn start(&self, pid: libc::pid_t) -> Result<()> {
+        // SAFETY: `PTRACE_INTERRUPT` does not have special safety requirements.
+        if self.syscalls && unsafe { libc::ptrace(libc::PTRACE_INTERRUPT as _, pid, 0, 0) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Record that the child entered a ptrace-stop with the wait status `status`.
+    pub(crate) fn stopped(&mut self, status: libc::c_int) {
+        self.stopped = true;
+        // Only signal-delivery-stops carry a signal to deliver, other stops have an event or are
+        // system call stops.
+        let sig = libc::WSTOPSIG(status);
+        self.signal = match status >> 16 == 0 && sig != libc::SIGTRAP | 0x80 {
+            true => sig,
+            false => 0,
+        };
+    }
+
+    /// Resume the child if it is stopped.
+    pub(crate) fn resume(&mut self, pid: libc::pid_t) -> Result<()> {
+        if !self.stopped {
+            return Ok(());
+        }
+        let request = match self.syscalls {
+            true => libc::PTRACE_SYSCALL,
+            false => libc::PTRACE_CONT,
+        };
+        // SAFETY: resuming a tracee does not have special safety requirements.
+        if unsafe { libc::ptrace(request as _, pid, 0, self.signal) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        self.stopped = false;
+        self.signal = 0;
+        Ok(())
+    }
+}
+
+impl Child {
+    /// Resumes the traced child if it is stopped, and waits for its next event.
+    ///
+    /// The child stays stopped at the returned event until this is called again, or until it is
+    /// waited for otherwise, e.g. with [`join`](Self::join), which resumes it until it exits.
+    ///
+    /// Fails with [`ErrorKind::InvalidInput`] if the child is not spawned with
+    /// [`ForkBuilder::traced`](crate::ForkBuilder::traced).
+    pub fn next_trace_event(&mut self) -> Result<TraceEvent> {
+        let pid = self.pid() as libc::pid_t;
+        if let Some(status) = self.reaped_status() {
+            return Ok(TraceEvent::Exited(status));
+        }
+        self.trace
+            .as_mut()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "child is not traced"))?
+            .resume(pid)?;
+        loop {
+            let (status, report) = match self.wait_raw(0) {
+                Ok(result) => result.expect("blocking wait returned no status"),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            if !libc::WIFSTOPPED(status) {
+                return Ok(TraceEvent::Exited(report.status));
+            }
+            let trace = self.trace.as_mut().unwrap();
+            trace.stopped(status);
+            if libc::WSTOPSIG(status) == libc::SIGTRAP | 0x80 {
+                if let Some(event) = syscall_event(pid)? {
+                    return Ok(event);
+                }
+            } else {
+                match status >> 16 {
+                    0 => return Ok(TraceEvent::Signal(libc::WSTOPSIG(status))),
+                    libc::PTRACE_EVENT_EXEC => return Ok(TraceEvent::Exec),
+                    // Group-stops and stops requested with `PTRACE_INTERRUPT` are not reported.
+                    _ => (),
+                }
+            }
+            trace.resume(pid)?;
+        }
+    }
+
+    /// Prevents the signal of the last [`TraceEvent::Signal`] from being delivered to the child.
+    pub fn suppress_signal(&mut self) {
+        if let Some(trace) = &mut self.trace {
+            trace.signal = 0;
+        }
+    }
+}
+
+/// Read the system call that the child is stopped at.
+fn syscall_event(pid: libc::pid_t) -> Result<Option<TraceEvent>> {
+    // SAFETY: all-zero is a valid `SyscallInfo`.
+    let mut info: SyscallInfo = unsafe { std::mem::zeroed() };
+    // SAFETY: `info` is valid for writes of the given size for the duration of the call.
+    let ret = unsafe {
+        libc::ptrace(
+            PTRACE_GET_SYSCALL_INFO as _,
+            pid,
+            std::mem::size_of::<SyscallInfo>(),
+            &mut info,
+        )
+    };
+    if ret < 0 {
+        return Err(Error::last_os_error());
+    }
+    let [nr, args @ .., _] = info.data;
+    Ok(match info.op {
+        PTRACE_SYSCALL_INFO_ENTRY => Some(TraceEvent::SyscallEnter { nr, args }),
+        PTRACE_SYSCALL_INFO_EXIT => Some(TraceEvent::SyscallExit {
+            ret: nr as i64,
+            is_error: info.data[1] != 0,
+        }),
+        _ => None,
+    })
+}
@@ -0,0 +1,166 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::ChildStatus;
+
+/// Whether a reaper is installed.
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Write end of the pipe that the signal handler notifies, or -1 if there is none.
+static NOTIFY: AtomicI32 = AtomicI32::new(-1);
+
+/// Statuses of children reaped while the reaper is installed that have not been claimed yet.
+static EXITED: Mutex<Vec<(u32, ChildStatus)>> = Mutex::new(Vec::new());
+
+/// Reaper of children whose handles are dropped or detached.
+///
+/// While a reaper is installed, a `SIGCHLD` handler is set up, and [`Child::detach`] no longer
+/// leaves reaping to the caller: detached children are reaped like dropped ones, and their exit
+/// statuses are queued so that they can be retrieved with [`Reaper::reap`].
+///
+/// The signal handler only wakes up the reaper, as signal handlers are severely restricted in what
+/// they can safely do. Children are reaped when [`Reaper::reap`] or [`Reaper::wait`] is called,
+/// e.g. from an event loop once the file descriptor exposed via [`AsFd`] becomes readable.
+///
+/// Only one reaper may be installed at a time. The previous `SIGCHLD` handler is restored when the
+/// reaper is dropped.
+///
+/// [`Child::detach`]: crate::Child::detach
+#[derive(Debug)]
+pub struct Reaper {
+    reader: File,
+    /// Write end of the pipe, kept open for the signal handler.
+    _writer: File,
+    previous: libc::sigaction,
+}
+
+impl Reaper {
+    /// Installs the reaper.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`ErrorKind::AlreadyExists`] if a reaper is already installed.
+    pub fn install() -> Result<Self> {
+        if INSTALLED
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "a reaper is already installed",
+            ));
+        }
+
+        let result = (|| {
+            let (reader, writer) = crate::pipe()?;
+            crate::set_nonblocking(reader.as_raw_fd())?;
+            crate::set_nonblocking(writer.as_raw_fd())?;
+            NOTIFY.store(writer.as_raw_fd(), Ordering::Release);
+
+            // SAFETY: all-zero is a valid `sigaction`.
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = handle_sigchld as extern "C" fn(libc::c_int) as _;
+            action.sa_flags = libc::SA_RESTART | libc::SA_NOCLDSTOP;
+            // SAFETY: all-zero is a valid `sigaction`.
+            let mut previous: libc::sigaction = unsafe { std::mem::zeroed() };
+            // SAFETY: the handler only calls async-signal-safe functions, and `action` and
+            // `previous` are valid for the duration of the call.
+            if unsafe { libc::sigaction(libc::SIGCHLD, &action, &mut previous) } < 0 {
+                NOTIFY.store(-1, Ordering::Release);
+                return Err(Error::last_os_error());
+            }
+            Ok(Self {
+                reader,
+                _writer: writer,
+                previous,
+            })
+        })();
+        if result.is_err() {
+            INSTALLED.store(false, Ordering::Release);
+        }
+        result
+    }
+
+    /// Reaps the dropped and detached children that have exited, returning their PIDs and exit
+    /// statuses.
+    ///
+    /// This does not block.
+    pub fn reap(&self) -> Vec<(u32, ChildStatus)> {
+        // Drain the notifications first, so that children exiting from now on notify again.
+        let mut buf = [0; 64];
+        while matches!((&self.reader).read(&mut buf), Ok(n) if n > 0) {}
+        crate::child::reap_orphans();
+        std::mem::take(&mut *EXITED.lock().unwrap())
+    }
+
+    /// Waits until at least one dropped or detached child exits, for at most `timeout` if given,
+    /// returning the PIDs and exit statuses of the children reaped.
+    ///
+    /// An empty list is returned if the timeout elapses.
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<Vec<(u32, ChildStatus)>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let exited = self.reap();
+            if !exited.is_empty() {
+                return Ok(exited);
+            }
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if remaining.is_some_and(|remaining| remaining.is_zero()) {
+                return Ok(exited);
+            }
+            let mut pollfd = libc::pollfd {
+                fd: self.reader.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            crate::poll(std::slice::from_mut(&mut pollfd), remaining)?;
+        }
+    }
+}
+
+impl Drop for Reaper {
+    fn drop(&mut self) {
+        // SAFETY: `previous` is the handler that was installed before the reaper.
+        unsafe { libc::sigaction(libc::SIGCHLD, &self.previous, std::ptr::null_mut()) };
+        NOTIFY.store(-1, Ordering::Release);
+        EXITED.lock().unwrap().clear();
+        INSTALLED.store(false, Ordering::Release);
+    }
+}
+
+impl AsFd for Reaper {
+    /// Borrows a file descriptor that becomes readable when a child exits.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.reader.as_fd()
+    }
+}
+
+extern "C" fn handle_sigchld(_: libc::c_int) {
+    let fd = NOTIFY.load(Ordering::Acquire);
+    if fd >= 0 {
+        // SAFETY: `errno` is thread-local, and `write` is async-signal-safe. The errno is saved
+        // and restored so that the interrupted code is not affected.
+        unsafe {
+            let errno = *crate::sys::errno_location();
+            libc::write(fd, [0u8].as_ptr().cast(), 1);
+            *crate::sys::errno_location() = errno;
+        }
+    }
+}
+
+/// Whether a reaper is installed, in which case detached children are reaped too.
+pub(crate) fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Acquire)
+}
+
+/// Queue the statuses of reaped children if a reaper is installed.
+pub(crate) fn record(exited: Vec<(u32, ChildStatus)>) {
+    if !exited.is_empty() && is_installed() {
+        EXITED.lock().unwrap().extend(exited);
+    }
+}
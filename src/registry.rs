@@ -0,0 +1,212 @@
+#[cfg(unix)]
+use std::io::{Error, Result};
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::time::Duration;
+use std::time::Instant;
+
+#[cfg(unix)]
+use crate::ChildStatus;
+
+/// Children created by this crate that have not been reaped yet.
+static REGISTRY: Mutex<Vec<ChildInfo>> = Mutex::new(Vec::new());
+
+/// Limit on the number of running children.
+static LIMIT: Mutex<ChildLimit> = Mutex::new(ChildLimit::Unlimited);
+
+/// Limit on the number of children created by this crate that may run at the same time.
+///
+/// See [`set_child_limit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChildLimit {
+    /// Any number of children may run at the same time.
+    #[default]
+    Unlimited,
+    /// Creating a child blocks until fewer than the given number of children are running.
+    ///
+    /// If no child is running, so that none can exit to make room, creating a child fails as
+    /// with [`ChildLimit::Fail`] instead. This is only the case for a limit of zero.
+    Block(usize),
+    /// Creating a child fails with `EAGAIN` if the given number of children are running, which
+    /// has the error kind [`WouldBlock`](std::io::ErrorKind::WouldBlock).
+    ///
+    /// A limit of zero prevents creating children at all.
+    Fail(usize),
+}
+
+/// Limits the number of children created by this crate that may run at the same time, e.g. to
+/// guard against accidental fork bombs.
+///
+/// The limit applies to all functions that create children, and covers the children listed by
+/// [`active_children`] that have not exited yet. A child stops counting as soon as it exits, even
+/// if its handle has not been joined yet. Forked children inherit the limit, and it applies to
+/// their own children separately.
+pub fn set_child_limit(limit: ChildLimit) {
+    *LIMIT.lock().unwrap() = limit;
+}
+
+/// Information about a child created by this crate that has not been reaped yet.
+///
+/// See [`active_children`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChildInfo {
+    pid: u32,
+    spawned_at: Instant,
+    orphaned: bool,
+}
+
+impl ChildInfo {
+    /// Returns the PID of the child.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Returns the time at which the child was created.
+    pub fn spawned_at(&self) -> Instant {
+        self.spawned_at
+    }
+
+    /// Returns whether the [`Child`](crate::Child) handle of the child has been dropped while it
+    /// was still running, so that it is reaped in the background.
+    pub fn is_orphaned(&self) -> bool {
+        self.orphaned
+    }
+}
+
+/// Returns the children created by this crate that have not been reaped yet, in the order they
+/// were created.
+///
+/// This includes children whose handles are still alive, as well as orphaned children whose
+/// handles have been dropped but which have not exited or have not been reaped since. Detached
+/// children are not tracked, unless a [`Reaper`](crate::Reaper) is installed.
+///
+/// This can be used to check that no children are leaked, e.g. when a daemon shuts down.
+pub fn active_children() -> Vec<ChildInfo> {
+    REGISTRY.lock().unwrap().clone()
+}
+
+/// Reaps the orphaned children that have exited, returning their PIDs and exit statuses.
+///
+/// This does not block. Orphaned children are otherwise only reaped when forking again. If a
+/// [`Reaper`](crate::Reaper) is installed, the statuses are also queued for it.
+#[cfg(unix)]
+pub fn reap_exited() -> Vec<(u32, ChildStatus)> {
+    crate::child::reap_orphans()
+}
+
+/// Wait until a child may be created, according to the limit on children.
+#[cfg(unix)]
+pub(crate) fn acquire() -> Result<()> {
+    let (max, block) = match *LIMIT.lock().unwrap() {
+        ChildLimit::Unlimited => return Ok(()),
+        ChildLimit::Block(max) => (max, true),
+        ChildLimit::Fail(max) => (max, false),
+    };
+    loop {
+        crate::child::reap_orphans();
+        let running: Vec<_> = REGISTRY
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|info| info.pid)
+            .filter(|&pid| is_running(pid))
+            .collect();
+        if running.len() < max {
+            return Ok(());
+        }
+        // Without running children, blocking would wait forever.
+        if !block || running.is_empty() {
+            return Err(Error::from_raw_os_error(libc::EAGAIN));
+        }
+        wait_any_exit(&running)?;
+    }
+}
+
+/// Check whether the child has not exited yet, without reaping it.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // SAFETY: all-zero is a valid `siginfo_t`.
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    // SAFETY: `info` is valid for the duration of the call.
+    let ret = unsafe {
+        libc::waitid(
+            libc::P_PID,
+            pid as _,
+            &mut info,
+            libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+        )
+    };
+    // SAFETY: `waitid` fills in the PID for `WEXITED`, or leaves it zero if the child is running.
+    ret == 0 && unsafe { info.si_pid() } == 0
+}
+
+/// Wait until at least one of the running children exits.
+#[cfg(unix)]
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+fn wait_any_exit(pids: &[u32]) -> Result<()> {
+    // The children are not reaped yet, so their PIDs cannot be reused.
+    #[cfg(target_os = "linux")]
+    if let Ok(pidfds) = pids
+        .iter()
+        .map(|&pid| crate::sys::pidfd_open(pid as _))
+        .collect::<Result<Vec<_>>>()
+    {
+        use std::os::fd::AsRawFd;
+
+        let mut pollfds: Vec<_> = pidfds
+            .iter()
+            .map(|pidfd| libc::pollfd {
+                fd: pidfd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        crate::poll(&mut pollfds, None)?;
+        return Ok(());
+    }
+
+    // Without pidfd support, fall back to polling at a fixed interval.
+    std::thread::sleep(Duration::from_millis(10));
+    Ok(())
+}
+
+#[cfg(unix)]
+pub(crate) fn register(pid: u32) {
+    REGISTRY.lock().unwrap().push(ChildInfo {
+        pid,
+        spawned_at: Instant::now(),
+        orphaned: false,
+    });
+}
+
+/// Remove a child that has been reaped with `status`, and report it to the metrics sink.
+#[cfg(unix)]
+pub(crate) fn reaped(pid: u32, status: ChildStatus) {
+    let info = {
+        let mut registry = REGISTRY.lock().unwrap();
+        let index = registry.iter().position(|info| info.pid == pid);
+        index.map(|index| registry.remove(index))
+    };
+    if let Some(info) = info {
+        crate::metrics::child_reaped(status, info.spawned_at.elapsed());
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn unregister(pid: u32) {
+    REGISTRY.lock().unwrap().retain(|info| info.pid != pid);
+}
+
+#[cfg(unix)]
+pub(crate) fn mark_orphaned(pid: u32) {
+    let mut registry = REGISTRY.lock().unwrap();
+    if let Some(info) = registry.iter_mut().find(|info| info.pid == pid) {
+        info.orphaned = true;
+    }
+}
+
+/// Forget about all children. Used in a newly forked child, as they are not children of it.
+#[cfg(unix)]
+pub(crate) fn clear() {
+    REGISTRY.lock().unwrap().clear();
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use crate::{ChildStatus, ForkError, Termination};
+
+/// Policy describing when and how often [`fork_retry`] runs a closure again.
+///
+/// By default, every unsuccessful outcome is retried, immediately.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    exit_codes: Vec<i32>,
+    signals: Vec<i32>,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that runs the closure at most `max_attempts` times in total.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            exit_codes: Vec::new(),
+            signals: Vec::new(),
+        }
+    }
+
+    /// Delays retries, starting at `initial` and doubling after each retry up to `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Retries if the child exits with `code`.
+    ///
+    /// Once an exit code or a signal is given, only the outcomes given are retried.
+    pub fn retry_on_exit_code(mut self, code: i32) -> Self {
+        self.exit_codes.push(code);
+        self
+    }
+
+    /// Retries if the child is terminated by `signal`, e.g. `SIGKILL` sent by the OOM killer.
+    ///
+    /// Once an exit code or a signal is given, only the outcomes given are retried.
+    pub fn retry_on_signal(mut self, signal: i32) -> Self {
+        self.signals.push(signal);
+        self
+    }
+
+    fn is_retryable(&self, status: ChildStatus) -> bool {
+        if self.exit_codes.is_empty() && self.signals.is_empty() {
+            return !status.success();
+        }
+        match status {
+            ChildStatus::Exited(code) => self.exit_codes.contains(&code),
+            ChildStatus::Signaled { signal, .. } => self.signals.contains(&signal),
+            _ => false,
+        }
+    }
+
+    fn delay(&self, retries: u32) -> Duration {
+        let factor = 1u32.checked_shl(retries).unwrap_or(u32::MAX);
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Runs `f` in a child process, running it again in a new child process while it ends with an
+/// outcome that `policy` considers retryable.
+///
+/// Returns the status of the last attempt. Each attempt is forked from the calling process, so
+/// changes made by `f` in one attempt are not seen by the next one.
+///
+/// The forking process must be single-threaded. Otherwise, this call will fail.
+///
+/// # Example
+///
+/// ```
+/// use safe_fork::RetryPolicy;
+///
+/// let policy = RetryPolicy::new(3).retry_on_signal(libc::SIGKILL);
+/// let status = safe_fork::fork_retry(&policy, || 0).unwrap();
+/// assert!(status.success());
+/// ```
+pub fn fork_retry<T: Termination>(
+    policy: &RetryPolicy,
+    mut f: impl FnMut() -> T,
+) -> Result<ChildStatus, ForkError> {
+    let mut attempts = 0;
+    loop {
+        let status = crate::fork_spawn(&mut f)?
+            .join()
+            .map_err(ForkError::from_wait)?;
+        attempts += 1;
+        if attempts >= policy.max_attempts || !policy.is_retryable(status) {
+            return Ok(status);
+        }
+        std::thread::sleep(policy.delay(attempts - 1));
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(unix)]
+use std::io::{Error, Result};
+
+/// A resource that can be limited with `setrlimit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Resource {
+    /// Maximum size of the virtual address space, in bytes (`RLIMIT_AS`).
+    AddressSpace,
+    /// Maximum size of a core dump, in bytes (`RLIMIT_CORE`).
+    Core,
+    /// Maximum CPU time, in seconds (`RLIMIT_CPU`).
+    Cpu,
+    /// Maximum size of the data segment, in bytes (`RLIMIT_DATA`).
+    Data,
+    /// Maximum size of files created, in bytes (`RLIMIT_FSIZE`).
+    FileSize,
+    /// Maximum number of bytes locked into memory (`RLIMIT_MEMLOCK`).
+    MemLock,
+    /// Maximum number of open file descriptors (`RLIMIT_NOFILE`).
+    NoFile,
+    /// Maximum number of processes of the user (`RLIMIT_NPROC`).
+    NProc,
+    /// Maximum size of the stack, in bytes (`RLIMIT_STACK`).
+    Stack,
+}
+
+#[cfg(unix)]
+impl Resource {
+    fn raw(self) -> libc::c_int {
+        (match self {
+            Resource::AddressSpace => libc::RLIMIT_AS,
+            Resource::Core => libc::RLIMIT_CORE,
+            Resource::Cpu => libc::RLIMIT_CPU,
+            Resource::Data => libc::RLIMIT_DATA,
+            Resource::FileSize => libc::RLIMIT_FSIZE,
+            Resource::MemLock => libc::RLIMIT_MEMLOCK,
+            Resource::NoFile => libc::RLIMIT_NOFILE,
+            Resource::NProc => libc::RLIMIT_NPROC,
+            Resource::Stack => libc::RLIMIT_STACK,
+        }) as _
+    }
+}
+
+/// A set of resource limits to apply to the child process.
+///
+/// The convenience setters set both the soft and the hard limit to the same value; use
+/// [`Rlimits::set`] to set them separately.
+#[derive(Debug, Default, Clone)]
+pub struct Rlimits {
+    limits: Vec<(Resource, u64, u64)>,
+}
+
+impl Rlimits {
+    /// Value representing no limit.
+    ///
+    /// This and any value at least as large as the platform's `RLIM_INFINITY` are passed to
+    /// `setrlimit` as `RLIM_INFINITY`.
+    pub const INFINITY: u64 = u64::MAX;
+
+    /// Creates an empty set of limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the soft and hard limits of a resource.
+    pub fn set(mut self, resource: Resource, soft: u64, hard: u64) -> Self {
+        self.limits.retain(|&(r, ..)| r != resource);
+        self.limits.push((resource, soft, hard));
+        self
+    }
+
+    /// Limits the size of the virtual address space, in bytes.
+    pub fn address_space(self, bytes: u64) -> Self {
+        self.set(Resource::AddressSpace, bytes, bytes)
+    }
+
+    /// Limits the CPU time, in seconds.
+    pub fn cpu(self, seconds: u64) -> Self {
+        self.set(Resource::Cpu, seconds, seconds)
+    }
+
+    /// Limits the number of open file descriptors.
+    pub fn nofile(self, count: u64) -> Self {
+        self.set(Resource::NoFile, count, count)
+    }
+
+    /// Limits the size of core dumps, in bytes.
+    pub fn core(self, bytes: u64) -> Self {
+        self.set(Resource::Core, bytes, bytes)
+    }
+
+    /// Returns the soft and hard limits configured for a resource.
+    pub fn get(&self, resource: Resource) -> Option<(u64, u64)> {
+        self.limits
+            .iter()
+            .find(|&&(r, ..)| r == resource)
+            .map(|&(_, soft, hard)| (soft, hard))
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.limits.is_empty()
+    }
+
+    /// Apply the limits to the current process.
+    #[cfg(unix)]
+    pub(crate) fn apply(&self) -> Result<()> {
+        for &(resource, soft, hard) in &self.limits {
+            let limit = libc::rlimit {
+                rlim_cur: raw_limit(soft),
+                rlim_max: raw_limit(hard),
+            };
+            // SAFETY: `limit` is valid for the duration of the call.
+            if unsafe { libc::setrlimit(resource.raw() as _, &limit) } < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts a limit to `rlim_t`, which is signed on some platforms and may not represent
+/// `u64::MAX`.
+// `rlim_t` is `u64` on Linux, making the cast a no-op there.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn raw_limit(value: u64) -> libc::rlim_t {
+    if value >= libc::RLIM_INFINITY as u64 {
+        libc::RLIM_INFINITY
+    } else {
+        value as _
+    }
+}
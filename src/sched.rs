@@ -0,0 +1,97 @@
+use std::io::{Error, ErrorKind, Result};
+
+/// Scheduling policy for non-real-time processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SchedPolicy {
+    /// The default time-sharing policy (`SCHED_OTHER`).
+    Other,
+    /// Time-sharing for CPU-intensive, non-interactive processes (`SCHED_BATCH`).
+    ///
+    /// The scheduler assumes that the process is CPU-bound and penalises it slightly when waking
+    /// up.
+    Batch,
+    /// Only runs when the CPU would otherwise be idle (`SCHED_IDLE`).
+    Idle,
+}
+
+impl SchedPolicy {
+    fn raw(self) -> libc::c_int {
+        match self {
+            SchedPolicy::Other => libc::SCHED_OTHER,
+            SchedPolicy::Batch => libc::SCHED_BATCH,
+            SchedPolicy::Idle => libc::SCHED_IDLE,
+        }
+    }
+
+    /// Apply the policy to the current process.
+    pub(crate) fn apply(self) -> Result<()> {
+        // These policies only support the static priority 0.
+        let param = libc::sched_param { sched_priority: 0 };
+        // SAFETY: `param` is valid for the duration of the call.
+        if unsafe { libc::sched_setscheduler(0, self.raw(), &param) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+/// I/O scheduling class, as used by `ioprio_set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IoPriorityClass {
+    /// Gets first access to the disk, which may starve other processes (`IOPRIO_CLASS_RT`).
+    ///
+    /// Setting this class requires `CAP_SYS_ADMIN`.
+    Realtime,
+    /// The default class (`IOPRIO_CLASS_BE`).
+    BestEffort,
+    /// Only gets disk time when no other process needs it (`IOPRIO_CLASS_IDLE`).
+    ///
+    /// The priority level is ignored for this class.
+    Idle,
+}
+
+/// Set the I/O priority of the current process.
+pub(crate) fn set_io_priority(class: IoPriorityClass, level: u8) -> Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    if level > 7 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "I/O priority level out of range",
+        ));
+    }
+    let class = match class {
+        IoPriorityClass::Realtime => 1,
+        IoPriorityClass::BestEffort => 2,
+        IoPriorityClass::Idle => 3,
+    };
+    let priority = class << IOPRIO_CLASS_SHIFT | libc::c_int::from(level);
+    // SAFETY: `ioprio_set` does not have special safety requirements.
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, priority) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Restrict the current process to run on the given CPUs.
+pub(crate) fn set_affinity(cpus: &[usize]) -> Result<()> {
+    // SAFETY: all-zero is a valid, empty `cpu_set_t`.
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "CPU index out of range",
+            ));
+        }
+        // SAFETY: `cpu` is within the bounds of the set.
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // SAFETY: `set` is valid for the duration of the call.
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of_val(&set), &set) } < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use std::cell::RefCell;
+use std::io::Result;
+use std::panic::AssertUnwindSafe;
+
+use crate::{Child, ChildStatus, ForkError, Termination};
+
+/// A scope to spawn child processes in.
+///
+/// See [`fork_scope`] for details.
+#[derive(Debug)]
+pub struct Scope {
+    children: RefCell<Vec<Option<Child>>>,
+}
+
+/// Handle to a child process spawned in a [`Scope`].
+///
+/// The child is joined automatically when the scope ends if it is not joined explicitly.
+#[derive(Debug)]
+pub struct ScopedChild<'scope> {
+    scope: &'scope Scope,
+    index: usize,
+    pid: u32,
+}
+
+/// Create a scope for spawning child processes.
+///
+/// All children spawned in the scope that are not joined explicitly are joined before this
+/// function returns. If the closure panics, the remaining children are killed and reaped before
+/// the panic is propagated.
+pub fn fork_scope<R>(f: impl FnOnce(&Scope) -> R) -> R {
+    let scope = Scope {
+        children: RefCell::new(Vec::new()),
+    };
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+    let children = scope.children.take();
+    match result {
+        Ok(result) => {
+            for child in children.into_iter().flatten() {
+                let _ = child.join();
+            }
+            result
+        }
+        Err(payload) => {
+            for child in children.into_iter().flatten() {
+                let _ = child.kill();
+                let _ = child.join();
+            }
+            std::panic::resume_unwind(payload)
+        }
+    }
+}
+
+impl Scope {
+    /// Fork the current process, and execute the provided closure within child process.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn spawn<T: Termination>(
+        &self,
+        f: impl FnOnce() -> T,
+    ) -> std::result::Result<ScopedChild<'_>, ForkError> {
+        let child = crate::fork_spawn(f)?;
+        let pid = child.pid();
+        let mut children = self.children.borrow_mut();
+        children.push(Some(child));
+        Ok(ScopedChild {
+            scope: self,
+            index: children.len() - 1,
+            pid,
+        })
+    }
+}
+
+impl ScopedChild<'_> {
+    /// Returns the OS-assigned process identifier associated with this child.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Waits for the child to exit completely, returning the status that it exited with.
+    pub fn join(self) -> Result<ChildStatus> {
+        let child = self.scope.children.borrow_mut()[self.index].take();
+        // The child is only taken out of the scope here, which consumes the handle.
+        child.unwrap().join()
+    }
+
+    /// Sends the signal `sig` to the child.
+    pub fn signal(&self, sig: i32) -> Result<()> {
+        match &self.scope.children.borrow()[self.index] {
+            Some(child) => child.signal(sig),
+            None => Ok(()),
+        }
+    }
+
+    /// Forces the child to exit by sending `SIGKILL`.
+    pub fn kill(&self) -> Result<()> {
+        match &self.scope.children.borrow()[self.index] {
+            Some(child) => child.kill(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(unix)]
+use std::fmt;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// A value holding sensitive data that can be wiped in place.
+///
+/// Children inherit a copy of all memory of the parent, including key material and passwords.
+/// Values implementing this trait can be wiped in the child with hooks registered with
+/// [`ForkBuilder::scrub`](crate::ForkBuilder::scrub). The writes are volatile, so they are not
+/// optimized away even if the value is never read again.
+pub trait Scrubbable {
+    /// Overwrites the value with zeros, or an equivalent empty state.
+    fn scrub(&mut self);
+}
+
+macro_rules! scrub_int {
+    ($($ty:ty),*) => {$(
+        impl Scrubbable for $ty {
+            fn scrub(&mut self) {
+                // SAFETY: `self` is valid for writes.
+                unsafe { std::ptr::write_volatile(self, 0) };
+                compiler_fence(Ordering::SeqCst);
+            }
+        }
+    )*};
+}
+
+scrub_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: Scrubbable> Scrubbable for [T] {
+    fn scrub(&mut self) {
+        self.iter_mut().for_each(T::scrub);
+    }
+}
+
+impl<T: Scrubbable, const N: usize> Scrubbable for [T; N] {
+    fn scrub(&mut self) {
+        self[..].scrub();
+    }
+}
+
+impl<T: Scrubbable> Scrubbable for Vec<T> {
+    fn scrub(&mut self) {
+        self[..].scrub();
+    }
+}
+
+impl Scrubbable for String {
+    fn scrub(&mut self) {
+        // SAFETY: a string of NUL characters is valid UTF-8.
+        unsafe { self.as_mut_vec() }.scrub();
+    }
+}
+
+impl<T: Scrubbable + ?Sized> Scrubbable for Box<T> {
+    fn scrub(&mut self) {
+        (**self).scrub();
+    }
+}
+
+impl<T: Scrubbable> Scrubbable for Option<T> {
+    fn scrub(&mut self) {
+        if let Some(value) = self {
+            value.scrub();
+        }
+    }
+}
+
+/// Hooks registered with [`ForkBuilder::scrub`](crate::ForkBuilder::scrub).
+#[cfg(unix)]
+#[derive(Default)]
+pub(crate) struct ScrubHooks(Vec<Box<dyn Fn() + Send + Sync>>);
+
+#[cfg(unix)]
+impl ScrubHooks {
+    pub(crate) fn push(&mut self, hook: impl Fn() + Send + Sync + 'static) {
+        self.0.push(Box::new(hook));
+    }
+
+    pub(crate) fn run(&self) {
+        for hook in &self.0 {
+            hook();
+        }
+    }
+}
+
+#[cfg(unix)]
+impl fmt::Debug for ScrubHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScrubHooks")
+            .field("len", &self.0.len())
+            .finish()
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::{Error, Result};
+
+/// Action taken when a system call matches a rule of a [`SeccompFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SeccompAction {
+    /// Allow the system call.
+    Allow,
+    /// Fail the system call with the given errno.
+    Errno(u16),
+    /// Allow the system call, but log it.
+    Log,
+    /// Raise `SIGSYS` in the thread making the system call.
+    Trap,
+    /// Kill the process.
+    KillProcess,
+}
+
+impl SeccompAction {
+    fn raw(self) -> u32 {
+        match self {
+            SeccompAction::Allow => libc::SECCOMP_RET_ALLOW,
+            SeccompAction::Errno(errno) => libc::SECCOMP_RET_ERRNO | errno as u32,
+            SeccompAction::Log => libc::SECCOMP_RET_LOG,
+            SeccompAction::Trap => libc::SECCOMP_RET_TRAP,
+            SeccompAction::KillProcess => libc::SECCOMP_RET_KILL_PROCESS,
+        }
+    }
+}
+
+/// A seccomp filter that restricts the system calls of the child process.
+///
+/// System calls are identified by their numbers, e.g. [`libc::SYS_read`]. Calls that match no rule
+/// get the default action. Calls made with a different system call ABI than the native one, such as
+/// the 32-bit ABI on x86-64, always kill the process.
+#[derive(Debug, Clone)]
+pub struct SeccompFilter {
+    default: SeccompAction,
+    rules: Vec<(libc::c_long, SeccompAction)>,
+}
+
+impl SeccompFilter {
+    /// Creates a filter that applies `default` to all system calls.
+    pub fn new(default: SeccompAction) -> Self {
+        Self {
+            default,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Creates a filter that only allows system calls needed for basic computation.
+    ///
+    /// This covers memory management, signal handling, threads synchronization, time, reading
+    /// from and writing to already open file descriptors, and exiting. All other system calls,
+    /// notably opening files, creating sockets and processes, fail with `EPERM`.
+    pub fn basic_compute() -> Self {
+        let mut syscalls = vec![
+            libc::SYS_read,
+            libc::SYS_write,
+            libc::SYS_readv,
+            libc::SYS_writev,
+            libc::SYS_pread64,
+            libc::SYS_pwrite64,
+            libc::SYS_lseek,
+            libc::SYS_close,
+            libc::SYS_fstat,
+            libc::SYS_newfstatat,
+            libc::SYS_statx,
+            libc::SYS_fcntl,
+            libc::SYS_ppoll,
+            libc::SYS_brk,
+            libc::SYS_mmap,
+            libc::SYS_munmap,
+            libc::SYS_mremap,
+            libc::SYS_mprotect,
+            libc::SYS_madvise,
+            libc::SYS_futex,
+            libc::SYS_sched_yield,
+            libc::SYS_sched_getaffinity,
+            libc::SYS_rt_sigaction,
+            libc::SYS_rt_sigprocmask,
+            libc::SYS_rt_sigreturn,
+            libc::SYS_sigaltstack,
+            libc::SYS_clock_gettime,
+            libc::SYS_clock_getres,
+            libc::SYS_clock_nanosleep,
+            libc::SYS_nanosleep,
+            libc::SYS_gettimeofday,
+            libc::SYS_getrandom,
+            libc::SYS_getpid,
+            libc::SYS_gettid,
+            libc::SYS_exit,
+            libc::SYS_exit_group,
+        ];
+        #[cfg(target_arch = "x86_64")]
+        syscalls.extend([libc::SYS_poll, libc::SYS_arch_prctl]);
+        Self::new(SeccompAction::Errno(libc::EPERM as u16)).allow(&syscalls)
+    }
+
+    /// Applies `action` to the system call.
+    ///
+    /// Rules added earlier take precedence.
+    pub fn rule(mut self, syscall: libc::c_long, action: SeccompAction) -> Self {
+        self.rules.push((syscall, action));
+        self
+    }
+
+    /// Allows the system calls.
+    pub fn allow(mut self, syscalls: &[libc::c_long]) -> Self {
+        self.rules
+            .extend(syscalls.iter().map(|&nr| (nr, SeccompAction::Allow)));
+        self
+    }
+
+    /// Install the filter in the current process.
+    ///
+    /// `no_new_privs` is set first, which is required to install filters without privilege.
+    pub(crate) fn install(&self) -> Result<()> {
+        let program = self.compile()?;
+        let prog = libc::sock_fprog {
+            len: program.len() as _,
+            filter: program.as_ptr().cast_mut(),
+        };
+        // SAFETY: `PR_SET_NO_NEW_PRIVS` does not have special safety requirements, and `prog`
+        // points to a valid program for the duration of the `PR_SET_SECCOMP` call.
+        unsafe {
+            if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+                return Err(Error::last_os_error());
+            }
+            if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_FILTER, &prog) < 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// Compile the filter into a classic BPF program.
+    fn compile(&self) -> Result<Vec<libc::sock_filter>> {
+        // Offsets of the fields of `struct seccomp_data`.
+        const NR: u32 = 0;
+        const ARCH: u32 = 4;
+        const LOAD: u16 = (libc::BPF_LD | libc::BPF_W | libc::BPF_ABS) as u16;
+        const JEQ: u16 = (libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K) as u16;
+        const RET: u16 = (libc::BPF_RET | libc::BPF_K) as u16;
+        let stmt = |code, k| libc::sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+        let jump = |code, k, jt, jf| libc::sock_filter { code, jt, jf, k };
+        let kill = libc::SECCOMP_RET_KILL_PROCESS;
+
+        let mut program = vec![
+            stmt(LOAD, ARCH),
+            jump(JEQ, audit_arch()?, 1, 0),
+            stmt(RET, kill),
+            stmt(LOAD, NR),
+        ];
+        // The x32 ABI shares the architecture with x86-64, and is distinguished by this bit.
+        #[cfg(target_arch = "x86_64")]
+        {
+            const JGE: u16 = (libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K) as u16;
+            program.extend([jump(JGE, 0x4000_0000, 0, 1), stmt(RET, kill)]);
+        }
+        for &(nr, action) in &self.rules {
+            program.extend([jump(JEQ, nr as u32, 0, 1), stmt(RET, action.raw())]);
+        }
+        program.push(stmt(RET, self.default.raw()));
+        Ok(program)
+    }
+}
+
+/// The `AUDIT_ARCH_*` value of the native system call ABI.
+fn audit_arch() -> Result<u32> {
+    if cfg!(target_arch = "x86_64") {
+        Ok(0xc000_003e)
+    } else if cfg!(target_arch = "aarch64") {
+        Ok(0xc000_00b7)
+    } else if cfg!(target_arch = "riscv64") {
+        Ok(0xc000_00f3)
+    } else {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
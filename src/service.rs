@@ -0,0 +1,161 @@
+use std::cell::Cell;
+use std::io::{Error, ErrorKind, Result};
+use std::os::fd::{AsFd, AsRawFd};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::channel::{Receiver, Sender};
+use crate::{Bincode, Child, ChildStatus, Codec, ForkBuilder};
+
+/// Fork a child process that serves requests with `handler` until the channel is closed.
+///
+/// The child loops receiving requests and replying with the responses computed by `handler`.
+/// The parent talks to it through the returned [`ServiceHandle`], so the child can hold
+/// resources or privileges that the parent gives up, or the other way round.
+///
+/// Use [`ForkBuilder::spawn_service`] to set up the child process before it starts serving.
+///
+/// The forking process must be single-threaded. Otherwise, this call will fail.
+///
+/// # Example
+///
+/// ```
+/// let mut service = safe_fork::fork_service(|x: u32| x * 2).unwrap();
+/// assert_eq!(service.call(21).unwrap(), 42);
+/// assert!(service.shutdown().unwrap().success());
+/// ```
+pub fn fork_service<Req, Resp>(handler: impl FnMut(Req) -> Resp) -> Result<ServiceHandle<Req, Resp>>
+where
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    fork_service_codec(handler)
+}
+
+/// Fork a child process that serves requests with `handler`, serializing the requests and the
+/// responses with the codec `C`.
+///
+/// See [`fork_service`].
+pub fn fork_service_codec<C, Req, Resp>(
+    handler: impl FnMut(Req) -> Resp,
+) -> Result<ServiceHandle<Req, Resp, C>>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    spawn(&mut ForkBuilder::new(), handler)
+}
+
+/// Fork a service child with the given builder.
+pub(crate) fn spawn<C, Req, Resp>(
+    builder: &mut ForkBuilder,
+    mut handler: impl FnMut(Req) -> Resp,
+) -> Result<ServiceHandle<Req, Resp, C>>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    let ((tx, rx), (child_tx, child_rx)) = crate::channel::channel::<Req, Resp, C>()?;
+    // The endpoint of the child must survive the file descriptor setup of the builder.
+    let child_tx = child_tx.map_stream(|stream| builder.shelter(stream))?;
+    let child_rx = child_rx.map_stream(|stream| builder.shelter(stream))?;
+    let internal = [child_tx.as_fd().as_raw_fd(), child_rx.as_fd().as_raw_fd()];
+    let parent_end = Cell::new(Some((tx, rx)));
+    // Close the endpoint of the parent, so that the child observes EOF once the parent closes it.
+    let child = builder.spawn_keeping(
+        &internal,
+        || drop(parent_end.take()),
+        || loop {
+            match child_rx.recv() {
+                Ok(req) => {
+                    if child_tx.send(&handler(req)).is_err() {
+                        return 1;
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => return 0,
+                Err(_) => return 1,
+            }
+        },
+    )?;
+
+    let (tx, rx) = parent_end.take().unwrap();
+    Ok(ServiceHandle {
+        tx,
+        rx,
+        child,
+        broken: false,
+    })
+}
+
+/// Handle to a child process forked by [`fork_service`], used to send it requests.
+///
+/// Requests are served one at a time, in order. If a call fails, e.g. because the child died or
+/// a timeout elapsed, the child is killed and all further calls fail with
+/// [`ErrorKind::NotConnected`], as the responses can no longer be matched with the requests.
+///
+/// Dropping the handle closes the channel, which makes the child exit.
+#[derive(Debug)]
+pub struct ServiceHandle<Req, Resp, C = Bincode> {
+    tx: Sender<Req, C>,
+    rx: Receiver<Resp, C>,
+    child: Child,
+    broken: bool,
+}
+
+impl<Req, Resp, C> ServiceHandle<Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Returns the PID of the child process.
+    pub fn pid(&self) -> u32 {
+        self.child.pid()
+    }
+
+    /// Sends a request to the child, and waits for the response.
+    pub fn call(&mut self, req: Req) -> Result<Resp> {
+        self.call_inner(req, None)
+    }
+
+    /// Sends a request to the child, and waits for the response for at most `timeout`.
+    ///
+    /// The timeout covers receiving the whole response, not only its beginning. If it elapses, the
+    /// child is killed and this fails with [`ErrorKind::TimedOut`].
+    pub fn call_timeout(&mut self, req: Req, timeout: Duration) -> Result<Resp> {
+        self.call_inner(req, Some(timeout))
+    }
+
+    fn call_inner(&mut self, req: Req, timeout: Option<Duration>) -> Result<Resp> {
+        if self.broken {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "service is no longer running",
+            ));
+        }
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let result = (|| {
+            self.tx.send(&req)?;
+            match deadline {
+                Some(deadline) => self.rx.recv_deadline(deadline),
+                None => self.rx.recv(),
+            }
+        })();
+        if result.is_err() {
+            self.broken = true;
+            let _ = self.child.kill();
+        }
+        result
+    }
+
+    /// Closes the channel, and waits for the child to exit.
+    pub fn shutdown(self) -> Result<ChildStatus> {
+        drop((self.tx, self.rx));
+        self.child.join()
+    }
+}
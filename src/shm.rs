@@ -0,0 +1,231 @@
+use std::io::{Error, Result};
+#[cfg(all(target_os = "linux", feature = "bytemuck"))]
+use std::os::fd::{AsFd, BorrowedFd};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::ptr::NonNull;
+#[cfg(feature = "bytemuck")]
+use std::sync::atomic::{AtomicU32, AtomicU64};
+
+/// A zero-initialized memory mapping that stays shared with child processes forked after it is
+/// created.
+#[derive(Debug)]
+pub(crate) struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+    /// The memfd backing the mapping.
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(not(feature = "bytemuck"), allow(dead_code))]
+    fd: OwnedFd,
+}
+
+impl Mapping {
+    /// Map `len` bytes of shared memory. The mapping is page-aligned, and at least one byte is
+    /// mapped as empty mappings are not allowed.
+    pub(crate) fn new(len: usize) -> Result<Self> {
+        let len = len.max(1);
+
+        #[cfg(target_os = "linux")]
+        let (fd, flags, raw_fd) = {
+            // SAFETY: the name is a valid C string.
+            let fd = unsafe { libc::memfd_create(c"safe-fork".as_ptr(), libc::MFD_CLOEXEC) };
+            if fd < 0 {
+                return Err(Error::last_os_error());
+            }
+            // SAFETY: the file descriptor is freshly created and exclusively owned.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            // SAFETY: `ftruncate` does not have special safety requirements.
+            if unsafe { libc::ftruncate(fd.as_raw_fd(), len as libc::off_t) } < 0 {
+                return Err(Error::last_os_error());
+            }
+            let raw_fd = fd.as_raw_fd();
+            (fd, libc::MAP_SHARED, raw_fd)
+        };
+        #[cfg(not(target_os = "linux"))]
+        let (flags, raw_fd) = (libc::MAP_SHARED | libc::MAP_ANON, -1);
+
+        // SAFETY: a new mapping is created, which does not alias any existing memory.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                raw_fd,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: NonNull::new(ptr.cast()).unwrap(),
+            len,
+            #[cfg(target_os = "linux")]
+            fd,
+        })
+    }
+
+    pub(crate) fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    #[cfg(all(target_os = "linux", feature = "bytemuck"))]
+    pub(crate) fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // SAFETY: the mapping is created by `mmap` with this length, and no references into it
+        // outlive `self`.
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+    }
+}
+
+/// Typed memory region shared between a process and the children that it forks afterwards.
+///
+/// The region holds `len` values of `T`, initialized to zero. Writes made by one process are
+/// visible to all others, without copying.
+///
+/// Accesses from multiple processes at the same time are subject to the same rules as accesses
+/// from multiple threads. Use the atomic views, such as [`as_atomic_u64`](Self::as_atomic_u64),
+/// for values updated concurrently, and [`get`](Self::get) and [`set`](Self::set) for values that
+/// are accessed by one process at a time, e.g. results read by the parent after joining the
+/// child.
+///
+/// On Linux, the region is backed by a memfd, which is available via [`AsFd`].
+///
+/// This requires the `bytemuck` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::Ordering;
+///
+/// use safe_fork::SharedMem;
+///
+/// let counter = SharedMem::<u64>::new().unwrap();
+/// let child = safe_fork::fork_spawn(|| {
+///     counter.as_atomic_u64()[0].fetch_add(1, Ordering::Relaxed);
+/// })
+/// .unwrap();
+/// child.join().unwrap();
+/// assert_eq!(counter.get(0), 1);
+/// ```
+#[cfg(feature = "bytemuck")]
+#[derive(Debug)]
+pub struct SharedMem<T> {
+    mapping: Mapping,
+    len: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> SharedMem<T> {
+    /// Creates a region holding a single value.
+    pub fn new() -> Result<Self> {
+        Self::with_len(1)
+    }
+
+    /// Creates a region holding `len` values.
+    pub fn with_len(len: usize) -> Result<Self> {
+        let size = std::mem::size_of::<T>()
+            .checked_mul(len)
+            .ok_or(std::io::ErrorKind::InvalidInput)?;
+        // The mapping is page-aligned, so it satisfies the alignment of any `T`.
+        Ok(Self {
+            mapping: Mapping::new(size)?,
+            len,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Returns the number of values in the region.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the region holds no values.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a raw pointer to the first value.
+    pub fn as_ptr(&self) -> *mut T {
+        self.mapping.as_ptr().cast()
+    }
+
+    /// Reads the value at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> T {
+        assert!(index < self.len, "index out of bounds");
+        // SAFETY: the pointer is in bounds and aligned, and any bit pattern is a valid `T`. The
+        // read is volatile, as the memory may be changed by other processes.
+        unsafe { self.as_ptr().add(index).read_volatile() }
+    }
+
+    /// Writes `value` at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&self, index: usize, value: T) {
+        assert!(index < self.len, "index out of bounds");
+        // SAFETY: the pointer is in bounds and aligned. The write is volatile, as the memory may
+        // be read by other processes.
+        unsafe { self.as_ptr().add(index).write_volatile(value) }
+    }
+
+    /// Returns the region as a slice.
+    ///
+    /// # Safety
+    ///
+    /// No process may write to the region while the slice is alive.
+    pub unsafe fn as_slice(&self) -> &[T] {
+        // SAFETY: the region holds `len` initialized values, and the caller guarantees the
+        // absence of writes.
+        unsafe { std::slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    /// Returns the region as a mutable slice.
+    ///
+    /// # Safety
+    ///
+    /// No other process may access the region and no other reference into it may be alive while
+    /// the slice is alive.
+    #[allow(clippy::mut_from_ref)]
+    pub unsafe fn as_mut_slice(&self) -> &mut [T] {
+        // SAFETY: the region holds `len` initialized values, and the caller guarantees exclusive
+        // access.
+        unsafe { std::slice::from_raw_parts_mut(self.as_ptr(), self.len) }
+    }
+
+    /// Views the region as 32-bit atomics, ignoring trailing bytes that do not fill one.
+    pub fn as_atomic_u32(&self) -> &[AtomicU32] {
+        let len = self.len * std::mem::size_of::<T>() / std::mem::size_of::<AtomicU32>();
+        // SAFETY: the mapping is page-aligned and at least `len` atomics long. Atomics are valid
+        // for any bit pattern and allow shared mutation.
+        unsafe { std::slice::from_raw_parts(self.mapping.as_ptr().cast(), len) }
+    }
+
+    /// Views the region as 64-bit atomics, ignoring trailing bytes that do not fill one.
+    pub fn as_atomic_u64(&self) -> &[AtomicU64] {
+        let len = self.len * std::mem::size_of::<T>() / std::mem::size_of::<AtomicU64>();
+        // SAFETY: the mapping is page-aligned and at least `len` atomics long. Atomics are valid
+        // for any bit pattern and allow shared mutation.
+        unsafe { std::slice::from_raw_parts(self.mapping.as_ptr().cast(), len) }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "bytemuck"))]
+impl<T> AsFd for SharedMem<T> {
+    /// Borrows the memfd backing the region.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.mapping.as_fd()
+    }
+}
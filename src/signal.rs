@@ -0,0 +1,126 @@
+use std::fmt;
+use std::io::{Error, Result};
+
+/// A set of signals, used as the signal mask of a child process.
+///
+/// # Example
+///
+/// ```
+/// use safe_fork::SigSet;
+///
+/// let set = SigSet::empty().with(libc::SIGTERM);
+/// assert!(set.contains(libc::SIGTERM));
+/// assert!(!set.contains(libc::SIGINT));
+/// ```
+#[derive(Clone, Copy)]
+pub struct SigSet(libc::sigset_t);
+
+impl SigSet {
+    /// Creates a set containing no signals.
+    pub fn empty() -> Self {
+        // SAFETY: all-zero is a valid `sigset_t`, which is then initialized by `sigemptyset`.
+        unsafe {
+            let mut set = std::mem::zeroed();
+            libc::sigemptyset(&mut set);
+            Self(set)
+        }
+    }
+
+    /// Creates a set containing all signals.
+    pub fn full() -> Self {
+        // SAFETY: all-zero is a valid `sigset_t`, which is then initialized by `sigfillset`.
+        unsafe {
+            let mut set = std::mem::zeroed();
+            libc::sigfillset(&mut set);
+            Self(set)
+        }
+    }
+
+    /// Returns the signal mask of the calling thread.
+    pub fn current() -> Result<Self> {
+        let mut set = Self::empty();
+        // SAFETY: `set` is valid for the duration of the call.
+        let ret = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, std::ptr::null(), &mut set.0) };
+        if ret != 0 {
+            return Err(Error::from_raw_os_error(ret));
+        }
+        Ok(set)
+    }
+
+    /// Adds `sig` to the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sig` is not a valid signal number.
+    pub fn with(mut self, sig: i32) -> Self {
+        // SAFETY: `self.0` is an initialized `sigset_t`.
+        let ret = unsafe { libc::sigaddset(&mut self.0, sig) };
+        assert_eq!(ret, 0, "invalid signal number {sig}");
+        self
+    }
+
+    /// Removes `sig` from the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sig` is not a valid signal number.
+    pub fn without(mut self, sig: i32) -> Self {
+        // SAFETY: `self.0` is an initialized `sigset_t`.
+        let ret = unsafe { libc::sigdelset(&mut self.0, sig) };
+        assert_eq!(ret, 0, "invalid signal number {sig}");
+        self
+    }
+
+    /// Returns whether `sig` is in the set.
+    pub fn contains(&self, sig: i32) -> bool {
+        // SAFETY: `self.0` is an initialized `sigset_t`.
+        unsafe { libc::sigismember(&self.0, sig) == 1 }
+    }
+
+    pub(crate) fn as_raw(&self) -> &libc::sigset_t {
+        &self.0
+    }
+
+    /// Replace the signal mask of the calling thread with the set.
+    pub(crate) fn set_mask(&self) -> Result<()> {
+        // SAFETY: `self.0` is valid for the duration of the call.
+        let ret =
+            unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &self.0, std::ptr::null_mut()) };
+        if ret != 0 {
+            return Err(Error::from_raw_os_error(ret));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SigSet {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for SigSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set()
+            .entries((1..=MAX_SIGNAL).filter(|&sig| self.contains(sig)))
+            .finish()
+    }
+}
+
+/// The largest signal number, including real-time signals.
+#[cfg(target_os = "linux")]
+const MAX_SIGNAL: i32 = 64;
+#[cfg(not(target_os = "linux"))]
+const MAX_SIGNAL: i32 = 31;
+
+/// Reset the dispositions of all signals of the current process to the default.
+pub(crate) fn reset_handlers() {
+    for sig in 1..=MAX_SIGNAL {
+        if sig == libc::SIGKILL || sig == libc::SIGSTOP {
+            continue;
+        }
+        // Signals reserved by the C library cannot be changed, which is fine to ignore.
+        // SAFETY: restoring the default disposition is always sound.
+        unsafe { libc::signal(sig, libc::SIG_DFL) };
+    }
+}
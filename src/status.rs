@@ -0,0 +1,231 @@
+use std::fmt;
+#[cfg(unix)]
+use std::os::unix::process::ExitStatusExt;
+#[cfg(unix)]
+use std::process::ExitStatus;
+use std::time::Duration;
+
+/// Status of a child process, as reported by `waitpid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildStatus {
+    /// The child exited normally with the given exit code.
+    Exited(i32),
+    /// The child was terminated by a signal.
+    Signaled {
+        /// The signal that terminated the child.
+        signal: i32,
+        /// Whether a core dump was produced.
+        core_dumped: bool,
+    },
+    /// The child was stopped by the given signal.
+    Stopped(i32),
+    /// The child was resumed by `SIGCONT`.
+    Continued,
+}
+
+impl ChildStatus {
+    /// Decodes a raw wait status.
+    #[cfg(unix)]
+    pub fn from_raw(status: i32) -> Self {
+        if libc::WIFEXITED(status) {
+            ChildStatus::Exited(libc::WEXITSTATUS(status))
+        } else if libc::WIFSIGNALED(status) {
+            ChildStatus::Signaled {
+                signal: libc::WTERMSIG(status),
+                core_dumped: libc::WCOREDUMP(status),
+            }
+        } else if libc::WIFSTOPPED(status) {
+            ChildStatus::Stopped(libc::WSTOPSIG(status))
+        } else {
+            ChildStatus::Continued
+        }
+    }
+
+    /// Decodes the status reported by `waitid` for a child that exited.
+    #[cfg(unix)]
+    pub(crate) fn from_siginfo(info: &libc::siginfo_t) -> Self {
+        // SAFETY: `waitid` fills in the status for `WEXITED`.
+        let status = unsafe { info.si_status() };
+        match info.si_code {
+            libc::CLD_EXITED => ChildStatus::Exited(status),
+            code => ChildStatus::Signaled {
+                signal: status,
+                core_dumped: code == libc::CLD_DUMPED,
+            },
+        }
+    }
+
+    /// Encodes the status as a raw wait status.
+    pub fn into_raw(self) -> i32 {
+        match self {
+            ChildStatus::Exited(code) => (code & 0xff) << 8,
+            ChildStatus::Signaled {
+                signal,
+                core_dumped,
+            } => signal | if core_dumped { 0x80 } else { 0 },
+            ChildStatus::Stopped(signal) => (signal << 8) | 0x7f,
+            ChildStatus::Continued => 0xffff,
+        }
+    }
+
+    /// Returns whether the child exited normally with exit code 0.
+    pub fn success(self) -> bool {
+        self == ChildStatus::Exited(0)
+    }
+
+    /// Returns the exit code if the child exited normally.
+    pub fn code(self) -> Option<i32> {
+        match self {
+            ChildStatus::Exited(code) => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Returns the signal that terminated the child, if any.
+    pub fn signal(self) -> Option<i32> {
+        match self {
+            ChildStatus::Signaled { signal, .. } => Some(signal),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the child was terminated by a signal and produced a core dump.
+    pub fn core_dumped(self) -> bool {
+        matches!(
+            self,
+            ChildStatus::Signaled {
+                core_dumped: true,
+                ..
+            }
+        )
+    }
+}
+
+impl fmt::Display for ChildStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ChildStatus::Exited(code) => write!(f, "exit status: {code}"),
+            ChildStatus::Signaled {
+                signal,
+                core_dumped,
+            } => {
+                write!(f, "signal: {signal}")?;
+                if core_dumped {
+                    write!(f, " (core dumped)")?;
+                }
+                Ok(())
+            }
+            ChildStatus::Stopped(signal) => {
+                write!(f, "stopped (not terminated) by signal: {signal}")
+            }
+            ChildStatus::Continued => write!(f, "continued (WIFCONTINUED)"),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<ExitStatus> for ChildStatus {
+    fn from(status: ExitStatus) -> Self {
+        Self::from_raw(status.into_raw())
+    }
+}
+
+#[cfg(unix)]
+impl From<ChildStatus> for ExitStatus {
+    fn from(status: ChildStatus) -> Self {
+        ExitStatus::from_raw(status.into_raw())
+    }
+}
+
+/// Report of a child process that has been joined.
+///
+/// See [`Child::join_with_report`](crate::Child::join_with_report).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct JoinReport {
+    /// The status that the child exited with.
+    pub status: ChildStatus,
+    /// Wall-clock time from forking the child until it is reaped.
+    pub duration: Duration,
+    /// Resources used by the child and its reaped descendants.
+    pub rusage: ResourceUsage,
+}
+
+impl JoinReport {
+    /// Returns whether the child was terminated for exceeding a CPU time limit of `limit`.
+    ///
+    /// This is the case if the child was terminated by `SIGXCPU`, or by `SIGKILL` after using at
+    /// least `limit` of CPU time, which is how the kernel enforces the hard limit.
+    #[cfg(unix)]
+    pub fn cpu_limit_exceeded(&self, limit: Duration) -> bool {
+        match self.status.signal() {
+            Some(libc::SIGXCPU) => true,
+            Some(libc::SIGKILL) => self.rusage.user_time + self.rusage.system_time >= limit,
+            _ => false,
+        }
+    }
+}
+
+/// Resource usage of a child process, as reported by `wait4`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct ResourceUsage {
+    /// CPU time spent in user mode.
+    pub user_time: Duration,
+    /// CPU time spent in kernel mode.
+    pub system_time: Duration,
+    /// Maximum resident set size, in bytes.
+    pub max_rss: u64,
+    /// Page faults serviced without I/O.
+    pub minor_faults: u64,
+    /// Page faults that required I/O.
+    pub major_faults: u64,
+    /// Context switches due to the process giving up the CPU voluntarily.
+    pub voluntary_context_switches: u64,
+    /// Context switches due to preemption.
+    pub involuntary_context_switches: u64,
+}
+
+#[cfg(unix)]
+impl From<libc::rusage> for ResourceUsage {
+    fn from(usage: libc::rusage) -> Self {
+        let duration =
+            |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+        Self {
+            user_time: duration(usage.ru_utime),
+            system_time: duration(usage.ru_stime),
+            // Apple platforms report the size in bytes, others in kilobytes.
+            #[cfg(target_vendor = "apple")]
+            max_rss: usage.ru_maxrss as u64,
+            #[cfg(not(target_vendor = "apple"))]
+            max_rss: usage.ru_maxrss as u64 * 1024,
+            minor_faults: usage.ru_minflt as u64,
+            major_faults: usage.ru_majflt as u64,
+            voluntary_context_switches: usage.ru_nvcsw as u64,
+            involuntary_context_switches: usage.ru_nivcsw as u64,
+        }
+    }
+}
+
+/// Error returned when waiting for a child process that panicked.
+///
+/// This is only reported for children spawned with
+/// [`ForkBuilder::capture_panics`](crate::ForkBuilder::capture_panics), and is wrapped in an
+/// [`std::io::Error`]. Use [`Error::get_ref`](std::io::Error::get_ref) and `downcast_ref` to
+/// retrieve it. Functions returning a [`ForkError`](crate::ForkError) report it as
+/// [`ForkError::Panicked`](crate::ForkError::Panicked) instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildPanicked {
+    /// The panic message.
+    pub message: String,
+    /// The backtrace captured at the point of panic.
+    pub backtrace: String,
+}
+
+impl fmt::Display for ChildPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "child process panicked: {}", self.message)
+    }
+}
+
+impl std::error::Error for ChildPanicked {}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::{IoSlice, IoSliceMut, Read, Result, Write};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+
+/// Handle to the standard input of a child process, if it is piped.
+///
+/// Dropping the handle closes the pipe, so the child reads end-of-file.
+#[derive(Debug)]
+pub struct ChildStdin(pub(crate) File);
+
+impl Write for ChildStdin {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Handle to the standard output of a child process, if it is piped.
+#[derive(Debug)]
+pub struct ChildStdout(pub(crate) File);
+
+/// Handle to the standard error of a child process, if it is piped.
+#[derive(Debug)]
+pub struct ChildStderr(pub(crate) File);
+
+macro_rules! impl_read {
+    ($ty:ty) => {
+        impl Read for $ty {
+            fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+                self.0.read(buf)
+            }
+
+            fn read_vectored(&mut self, bufs: &mut [IoSliceMut<'_>]) -> Result<usize> {
+                self.0.read_vectored(bufs)
+            }
+
+            fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+                self.0.read_to_end(buf)
+            }
+        }
+    };
+}
+
+impl_read!(ChildStdout);
+impl_read!(ChildStderr);
+
+macro_rules! impl_fd {
+    ($ty:ty) => {
+        impl AsFd for $ty {
+            fn as_fd(&self) -> BorrowedFd<'_> {
+                self.0.as_fd()
+            }
+        }
+
+        impl AsRawFd for $ty {
+            fn as_raw_fd(&self) -> RawFd {
+                self.0.as_raw_fd()
+            }
+        }
+
+        impl From<$ty> for OwnedFd {
+            fn from(stdio: $ty) -> OwnedFd {
+                stdio.0.into()
+            }
+        }
+    };
+}
+
+impl_fd!(ChildStdin);
+impl_fd!(ChildStdout);
+impl_fd!(ChildStderr);
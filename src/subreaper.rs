@@ -0,0 +1,88 @@
+use std::io::{Error, Result};
+
+use crate::{Child, ChildStatus};
+
+/// Marks the current process as a child subreaper, or unmarks it.
+///
+/// Orphaned descendants of a subreaper are reparented to it instead of to init, so that it can
+/// reap them, e.g. with [`reap_children`] or [`join_reaping`]. This is useful for processes that
+/// act as init for a group of processes, such as the init process of a container.
+///
+/// This is supported on Linux and FreeBSD, and fails with
+/// [`ErrorKind::Unsupported`](std::io::ErrorKind::Unsupported) elsewhere.
+pub fn set_child_subreaper(enabled: bool) -> Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))]
+    return crate::sys::set_child_subreaper(enabled);
+    #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+    {
+        let _ = enabled;
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Reaps all children of the current process that have exited, returning their PIDs and exit
+/// statuses.
+///
+/// This does not block. Unlike other functions of this crate, all children are reaped, including
+/// reparented descendants and children that have a [`Child`] handle; joining such a handle
+/// afterwards fails.
+pub fn reap_children() -> Result<Vec<(u32, ChildStatus)>> {
+    let mut exited = Vec::new();
+    loop {
+        let mut status = 0;
+        // SAFETY: `status` is valid for the duration of the call.
+        let ret = unsafe { libc::waitpid(-1, &mut status, libc::WNOHANG) };
+        if ret > 0 {
+            let status = ChildStatus::from_raw(status);
+            trace!(debug, pid = ret, status = ?status, "reaped child");
+            crate::registry::reaped(ret as u32, status);
+            exited.push((ret as u32, status));
+            continue;
+        }
+        if ret == 0 {
+            return Ok(exited);
+        }
+        let err = Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINTR) => (),
+            // There are no children left.
+            Some(libc::ECHILD) => return Ok(exited),
+            _ => return Err(err),
+        }
+    }
+}
+
+/// Waits for the child to exit, reaping all other children of the current process that exit in
+/// the meantime.
+///
+/// This is the main loop of an init-like process: the other children are typically reparented
+/// descendants of a [subreaper](set_child_subreaper). As with [`reap_children`], children that
+/// have a [`Child`] handle are reaped too.
+pub fn join_reaping(mut child: Child) -> Result<ChildStatus> {
+    if let Some(status) = child.try_join()? {
+        return Ok(status);
+    }
+    loop {
+        // SAFETY: all-zero is a valid `siginfo_t`.
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        // Peek at the exited child without reaping it, so that `child` can be joined normally.
+        // SAFETY: `info` is valid for the duration of the call.
+        let ret = unsafe { libc::waitid(libc::P_ALL, 0, &mut info, libc::WEXITED | libc::WNOWAIT) };
+        if ret < 0 {
+            let err = Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+
+        // SAFETY: `waitid` fills in the PID for `WEXITED`.
+        let pid = unsafe { info.si_pid() };
+        if pid == child.pid() as libc::pid_t {
+            return child.join();
+        }
+        // SAFETY: `waitpid` does not have special safety requirements.
+        unsafe { libc::waitpid(pid, std::ptr::null_mut(), 0) };
+        crate::registry::unregister(pid as u32);
+    }
+}
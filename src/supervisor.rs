@@ -0,0 +1,227 @@
+use std::io::Result;
+#[cfg(unix)]
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+use crate::{Child, ChildStatus, ForkError, Termination};
+
+/// When a supervised child should be restarted after it exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// Never restart the child.
+    Never,
+    /// Always restart the child, regardless of how it exited.
+    Always,
+    /// Restart the child only if it did not exit successfully.
+    OnFailure,
+}
+
+/// Policy describing how a supervised child is restarted.
+///
+/// By default, the number of restarts is unlimited and children are restarted immediately.
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    restart: Restart,
+    max_restarts: Option<u32>,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    /// Creates a policy that restarts children in the given condition.
+    pub fn new(restart: Restart) -> Self {
+        Self {
+            restart,
+            max_restarts: None,
+            backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    /// Limits the number of times that the child is restarted.
+    pub fn max_restarts(mut self, count: u32) -> Self {
+        self.max_restarts = Some(count);
+        self
+    }
+
+    /// Delays restarts, starting at `initial` and doubling after each restart up to `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+}
+
+/// A set of supervised child processes that are restarted according to their policies.
+///
+/// Each child is forked from the process that owns the supervisor and runs its closure again on
+/// every restart, so the closure may use any state that was available at that point.
+pub struct Supervisor {
+    workers: Vec<Worker>,
+}
+
+struct Worker {
+    f: Box<dyn FnMut() -> i32>,
+    policy: RestartPolicy,
+    child: Option<Child>,
+    restarts: u32,
+    /// Time at which the child is due to be restarted, if it is waiting for one.
+    restart_at: Option<Instant>,
+    /// Status that the child last exited with.
+    status: Option<ChildStatus>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Supervisor {
+    /// Creates a supervisor without any children.
+    pub fn new() -> Self {
+        Self {
+            workers: Vec::new(),
+        }
+    }
+
+    /// Spawns a supervised child running `f` with the given restart policy.
+    ///
+    /// The forking process must be single-threaded. Otherwise, this call will fail.
+    pub fn spawn<T: Termination>(
+        &mut self,
+        policy: RestartPolicy,
+        mut f: impl FnMut() -> T + 'static,
+    ) -> std::result::Result<(), ForkError> {
+        let mut worker = Worker {
+            f: Box::new(move || f().report()),
+            policy,
+            child: None,
+            restarts: 0,
+            restart_at: None,
+            status: None,
+        };
+        worker.start()?;
+        self.workers.push(worker);
+        Ok(())
+    }
+
+    /// Returns the PIDs of the children that are currently running, in the order they are spawned.
+    pub fn pids(&self) -> Vec<u32> {
+        self.workers
+            .iter()
+            .filter_map(|worker| worker.child.as_ref().map(Child::pid))
+            .collect()
+    }
+
+    /// Supervises the children until none of them is running or due to be restarted.
+    ///
+    /// Returns the status that each child last exited with, in the order they are spawned.
+    pub fn run(&mut self) -> std::result::Result<Vec<ChildStatus>, ForkError> {
+        loop {
+            let now = Instant::now();
+            let mut next_restart = None::<Instant>;
+            for worker in &mut self.workers {
+                if let Some(child) = &mut worker.child {
+                    let Some(status) = child.try_join().map_err(ForkError::from_wait)? else {
+                        continue;
+                    };
+                    worker.child = None;
+                    worker.status = Some(status);
+                    if worker.should_restart(status) {
+                        let delay = worker.delay();
+                        worker.restarts += 1;
+                        worker.restart_at = Some(now + delay);
+                    }
+                }
+                if let Some(restart_at) = worker.restart_at {
+                    if restart_at <= now {
+                        worker.restart_at = None;
+                        worker.start()?;
+                    } else {
+                        next_restart = Some(next_restart.map_or(restart_at, |t| t.min(restart_at)));
+                    }
+                }
+            }
+
+            let running: Vec<_> = self
+                .workers
+                .iter()
+                .flat_map(|worker| &worker.child)
+                .collect();
+            if running.is_empty() && next_restart.is_none() {
+                break;
+            }
+            let timeout = next_restart.map(|t| t.saturating_duration_since(Instant::now()));
+            #[cfg(unix)]
+            if running.iter().all(|child| child.pidfd().is_some()) {
+                let mut pollfds: Vec<_> = running
+                    .iter()
+                    .map(|child| libc::pollfd {
+                        fd: child.pidfd().unwrap().as_raw_fd(),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    })
+                    .collect();
+                crate::poll(&mut pollfds, timeout)?;
+                continue;
+            }
+            // Without pidfd support, fall back to polling at a fixed interval.
+            let interval = Duration::from_millis(10);
+            std::thread::sleep(timeout.map_or(interval, |t| t.min(interval)));
+        }
+
+        Ok(self
+            .workers
+            .iter()
+            .map(|worker| worker.status.unwrap())
+            .collect())
+    }
+
+    /// Stops supervising the children, asking the running ones to exit with `SIGTERM` and waiting
+    /// for them to do so.
+    ///
+    /// Pending restarts are cancelled.
+    pub fn shutdown(&mut self) -> Result<()> {
+        for worker in &mut self.workers {
+            worker.restart_at = None;
+            if let Some(child) = &worker.child {
+                child.terminate()?;
+            }
+        }
+        for worker in &mut self.workers {
+            if let Some(child) = worker.child.take() {
+                worker.status = Some(child.join()?);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Worker {
+    fn start(&mut self) -> std::result::Result<(), ForkError> {
+        self.child = Some(crate::fork_spawn(&mut self.f)?);
+        Ok(())
+    }
+
+    fn should_restart(&self, status: ChildStatus) -> bool {
+        let restart = match self.policy.restart {
+            Restart::Never => false,
+            Restart::Always => true,
+            Restart::OnFailure => !status.success(),
+        };
+        restart
+            && self
+                .policy
+                .max_restarts
+                .is_none_or(|max| self.restarts < max)
+    }
+
+    fn delay(&self) -> Duration {
+        let factor = 1u32.checked_shl(self.restarts).unwrap_or(u32::MAX);
+        self.policy
+            .backoff
+            .saturating_mul(factor)
+            .min(self.policy.max_backoff)
+    }
+}
@@ -0,0 +1,311 @@
+use std::io::Result;
+#[cfg(feature = "bytemuck")]
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::shm::Mapping;
+
+/// Wait until woken, if `atomic` still holds `expected`, for at most `timeout` if given.
+///
+/// Spurious wake-ups are possible, so the caller must check the condition again.
+fn futex_wait(atomic: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+    let timeout = timeout.map(|timeout| libc::timespec {
+        tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+        tv_nsec: timeout.subsec_nanos() as _,
+    });
+    let timeout = timeout
+        .as_ref()
+        .map_or(std::ptr::null(), |timeout| timeout as *const libc::timespec);
+    // SAFETY: `atomic` and `timeout` are valid for the duration of the call. The futex is not
+    // private, as it is shared between processes.
+    unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            atomic.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            timeout,
+        )
+    };
+}
+
+/// Wake up at most `count` processes waiting on `atomic`.
+fn futex_wake(atomic: &AtomicU32, count: i32) {
+    // SAFETY: `atomic` is valid for the duration of the call.
+    unsafe { libc::syscall(libc::SYS_futex, atomic.as_ptr(), libc::FUTEX_WAKE, count) };
+}
+
+/// A mutual exclusion primitive shared between a process and the children that it forks
+/// afterwards, protecting a value in shared memory.
+///
+/// This is implemented with a futex in shared memory, so the lock works across processes as
+/// well as across threads. Unlike [`std::sync::Mutex`], there is no poisoning, and if a process
+/// exits while holding the lock, the lock is never released.
+///
+/// This requires the `bytemuck` feature.
+///
+/// # Example
+///
+/// ```
+/// use safe_fork::ProcessMutex;
+///
+/// let total = ProcessMutex::new(0u64).unwrap();
+/// let children: Vec<_> = (0..4)
+///     .map(|_| safe_fork::fork_spawn(|| *total.lock() += 1).unwrap())
+///     .collect();
+/// safe_fork::join_all(children).unwrap();
+/// assert_eq!(*total.lock(), 4);
+/// ```
+#[cfg(feature = "bytemuck")]
+#[derive(Debug)]
+pub struct ProcessMutex<T> {
+    mapping: Mapping,
+    _marker: std::marker::PhantomData<T>,
+}
+
+// SAFETY: access to the value is serialized by the lock, and `Pod` values can be sent anywhere.
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> Send for ProcessMutex<T> {}
+// SAFETY: same as above.
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: bytemuck::Pod> Sync for ProcessMutex<T> {}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> ProcessMutex<T> {
+    /// Creates an unlocked mutex holding `value`.
+    pub fn new(value: T) -> Result<Self> {
+        let mapping = Mapping::new(Self::offset() + std::mem::size_of::<T>())?;
+        // SAFETY: the mapping is large enough and suitably aligned, and nothing else refers to
+        // it yet.
+        unsafe {
+            mapping
+                .as_ptr()
+                .add(Self::offset())
+                .cast::<T>()
+                .write(value)
+        };
+        Ok(Self {
+            mapping,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    /// Offset of the value within the mapping, after the lock state.
+    fn offset() -> usize {
+        std::mem::size_of::<AtomicU32>().next_multiple_of(std::mem::align_of::<T>())
+    }
+
+    /// State of the lock: 0 if unlocked, 1 if locked, and 2 if locked with possible waiters.
+    fn state(&self) -> &AtomicU32 {
+        // SAFETY: the mapping is page-aligned, and starts with the lock state.
+        unsafe { &*self.mapping.as_ptr().cast() }
+    }
+
+    /// Acquires the lock, blocking until it is available.
+    pub fn lock(&self) -> ProcessMutexGuard<'_, T> {
+        if self
+            .state()
+            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // Mark the lock as contended, so that the holder wakes us when releasing.
+            while self.state().swap(2, Ordering::Acquire) != 0 {
+                futex_wait(self.state(), 2, None);
+            }
+        }
+        ProcessMutexGuard { mutex: self }
+    }
+
+    /// Attempts to acquire the lock without blocking.
+    pub fn try_lock(&self) -> Option<ProcessMutexGuard<'_, T>> {
+        self.state()
+            .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| ProcessMutexGuard { mutex: self })
+    }
+}
+
+/// Guard of a locked [`ProcessMutex`], giving access to the value. The lock is released when the
+/// guard is dropped.
+#[cfg(feature = "bytemuck")]
+#[derive(Debug)]
+pub struct ProcessMutexGuard<'a, T: bytemuck::Pod> {
+    mutex: &'a ProcessMutex<T>,
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> Deref for ProcessMutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the value is initialized, and the lock is held.
+        unsafe {
+            &*self
+                .mutex
+                .mapping
+                .as_ptr()
+                .add(ProcessMutex::<T>::offset())
+                .cast()
+        }
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> DerefMut for ProcessMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: the value is initialized, and the lock is held exclusively.
+        unsafe {
+            &mut *self
+                .mutex
+                .mapping
+                .as_ptr()
+                .add(ProcessMutex::<T>::offset())
+                .cast()
+        }
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> Drop for ProcessMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.mutex.state().swap(0, Ordering::Release) == 2 {
+            futex_wake(self.mutex.state(), 1);
+        }
+    }
+}
+
+/// A barrier shared between a process and the children that it forks afterwards, which blocks
+/// until a fixed number of processes have reached it.
+///
+/// The barrier can be reused once all processes have passed it.
+#[derive(Debug)]
+pub struct ProcessBarrier {
+    mapping: Mapping,
+    count: u32,
+}
+
+// SAFETY: the state is only accessed through atomics.
+unsafe impl Send for ProcessBarrier {}
+// SAFETY: same as above.
+unsafe impl Sync for ProcessBarrier {}
+
+impl ProcessBarrier {
+    /// Creates a barrier that blocks until `count` processes have called [`wait`](Self::wait).
+    pub fn new(count: u32) -> Result<Self> {
+        Ok(Self {
+            mapping: Mapping::new(2 * std::mem::size_of::<AtomicU32>())?,
+            count,
+        })
+    }
+
+    /// Number of processes that have arrived in the current generation.
+    fn arrived(&self) -> &AtomicU32 {
+        // SAFETY: the mapping is page-aligned, and holds two atomics.
+        unsafe { &*self.mapping.as_ptr().cast() }
+    }
+
+    /// Generation of the barrier, incremented each time all processes have arrived.
+    fn generation(&self) -> &AtomicU32 {
+        // SAFETY: same as above.
+        unsafe { &*self.mapping.as_ptr().cast::<AtomicU32>().add(1) }
+    }
+
+    /// Blocks until all processes have reached the barrier.
+    ///
+    /// Returns `true` in exactly one of the processes, the last one to arrive.
+    pub fn wait(&self) -> bool {
+        let generation = self.generation().load(Ordering::Acquire);
+        if self.arrived().fetch_add(1, Ordering::AcqRel) + 1 >= self.count {
+            // The others only proceed once the generation changes, so the count can be reset
+            // before that.
+            self.arrived().store(0, Ordering::Relaxed);
+            self.generation().fetch_add(1, Ordering::Release);
+            futex_wake(self.generation(), i32::MAX);
+            return true;
+        }
+        while self.generation().load(Ordering::Acquire) == generation {
+            futex_wait(self.generation(), generation, None);
+        }
+        false
+    }
+}
+
+/// An event shared between a process and the children that it forks afterwards, which processes
+/// can wait for until it is set, e.g. to signal that a child is ready.
+///
+/// The event stays set until [`reset`](Self::reset) is called.
+///
+/// # Example
+///
+/// ```
+/// use safe_fork::Event;
+///
+/// let ready = Event::new().unwrap();
+/// let child = safe_fork::fork_spawn(|| ready.set()).unwrap();
+/// ready.wait();
+/// child.join().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Event {
+    mapping: Mapping,
+}
+
+// SAFETY: the state is only accessed through atomics.
+unsafe impl Send for Event {}
+// SAFETY: same as above.
+unsafe impl Sync for Event {}
+
+impl Event {
+    /// Creates an event that is not set.
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            mapping: Mapping::new(std::mem::size_of::<AtomicU32>())?,
+        })
+    }
+
+    /// 1 if the event is set, 0 otherwise.
+    fn state(&self) -> &AtomicU32 {
+        // SAFETY: the mapping is page-aligned, and holds an atomic.
+        unsafe { &*self.mapping.as_ptr().cast() }
+    }
+
+    /// Sets the event, waking up all processes waiting for it.
+    pub fn set(&self) {
+        if self.state().swap(1, Ordering::Release) == 0 {
+            futex_wake(self.state(), i32::MAX);
+        }
+    }
+
+    /// Clears the event.
+    pub fn reset(&self) {
+        self.state().store(0, Ordering::Relaxed);
+    }
+
+    /// Returns whether the event is set.
+    pub fn is_set(&self) -> bool {
+        self.state().load(Ordering::Acquire) == 1
+    }
+
+    /// Blocks until the event is set.
+    pub fn wait(&self) {
+        while !self.is_set() {
+            futex_wait(self.state(), 0, None);
+        }
+    }
+
+    /// Blocks until the event is set, for at most `timeout`.
+    ///
+    /// Returns whether the event is set.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.is_set() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            futex_wait(self.state(), 0, Some(remaining));
+        }
+        true
+    }
+}
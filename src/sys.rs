@@ -0,0 +1,181 @@
+//! Wrappers of system calls, implemented with `rustix` if the `rustix` feature is enabled and with
+//! `libc` otherwise.
+//!
+//! `fork` itself goes through the C library, so that its `atfork` handlers run and its internal
+//! state stays consistent in the child. The exceptions are children that execute a program right
+//! away, and `clone3` flags that cannot be applied with `unshare`, see
+//! [`CloneFlags`](crate::CloneFlags). `wait4` always goes through the C library, as `rustix` does
+//! not report resource usage.
+
+use std::io::Result;
+#[cfg(target_os = "linux")]
+use std::os::fd::{BorrowedFd, OwnedFd};
+
+/// Location of the thread-local `errno`, which signal handlers must preserve.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) use libc::__errno_location as errno_location;
+/// Location of the thread-local `errno`, which signal handlers must preserve.
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+pub(crate) use libc::__error as errno_location;
+
+/// Unshare the address space of the calling thread, which fails with `EINVAL` if it is shared
+/// with other threads.
+#[cfg(all(target_os = "linux", not(feature = "rustix")))]
+pub(crate) fn unshare_vm() -> Result<()> {
+    // SAFETY: `unshare` does not have special safety requirements.
+    if unsafe { libc::unshare(libc::CLONE_VM) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Unshare the address space of the calling thread, which fails with `EINVAL` if it is shared
+/// with other threads.
+#[cfg(all(target_os = "linux", feature = "rustix"))]
+pub(crate) fn unshare_vm() -> Result<()> {
+    // `rustix` does not name `CLONE_VM`, as unsharing it is otherwise meaningless.
+    let flags = rustix::thread::UnshareFlags::from_bits_retain(libc::CLONE_VM as _);
+    // SAFETY: the file descriptor table is not unshared.
+    unsafe { rustix::thread::unshare_unsafe(flags) }?;
+    Ok(())
+}
+
+/// Obtain a file descriptor that refers to the process.
+#[cfg(all(target_os = "linux", not(feature = "rustix")))]
+pub(crate) fn pidfd_open(pid: libc::pid_t) -> Result<OwnedFd> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: `pidfd_open` does not have special safety requirements.
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: the file descriptor is freshly created and exclusively owned.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as _) })
+}
+
+/// Obtain a file descriptor that refers to the process.
+#[cfg(all(target_os = "linux", feature = "rustix"))]
+pub(crate) fn pidfd_open(pid: libc::pid_t) -> Result<OwnedFd> {
+    let pid = rustix::process::Pid::from_raw(pid).ok_or(std::io::ErrorKind::InvalidInput)?;
+    Ok(rustix::process::pidfd_open(
+        pid,
+        rustix::process::PidfdFlags::empty(),
+    )?)
+}
+
+/// Send a signal to the process referred to by the pidfd.
+#[cfg(all(target_os = "linux", not(feature = "rustix")))]
+pub(crate) fn pidfd_send_signal(pidfd: BorrowedFd<'_>, sig: i32) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: `pidfd_send_signal` does not have special safety requirements.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd.as_raw_fd(),
+            sig,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        )
+    };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Send a signal to the process referred to by the pidfd.
+#[cfg(all(target_os = "linux", feature = "rustix"))]
+pub(crate) fn pidfd_send_signal(pidfd: BorrowedFd<'_>, sig: i32) -> Result<()> {
+    Ok(rustix::process::pidfd_send_signal(pidfd, signal(sig)?)?)
+}
+
+/// Send a signal to the process.
+#[cfg(not(feature = "rustix"))]
+pub(crate) fn kill(pid: libc::pid_t, sig: i32) -> Result<()> {
+    // SAFETY: `kill` does not have special safety requirements.
+    if unsafe { libc::kill(pid, sig) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Send a signal to the process.
+#[cfg(feature = "rustix")]
+pub(crate) fn kill(pid: libc::pid_t, sig: i32) -> Result<()> {
+    let pid = rustix::process::Pid::from_raw(pid).ok_or(std::io::ErrorKind::InvalidInput)?;
+    Ok(rustix::process::kill_process(pid, signal(sig)?)?)
+}
+
+/// Send a signal to the process group.
+#[cfg(not(feature = "rustix"))]
+pub(crate) fn kill_process_group(pgid: libc::pid_t, sig: i32) -> Result<()> {
+    // SAFETY: `killpg` does not have special safety requirements.
+    if unsafe { libc::killpg(pgid, sig) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Send a signal to the process group.
+#[cfg(feature = "rustix")]
+pub(crate) fn kill_process_group(pgid: libc::pid_t, sig: i32) -> Result<()> {
+    let pgid = rustix::process::Pid::from_raw(pgid).ok_or(std::io::ErrorKind::InvalidInput)?;
+    Ok(rustix::process::kill_process_group(pgid, signal(sig)?)?)
+}
+
+/// Convert a raw signal number, rejecting the ones that are invalid or reserved by the C library.
+#[cfg(feature = "rustix")]
+fn signal(sig: i32) -> Result<rustix::process::Signal> {
+    #[cfg(target_os = "linux")]
+    let realtime = (libc::SIGRTMIN()..=libc::SIGRTMAX()).contains(&sig);
+    #[cfg(not(target_os = "linux"))]
+    let realtime = false;
+    if !(1..=31).contains(&sig) && !realtime {
+        return Err(rustix::io::Errno::INVAL.into());
+    }
+    // SAFETY: the signal number is valid, and not reserved by the C library.
+    Ok(unsafe { rustix::process::Signal::from_raw_unchecked(sig) })
+}
+
+/// Mark or unmark the calling process as a reaper of its orphaned descendants.
+#[cfg(all(target_os = "linux", not(feature = "rustix")))]
+pub(crate) fn set_child_subreaper(enabled: bool) -> Result<()> {
+    // SAFETY: `PR_SET_CHILD_SUBREAPER` does not have special safety requirements.
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, enabled as libc::c_ulong) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Mark or unmark the calling process as a reaper of its orphaned descendants.
+#[cfg(all(target_os = "linux", feature = "rustix"))]
+pub(crate) fn set_child_subreaper(enabled: bool) -> Result<()> {
+    // The kernel only checks whether the argument is non-zero, which any PID is.
+    Ok(rustix::process::set_child_subreaper(
+        enabled.then(rustix::process::getpid),
+    )?)
+}
+
+/// Mark or unmark the calling process as a reaper of its orphaned descendants.
+#[cfg(all(target_os = "freebsd", not(feature = "rustix")))]
+pub(crate) fn set_child_subreaper(enabled: bool) -> Result<()> {
+    let cmd = if enabled {
+        libc::PROC_REAP_ACQUIRE
+    } else {
+        libc::PROC_REAP_RELEASE
+    };
+    // SAFETY: `PROC_REAP_ACQUIRE` and `PROC_REAP_RELEASE` do not take any data.
+    let ret = unsafe { libc::procctl(libc::P_PID, 0, cmd, std::ptr::null_mut()) };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Mark or unmark the calling process as a reaper of its orphaned descendants.
+#[cfg(all(target_os = "freebsd", feature = "rustix"))]
+pub(crate) fn set_child_subreaper(enabled: bool) -> Result<()> {
+    Ok(rustix::process::set_reaper_status(enabled)?)
+}
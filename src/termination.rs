@@ -0,0 +1,49 @@
+use std::fmt::Debug;
+use std::process::ExitCode;
+
+/// Return type of closures run in child processes, determining the exit code.
+///
+/// This mirrors [`std::process::Termination`], which cannot be used directly as it is not
+/// implemented for `i32` and does not expose the exit code of [`ExitCode`].
+///
+/// As `!` cannot implement this trait on stable Rust, closures that never return need an explicit
+/// return type, e.g. `|| -> i32 { loop {} }`.
+pub trait Termination {
+    /// Returns the exit code of the child process.
+    fn report(self) -> i32;
+}
+
+impl Termination for i32 {
+    fn report(self) -> i32 {
+        self
+    }
+}
+
+impl Termination for () {
+    fn report(self) -> i32 {
+        0
+    }
+}
+
+impl Termination for ExitCode {
+    fn report(self) -> i32 {
+        // `ExitCode` is a `u8` on Unix, but there is no stable way to extract it.
+        (0..=u8::MAX)
+            .find(|&code| ExitCode::from(code) == self)
+            .map_or(1, i32::from)
+    }
+}
+
+impl<T: Termination, E: Debug> Termination for Result<T, E> {
+    /// Reports the exit code of `Ok`; for `Err`, prints the error to stderr and returns 1, same as
+    /// returning it from `main`.
+    fn report(self) -> i32 {
+        match self {
+            Ok(value) => value.report(),
+            Err(err) => {
+                eprintln!("Error: {err:?}");
+                1
+            }
+        }
+    }
+}
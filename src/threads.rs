@@ -0,0 +1,119 @@
+#[cfg(any(target_os = "linux", target_vendor = "apple", target_os = "freebsd"))]
+use std::io::Error;
+use std::io::Result;
+
+/// Information about a thread of the current process.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ThreadInfo {
+    /// The thread ID.
+    pub tid: u32,
+    /// The name of the thread, as set with `pthread_setname_np` or `prctl(PR_SET_NAME)`.
+    pub name: String,
+}
+
+/// Returns the number of threads in the current process.
+///
+/// This reads the `Threads:` field of `/proc/self/status`.
+#[cfg(target_os = "linux")]
+pub fn thread_count() -> Result<usize> {
+    let status = std::fs::read_to_string("/proc/self/status")?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))
+        .and_then(|count| count.trim().parse().ok())
+        .ok_or_else(|| {
+            Error::new(
+                std::io::ErrorKind::InvalidData,
+                "malformed /proc/self/status",
+            )
+        })
+}
+
+/// Returns the number of threads in the current process.
+///
+/// This queries the task information with `proc_pidinfo`.
+#[cfg(target_vendor = "apple")]
+pub fn thread_count() -> Result<usize> {
+    // SAFETY: all-zero is a valid `proc_taskinfo`.
+    let mut info: libc::proc_taskinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    // SAFETY: `info` is valid for writes of `size` bytes.
+    let ret = unsafe {
+        libc::proc_pidinfo(
+            libc::getpid(),
+            libc::PROC_PIDTASKINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    if ret != size {
+        return Err(Error::last_os_error());
+    }
+    Ok(info.pti_threadnum as usize)
+}
+
+/// Returns the number of threads in the current process.
+///
+/// This queries the process information with the `kern.proc.pid` sysctl.
+#[cfg(target_os = "freebsd")]
+pub fn thread_count() -> Result<usize> {
+    // SAFETY: `getpid` does not have special safety requirements.
+    let mib = [
+        libc::CTL_KERN,
+        libc::KERN_PROC,
+        libc::KERN_PROC_PID,
+        unsafe { libc::getpid() },
+    ];
+    // SAFETY: all-zero is a valid `kinfo_proc`.
+    let mut info: libc::kinfo_proc = unsafe { std::mem::zeroed() };
+    let mut size = std::mem::size_of::<libc::kinfo_proc>();
+    // SAFETY: `mib` is valid for reads and `info` is valid for writes of `size` bytes.
+    let ret = unsafe {
+        libc::sysctl(
+            mib.as_ptr(),
+            mib.len() as _,
+            &mut info as *mut _ as *mut libc::c_void,
+            &mut size,
+            std::ptr::null(),
+            0,
+        )
+    };
+    if ret < 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(info.ki_numthreads as usize)
+}
+
+/// Returns the number of threads in the current process.
+///
+/// This is not supported on the current platform, and always fails with
+/// [`std::io::ErrorKind::Unsupported`].
+#[cfg(not(any(target_os = "linux", target_vendor = "apple", target_os = "freebsd")))]
+pub fn thread_count() -> Result<usize> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Lists the threads in the current process, ordered by thread ID.
+///
+/// This enumerates `/proc/self/task`. Threads that exit during the enumeration are skipped.
+#[cfg(target_os = "linux")]
+pub fn threads() -> Result<Vec<ThreadInfo>> {
+    let mut threads = Vec::new();
+    for entry in std::fs::read_dir("/proc/self/task")? {
+        let entry = entry?;
+        let Some(tid) = entry.file_name().to_str().and_then(|tid| tid.parse().ok()) else {
+            continue;
+        };
+        let name = match std::fs::read_to_string(entry.path().join("comm")) {
+            Ok(name) => name.trim_end_matches('\n').to_owned(),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        threads.push(ThreadInfo { tid, name });
+    }
+    threads.sort_by_key(|thread| thread.tid);
+    Ok(threads)
+}
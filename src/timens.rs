@@ -0,0 +1,59 @@
+use std::fs::File;
+use std::io::{Error, Result};
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+/// Offsets of the clocks in a new time namespace, see
+/// [`ForkBuilder::new_time_ns`](crate::ForkBuilder::new_time_ns).
+///
+/// Only `CLOCK_MONOTONIC` and `CLOCK_BOOTTIME` (and their coarse and raw variants) can be shifted;
+/// `CLOCK_REALTIME` is not affected by time namespaces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeOffsets {
+    monotonic: Duration,
+    boottime: Duration,
+}
+
+impl TimeOffsets {
+    /// Creates offsets that leave both clocks unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Advances `CLOCK_MONOTONIC` by `offset`.
+    pub fn monotonic(mut self, offset: Duration) -> Self {
+        self.monotonic = offset;
+        self
+    }
+
+    /// Advances `CLOCK_BOOTTIME` by `offset`.
+    pub fn boottime(mut self, offset: Duration) -> Self {
+        self.boottime = offset;
+        self
+    }
+
+    /// Moves the calling process into a new time namespace with the offsets.
+    ///
+    /// The offsets can only be set before any process enters the namespace, so the namespace is
+    /// created for children with `unshare`, and entered with `setns` afterwards.
+    pub(crate) fn apply(&self) -> Result<()> {
+        // SAFETY: `unshare` does not have special safety requirements.
+        if unsafe { libc::unshare(libc::CLONE_NEWTIME) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        let offsets = format!(
+            "monotonic {} {}\nboottime {} {}\n",
+            self.monotonic.as_secs(),
+            self.monotonic.subsec_nanos(),
+            self.boottime.as_secs(),
+            self.boottime.subsec_nanos(),
+        );
+        std::fs::write("/proc/self/timens_offsets", offsets)?;
+        let ns = File::open("/proc/self/ns/time_for_children")?;
+        // SAFETY: `ns` is a valid time namespace file descriptor.
+        if unsafe { libc::setns(ns.as_raw_fd(), libc::CLONE_NEWTIME) } < 0 {
+            return Err(Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1163 @@
+//! Stand-ins for the public API on targets without `fork`, so that crates depending on this one
+//! still build there.
+//!
+//! Every function that would create a child process fails with [`ErrorKind::Unsupported`], and no
+//! [`Child`] can ever exist. Functions that only inspect or configure the current process behave
+//! as if no child had ever been created.
+
+use std::convert::Infallible;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+#[cfg(feature = "serde")]
+use std::marker::PhantomData;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::Output;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+#[cfg(feature = "serde")]
+use crate::{Bincode, Codec};
+use crate::{
+    ChildStatus, ForkError, JoinReport, ResourceUsage, RestartPolicy, Rlimits, Supervisor,
+    Termination,
+};
+
+fn unsupported() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "processes cannot be forked on this target",
+    )
+}
+
+/// Handle of a child process.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[derive(Debug)]
+pub struct Child {
+    /// Handle for writing to the standard input of the child, if it is piped.
+    pub stdin: Option<ChildStdin>,
+    /// Handle for reading from the standard output of the child, if it is piped.
+    pub stdout: Option<ChildStdout>,
+    /// Handle for reading from the standard error of the child, if it is piped.
+    pub stderr: Option<ChildStderr>,
+    never: Infallible,
+}
+
+impl Child {
+    /// Returns the OS-assigned process identifier associated with this child.
+    pub fn pid(&self) -> u32 {
+        match self.never {}
+    }
+
+    /// Returns whether the child is still running.
+    pub fn is_alive(&self) -> Result<bool> {
+        match self.never {}
+    }
+
+    /// Returns the time at which the child was spawned.
+    pub fn spawned_at(&self) -> Instant {
+        match self.never {}
+    }
+
+    /// Waits for the child to exit completely, returning the status that it exited with.
+    pub fn join(self) -> Result<ChildStatus> {
+        match self.never {}
+    }
+
+    /// Returns the status of the child if it has exited, without reaping it.
+    pub fn peek_status(&self) -> Result<Option<ChildStatus>> {
+        match self.never {}
+    }
+
+    /// Waits for the child to exit, returning its status and resource usage.
+    pub fn join_with_rusage(self) -> Result<(ChildStatus, ResourceUsage)> {
+        match self.never {}
+    }
+
+    /// Waits for the child to exit, returning its status, lifetime and resource usage.
+    pub fn join_with_report(self) -> Result<JoinReport> {
+        match self.never {}
+    }
+
+    /// Waits for the child to exit, classifying how it ended.
+    pub fn join_checked(self) -> std::result::Result<ChildStatus, ForkError> {
+        match self.never {}
+    }
+
+    /// Waits for the child to exit, collecting all remaining output on piped stdout and stderr.
+    pub fn wait_with_output(self) -> Result<Output> {
+        match self.never {}
+    }
+
+    /// Returns the status of the child if it has exited, without blocking.
+    pub fn try_join(&mut self) -> Result<Option<ChildStatus>> {
+        match self.never {}
+    }
+
+    /// Waits for the child to exit for at most `timeout`.
+    pub fn join_timeout(
+        self,
+        _timeout: Duration,
+    ) -> Result<std::result::Result<ChildStatus, Child>> {
+        match self.never {}
+    }
+
+    /// Releases the child without waiting for it.
+    pub fn detach(self) {
+        match self.never {}
+    }
+
+    /// Sends the signal `sig` to the child.
+    pub fn signal(&self, _sig: i32) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Forces the child to exit.
+    pub fn kill(&self) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Forces the child and all its descendants to exit.
+    pub fn kill_tree(&self) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Asks the child to exit.
+    pub fn terminate(&self) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Asks the child to exit, and forces it to if it does not within `grace`.
+    pub fn shutdown(self, _grace: Duration) -> Result<ChildStatus> {
+        match self.never {}
+    }
+
+    /// Stops the child until it is resumed.
+    pub fn suspend(&self) -> Result<()> {
+        match self.never {}
+    }
+
+    /// Resumes the child after it is stopped.
+    pub fn resume(&self) -> Result<()> {
+        match self.never {}
+    }
+}
+
+/// Handle to the standard input of a child process, if it is piped.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[derive(Debug)]
+pub struct ChildStdin {
+    never: Infallible,
+}
+
+impl Write for ChildStdin {
+    fn write(&mut self, _buf: &[u8]) -> Result<usize> {
+        match self.never {}
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self.never {}
+    }
+}
+
+/// Handle to the standard output of a child process, if it is piped.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[derive(Debug)]
+pub struct ChildStdout {
+    never: Infallible,
+}
+
+/// Handle to the standard error of a child process, if it is piped.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[derive(Debug)]
+pub struct ChildStderr {
+    never: Infallible,
+}
+
+impl Read for ChildStdout {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+        match self.never {}
+    }
+}
+
+impl Read for ChildStderr {
+    fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+        match self.never {}
+    }
+}
+
+/// Waits for all children to exit, returning their statuses in order.
+pub fn join_all(children: impl IntoIterator<Item = Child>) -> Result<Vec<ChildStatus>> {
+    children.into_iter().map(Child::join).collect()
+}
+
+/// Waits for any of the children to exit. Always fails on this target, as `children` is empty.
+pub fn wait_any(_children: &mut Vec<Child>) -> Result<(u32, ChildStatus)> {
+    Err(Error::new(
+        ErrorKind::InvalidInput,
+        "no children to wait for",
+    ))
+}
+
+/// Result of [`fork`].
+#[must_use]
+#[derive(Debug)]
+pub enum ForkResult {
+    /// Returned in the parent process, with the handle of the child.
+    Parent(Child),
+    /// Returned in the child process.
+    Child,
+}
+
+/// Ensures the current process is single-threaded. Always fails on this target.
+pub fn ensure_single_threaded() -> std::result::Result<(), ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Check if the current process is single-threaded. Always `false` on this target.
+pub fn is_single_threaded() -> bool {
+    false
+}
+
+/// Fork the current process. Always fails on this target.
+pub fn fork() -> std::result::Result<ForkResult, ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Fork the current process, and execute the provided closure within child process. Always fails
+/// on this target.
+pub fn fork_spawn<T: Termination>(_f: impl FnOnce() -> T) -> std::result::Result<Child, ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete. Always fails on this target.
+pub fn fork_join<T: Termination>(_f: impl FnOnce() -> T) -> std::result::Result<i32, ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete for at most `deadline`. Always fails on this target.
+pub fn fork_join_deadline<T: Termination>(
+    _f: impl FnOnce() -> T,
+    _deadline: Duration,
+) -> std::result::Result<i32, ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete, collecting its standard output and standard error. Always fails on this target.
+pub fn fork_output<T: Termination>(
+    _f: impl FnOnce() -> T,
+) -> std::result::Result<Output, ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Spawn a child process executing `program`. Always fails on this target.
+pub fn fork_exec<S, K, V>(
+    _program: impl AsRef<OsStr>,
+    _args: impl IntoIterator<Item = S>,
+    _env: impl IntoIterator<Item = (K, V)>,
+) -> Result<Child>
+where
+    S: AsRef<OsStr>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    Err(unsupported())
+}
+
+/// Describes what to do with a standard I/O stream of the child process.
+#[derive(Debug, Default)]
+pub enum Stdio {
+    /// The child inherits the stream from the parent.
+    #[default]
+    Inherit,
+    /// The stream is redirected to the null device.
+    Null,
+    /// A pipe is created to connect the parent and the child.
+    Piped,
+}
+
+/// How [`ForkBuilder::exec`] creates the child process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpawnBackend {
+    /// Fork, and fall back to `posix_spawn` if forking fails.
+    #[default]
+    Auto,
+    /// Always fork.
+    Fork,
+    /// Always use `posix_spawn`.
+    PosixSpawn,
+}
+
+/// Builder for configuring and spawning a child process.
+///
+/// The configuration is accepted but never used, as spawning always fails on this target.
+#[derive(Debug, Default)]
+pub struct ForkBuilder {
+    _private: (),
+}
+
+impl ForkBuilder {
+    /// Creates a new builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the working directory of the child.
+    pub fn chdir(&mut self, _dir: impl AsRef<Path>) -> &mut Self {
+        self
+    }
+
+    /// Changes the root directory of the child.
+    pub fn chroot(&mut self, _path: impl AsRef<Path>) -> &mut Self {
+        self
+    }
+
+    /// Puts the child in a new process group.
+    pub fn new_process_group(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Starts a new session in the child.
+    pub fn new_session(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Resets the signal handlers of the child to their defaults.
+    pub fn reset_signal_handlers(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Sanitizes the state inherited by the child.
+    pub fn sanitize_child(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Registers a hook wiping sensitive data in the child.
+    pub fn scrub(&mut self, _hook: impl Fn() + Send + Sync + 'static) -> &mut Self {
+        self
+    }
+
+    /// Sets the file mode creation mask of the child.
+    pub fn umask(&mut self, _mask: u32) -> &mut Self {
+        self
+    }
+
+    /// Configures the standard input of the child.
+    pub fn stdin(&mut self, _cfg: impl Into<Stdio>) -> &mut Self {
+        self
+    }
+
+    /// Configures the standard output of the child.
+    pub fn stdout(&mut self, _cfg: impl Into<Stdio>) -> &mut Self {
+        self
+    }
+
+    /// Configures the standard error of the child.
+    pub fn stderr(&mut self, _cfg: impl Into<Stdio>) -> &mut Self {
+        self
+    }
+
+    /// Redirects the standard error of the child to its standard output.
+    pub fn stderr_to_stdout(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Sets an environment variable in the child.
+    pub fn env(&mut self, _key: impl AsRef<OsStr>, _val: impl AsRef<OsStr>) -> &mut Self {
+        self
+    }
+
+    /// Removes an environment variable in the child.
+    pub fn env_remove(&mut self, _key: impl AsRef<OsStr>) -> &mut Self {
+        self
+    }
+
+    /// Clears the environment of the child.
+    pub fn env_clear(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Enables core dumps of the child into `dir`.
+    pub fn core_dumps(&mut self, _dir: impl AsRef<Path>) -> &mut Self {
+        self
+    }
+
+    /// Sets the resource limits of the child.
+    pub fn rlimits(&mut self, _limits: Rlimits) -> &mut Self {
+        self
+    }
+
+    /// Limits the CPU time of the child.
+    pub fn cpu_limit(&mut self, _limit: Duration) -> &mut Self {
+        self
+    }
+
+    /// Limits the memory of the child.
+    pub fn memory_limit(&mut self, _bytes: u64) -> &mut Self {
+        self
+    }
+
+    /// Sets the nice value of the child.
+    pub fn nice(&mut self, _value: i32) -> &mut Self {
+        self
+    }
+
+    /// Sets the user ID of the child.
+    pub fn uid(&mut self, _id: u32) -> &mut Self {
+        self
+    }
+
+    /// Sets the group ID of the child.
+    pub fn gid(&mut self, _id: u32) -> &mut Self {
+        self
+    }
+
+    /// Sets the supplementary groups of the child.
+    pub fn groups(&mut self, _groups: &[u32]) -> &mut Self {
+        self
+    }
+
+    /// Closes the file descriptors inherited by the child, other than its standard I/O.
+    pub fn close_fds(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Sets the exit code of the child when the closure panics.
+    pub fn panic_exit_code(&mut self, _code: i32) -> &mut Self {
+        self
+    }
+
+    /// Sends panic messages of the child back to the parent.
+    pub fn capture_panics(&mut self) -> &mut Self {
+        self
+    }
+
+    /// Sets how [`ForkBuilder::exec`] creates the child.
+    pub fn spawn_backend(&mut self, _backend: SpawnBackend) -> &mut Self {
+        self
+    }
+
+    /// Fork the current process, and execute the provided closure within child process. Always
+    /// fails on this target.
+    pub fn spawn<T: Termination>(
+        &mut self,
+        _f: impl FnOnce() -> T,
+    ) -> std::result::Result<Child, ForkError> {
+        Err(ForkError::Io(unsupported()))
+    }
+
+    /// Fork a child process that serves requests with `handler`. Always fails on this target.
+    #[cfg(feature = "serde")]
+    pub fn spawn_service<Req, Resp>(
+        &mut self,
+        _handler: impl FnMut(Req) -> Resp,
+    ) -> Result<ServiceHandle<Req, Resp>>
+    where
+        Req: Serialize + DeserializeOwned,
+        Resp: Serialize + DeserializeOwned,
+    {
+        Err(unsupported())
+    }
+
+    /// Fork a child process that serves requests with `handler`, serializing them with the codec
+    /// `C`. Always fails on this target.
+    #[cfg(feature = "serde")]
+    pub fn spawn_service_codec<C, Req, Resp>(
+        &mut self,
+        _handler: impl FnMut(Req) -> Resp,
+    ) -> Result<ServiceHandle<Req, Resp, C>>
+    where
+        C: Codec,
+        Req: Serialize + DeserializeOwned,
+        Resp: Serialize + DeserializeOwned,
+    {
+        Err(unsupported())
+    }
+
+    /// Spawn a child process executing `program`. Always fails on this target.
+    pub fn exec<S: AsRef<OsStr>>(
+        &mut self,
+        _program: impl AsRef<OsStr>,
+        _args: impl IntoIterator<Item = S>,
+    ) -> std::result::Result<Child, ForkError> {
+        Err(ForkError::Io(unsupported()))
+    }
+}
+
+/// Registers hooks to run around each fork performed by this crate.
+///
+/// No process is forked on this target, so the hooks never run.
+pub fn register_atfork(
+    _prepare: impl Fn() + Send + Sync + 'static,
+    _parent: impl Fn() + Send + Sync + 'static,
+    _child: impl Fn() + Send + Sync + 'static,
+) -> AtForkHandle {
+    AtForkHandle { _private: () }
+}
+
+/// Handle to hooks registered with [`register_atfork`].
+#[derive(Debug)]
+pub struct AtForkHandle {
+    _private: (),
+}
+
+impl AtForkHandle {
+    /// Unregisters the hooks.
+    pub fn unregister(self) {}
+}
+
+/// Identifier of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pid(i32);
+
+impl Pid {
+    /// Returns the PID of the current process.
+    pub fn current() -> Self {
+        Pid(std::process::id() as i32)
+    }
+
+    /// Returns the PID of the parent of the current process. Always `None` on this target.
+    pub fn parent() -> Option<Self> {
+        None
+    }
+
+    /// Returns the raw PID.
+    pub fn as_raw(self) -> i32 {
+        self.0
+    }
+
+    /// Checks whether a process with this PID exists. Always fails on this target.
+    pub fn is_alive(self) -> Result<bool> {
+        Err(unsupported())
+    }
+}
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<&Child> for Pid {
+    fn from(child: &Child) -> Self {
+        match child.never {}
+    }
+}
+
+impl From<Pid> for u32 {
+    fn from(pid: Pid) -> Self {
+        pid.0 as u32
+    }
+}
+
+impl From<Pid> for i32 {
+    fn from(pid: Pid) -> Self {
+        pid.0
+    }
+}
+
+impl TryFrom<i32> for Pid {
+    type Error = Error;
+
+    /// Fails with [`ErrorKind::InvalidInput`] if `pid` is not positive.
+    fn try_from(pid: i32) -> Result<Self> {
+        if pid <= 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid process ID"));
+        }
+        Ok(Pid(pid))
+    }
+}
+
+impl TryFrom<u32> for Pid {
+    type Error = Error;
+
+    /// Fails with [`ErrorKind::InvalidInput`] if `pid` is zero or too large.
+    fn try_from(pid: u32) -> Result<Self> {
+        i32::try_from(pid)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid process ID"))?
+            .try_into()
+    }
+}
+
+/// Builder for spawning a daemon process.
+#[derive(Debug, Default)]
+pub struct Daemon {
+    _private: (),
+}
+
+impl Daemon {
+    /// Creates a new builder with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the PID of the daemon to the given file before the closure runs.
+    pub fn pidfile(&mut self, _path: impl AsRef<Path>) -> &mut Self {
+        self
+    }
+
+    /// Spawn the provided closure in a daemon process. Always fails on this target.
+    pub fn spawn<T: Termination>(&mut self, _f: impl FnOnce() -> T) -> Result<u32> {
+        Err(unsupported())
+    }
+}
+
+/// Spawn the provided closure in a daemon process. Always fails on this target.
+pub fn daemonize<T: Termination>(_f: impl FnOnce() -> T) -> Result<u32> {
+    Err(unsupported())
+}
+
+/// Reaper of children whose handles are dropped or detached.
+///
+/// Reapers cannot be installed on this target, so no value of this type exists.
+#[derive(Debug)]
+pub struct Reaper {
+    never: Infallible,
+}
+
+impl Reaper {
+    /// Installs the reaper. Always fails on this target.
+    pub fn install() -> Result<Self> {
+        Err(unsupported())
+    }
+
+    /// Reaps the dropped or detached children that have exited.
+    pub fn reap(&self) -> Vec<(u32, ChildStatus)> {
+        match self.never {}
+    }
+
+    /// Waits until at least one dropped or detached child exits, for at most `timeout` if given.
+    pub fn wait(&self, _timeout: Option<Duration>) -> Result<Vec<(u32, ChildStatus)>> {
+        match self.never {}
+    }
+}
+
+/// Reaps the orphaned children that have exited. Always empty on this target.
+pub fn reap_exited() -> Vec<(u32, ChildStatus)> {
+    Vec::new()
+}
+
+/// Marks the current process as a child subreaper, or unmarks it. Always fails on this target.
+pub fn set_child_subreaper(_enabled: bool) -> Result<()> {
+    Err(unsupported())
+}
+
+/// Reaps all children of the current process that have exited. Always fails on this target.
+pub fn reap_children() -> Result<Vec<(u32, ChildStatus)>> {
+    Err(unsupported())
+}
+
+/// Waits for the child to exit, reaping all other children of the current process that exit in
+/// the meantime.
+pub fn join_reaping(child: Child) -> Result<ChildStatus> {
+    match child.never {}
+}
+
+/// Runs the closure in a child process, acting as its init process until it exits. Always fails
+/// on this target.
+pub fn pid1_init<T: Termination>(
+    _f: impl FnOnce() -> T,
+) -> std::result::Result<ChildStatus, ForkError> {
+    Err(ForkError::Io(unsupported()))
+}
+
+/// A listening socket that connections can be accepted from.
+pub trait Accept {
+    /// Type of the accepted connections.
+    type Stream;
+
+    /// Accepts a new incoming connection.
+    fn accept_stream(&self) -> Result<Self::Stream>;
+}
+
+impl Accept for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept_stream(&self) -> Result<TcpStream> {
+        self.accept().map(|(stream, _)| stream)
+    }
+}
+
+/// A prefork server, where a fixed number of supervised children accept connections from a
+/// listening socket that they inherit.
+///
+/// The children cannot be forked on this target, so [`PreforkServer::spawn`] always fails.
+pub struct PreforkServer<L> {
+    _listener: L,
+}
+
+impl<L: Accept + 'static> PreforkServer<L> {
+    /// Creates a server that accepts connections from `listener` in `workers` children.
+    pub fn new(listener: L, _workers: usize) -> Self {
+        Self {
+            _listener: listener,
+        }
+    }
+
+    /// Sets the policy that the children are restarted with.
+    pub fn restart_policy(self, _policy: RestartPolicy) -> Self {
+        self
+    }
+
+    /// Forks the children. Always fails on this target.
+    pub fn spawn(
+        self,
+        _handler: impl FnMut(L::Stream) + 'static,
+    ) -> std::result::Result<Supervisor, ForkError> {
+        Err(ForkError::Io(unsupported()))
+    }
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete, returning its value. Always fails on this target.
+#[cfg(feature = "serde")]
+pub fn fork_join_value<T>(_f: impl FnOnce() -> T) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Like [`fork_join_value`], but serializes the value with the codec `C`. Always fails on this
+/// target.
+#[cfg(feature = "serde")]
+pub fn fork_join_value_codec<C, T>(_f: impl FnOnce() -> T) -> Result<T>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete, returning its result. Always fails on this target.
+#[cfg(feature = "serde")]
+pub fn fork_join_result<T, E>(
+    _f: impl FnOnce() -> std::result::Result<T, E>,
+) -> std::result::Result<std::result::Result<T, E>, ForkError>
+where
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+{
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Like [`fork_join_result`], but serializes the result with the codec `C`. Always fails on this
+/// target.
+#[cfg(feature = "serde")]
+pub fn fork_join_result_codec<C, T, E>(
+    _f: impl FnOnce() -> std::result::Result<T, E>,
+) -> std::result::Result<std::result::Result<T, E>, ForkError>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+{
+    Err(ForkError::Io(unsupported()))
+}
+
+/// Fork the current process once per item, and execute the provided closure on each item within
+/// the child processes in parallel. Always fails on this target.
+#[cfg(feature = "serde")]
+pub fn fork_map<I, T>(_items: impl IntoIterator<Item = I>, _f: impl Fn(I) -> T) -> Result<Vec<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Configuration of a parallel map over child processes, as performed by [`fork_map`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct ForkMap<C = Bincode> {
+    _codec: PhantomData<fn() -> C>,
+}
+
+#[cfg(feature = "serde")]
+impl Default for ForkMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ForkMap {
+    /// Creates a configuration forking one child per item, all at the same time.
+    pub fn new() -> Self {
+        ForkMap {
+            _codec: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<C: Codec> ForkMap<C> {
+    /// Serializes the values with the codec `D` instead.
+    pub fn codec<D: Codec>(self) -> ForkMap<D> {
+        ForkMap {
+            _codec: PhantomData,
+        }
+    }
+
+    /// Limits the number of children running at the same time to `n`.
+    pub fn max_parallel(self, _n: usize) -> Self {
+        self
+    }
+
+    /// Processes `k` items in each child.
+    pub fn chunk_size(self, _k: usize) -> Self {
+        self
+    }
+
+    /// Runs the closure on each item in child processes. Always fails on this target.
+    pub fn run<I, T>(
+        &self,
+        _items: impl IntoIterator<Item = I>,
+        _f: impl Fn(I) -> T,
+    ) -> Result<Vec<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        Err(unsupported())
+    }
+}
+
+/// Like [`fork_map`], but yields the results as the children complete. Always fails on this
+/// target.
+#[cfg(feature = "serde")]
+pub fn fork_map_iter<I, T>(
+    _items: impl IntoIterator<Item = I>,
+    _f: impl Fn(I) -> T,
+) -> Result<ForkMapIter<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Like [`fork_map_iter`], but serializes the values with the codec `C`. Always fails on this
+/// target.
+#[cfg(feature = "serde")]
+pub fn fork_map_iter_codec<C, I, T>(
+    _items: impl IntoIterator<Item = I>,
+    _f: impl Fn(I) -> T,
+) -> Result<ForkMapIter<T, C>>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Iterator over the results of [`fork_map_iter`], in the order the children complete.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct ForkMapIter<T, C = Bincode> {
+    never: Infallible,
+    _marker: PhantomData<fn() -> (T, C)>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned, C: Codec> Iterator for ForkMapIter<T, C> {
+    type Item = (usize, Result<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.never {}
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned, C: Codec> ExactSizeIterator for ForkMapIter<T, C> {}
+
+/// Applies `f` to `state` in a child process as a dry run. Always fails on this target.
+#[cfg(feature = "serde")]
+pub fn snapshot<S, T, E, F>(_state: &mut S, _f: F) -> Result<SnapshotResult<'_, S, T, E, F>>
+where
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+    F: FnMut(&mut S) -> std::result::Result<T, E>,
+{
+    Err(unsupported())
+}
+
+/// Like [`snapshot`], but serializes the result with the codec `C`. Always fails on this target.
+#[cfg(feature = "serde")]
+pub fn snapshot_codec<C, S, T, E, F>(
+    _state: &mut S,
+    _f: F,
+) -> Result<SnapshotResult<'_, S, T, E, F>>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+    F: FnMut(&mut S) -> std::result::Result<T, E>,
+{
+    Err(unsupported())
+}
+
+/// Outcome of a dry run performed by [`snapshot`].
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct SnapshotResult<'a, S, T, E, F> {
+    never: Infallible,
+    _marker: PhantomData<(&'a mut S, T, E, F)>,
+}
+
+#[cfg(feature = "serde")]
+impl<S, T, E, F> SnapshotResult<'_, S, T, E, F>
+where
+    F: FnMut(&mut S) -> std::result::Result<T, E>,
+{
+    /// Returns the result of the dry run.
+    pub fn outcome(&self) -> &std::result::Result<T, E> {
+        match self.never {}
+    }
+
+    /// Returns whether the dry run succeeded.
+    pub fn is_ok(&self) -> bool {
+        match self.never {}
+    }
+
+    /// Applies the mutation to the state of the parent by running it again, returning its result.
+    pub fn commit(self) -> std::result::Result<T, E> {
+        match self.never {}
+    }
+
+    /// Discards the dry run, leaving the state untouched, and returns its result.
+    pub fn discard(self) -> std::result::Result<T, E> {
+        match self.never {}
+    }
+}
+
+/// A pool of pre-forked worker processes.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+pub struct ForkPool<Req, Resp, C = Bincode> {
+    never: Infallible,
+    _marker: PhantomData<fn(Req) -> (Resp, C)>,
+}
+
+#[cfg(feature = "serde")]
+impl<Req, Resp> ForkPool<Req, Resp>
+where
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Spawns a pool with `workers` worker processes running `handler`. Always fails on this
+    /// target.
+    pub fn new(workers: usize, handler: impl Fn(Req) -> Resp + 'static) -> Result<Self> {
+        Self::with_codec(workers, handler)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<Req, Resp, C> ForkPool<Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Spawns a pool with `workers` worker processes running `handler`, serializing the tasks and
+    /// the results with the codec `C`. Always fails on this target.
+    pub fn with_codec(_workers: usize, _handler: impl Fn(Req) -> Resp + 'static) -> Result<Self> {
+        Err(unsupported())
+    }
+
+    /// Returns the number of workers in the pool.
+    pub fn workers(&self) -> usize {
+        match self.never {}
+    }
+
+    /// Submits a task to the pool, returning a handle to wait for its result.
+    pub fn submit(&self, _req: Req) -> Result<TaskHandle<'_, Req, Resp, C>> {
+        match self.never {}
+    }
+
+    /// Submits a task to the pool that may run for at most `timeout`.
+    pub fn submit_timeout(
+        &self,
+        _req: Req,
+        _timeout: Duration,
+    ) -> Result<TaskHandle<'_, Req, Resp, C>> {
+        match self.never {}
+    }
+
+    /// Runs a task on the pool and waits for its result.
+    pub fn call(&self, _req: Req) -> Result<Resp> {
+        match self.never {}
+    }
+
+    /// Runs all tasks on the pool, returning the results in order.
+    pub fn map(&self, _reqs: impl IntoIterator<Item = Req>) -> Result<Vec<Resp>> {
+        match self.never {}
+    }
+}
+
+/// Handle to a task submitted to a [`ForkPool`].
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+pub struct TaskHandle<'a, Req, Resp, C = Bincode> {
+    never: Infallible,
+    _marker: PhantomData<&'a ForkPool<Req, Resp, C>>,
+}
+
+#[cfg(feature = "serde")]
+impl<Req, Resp, C> TaskHandle<'_, Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Waits for the task to complete, returning its result.
+    pub fn join(self) -> Result<Resp> {
+        match self.never {}
+    }
+
+    /// Cancels the task.
+    pub fn cancel(self) -> Result<()> {
+        match self.never {}
+    }
+}
+
+/// Sending half of a channel between a parent and a child.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct Sender<T, C = Bincode> {
+    never: Infallible,
+    _marker: PhantomData<fn(T) -> C>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize, C: Codec> Sender<T, C> {
+    /// Sends a value to the other end.
+    pub fn send(&self, _value: &T) -> Result<()> {
+        match self.never {}
+    }
+}
+
+/// Receiving half of a channel between a parent and a child.
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct Receiver<T, C = Bincode> {
+    never: Infallible,
+    _marker: PhantomData<fn() -> (T, C)>,
+}
+
+#[cfg(feature = "serde")]
+impl<T: DeserializeOwned, C: Codec> Receiver<T, C> {
+    /// Receives a value from the other end.
+    pub fn recv(&self) -> Result<T> {
+        match self.never {}
+    }
+}
+
+/// Result of [`fork_with_channel`].
+#[cfg(feature = "serde")]
+#[must_use]
+#[derive(Debug)]
+pub enum ForkChannel<T, C = Bincode> {
+    /// Returned in the parent process, with the handle of the child and the parent's endpoint.
+    Parent(Child, Sender<T, C>, Receiver<T, C>),
+    /// Returned in the child process, with the child's endpoint.
+    Child(Sender<T, C>, Receiver<T, C>),
+}
+
+/// Fork the current process, with a typed channel connecting the parent and the child. Always
+/// fails on this target.
+#[cfg(feature = "serde")]
+pub fn fork_with_channel<T>() -> Result<ForkChannel<T>> {
+    Err(unsupported())
+}
+
+/// Like [`fork_with_channel`], but serializes values with the codec `C`. Always fails on this
+/// target.
+#[cfg(feature = "serde")]
+pub fn fork_with_channel_codec<C: Codec, T>() -> Result<ForkChannel<T, C>> {
+    Err(unsupported())
+}
+
+/// Fork a child process that serves requests with `handler`. Always fails on this target.
+#[cfg(feature = "serde")]
+pub fn fork_service<Req, Resp>(
+    _handler: impl FnMut(Req) -> Resp,
+) -> Result<ServiceHandle<Req, Resp>>
+where
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Like [`fork_service`], but serializes requests and responses with the codec `C`. Always fails
+/// on this target.
+#[cfg(feature = "serde")]
+pub fn fork_service_codec<C, Req, Resp>(
+    _handler: impl FnMut(Req) -> Resp,
+) -> Result<ServiceHandle<Req, Resp, C>>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    Err(unsupported())
+}
+
+/// Handle to a child process spawned with [`fork_service`].
+///
+/// Processes cannot be spawned on this target, so no value of this type exists.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct ServiceHandle<Req, Resp, C = Bincode> {
+    never: Infallible,
+    _marker: PhantomData<fn(Req) -> (Resp, C)>,
+}
+
+#[cfg(feature = "serde")]
+impl<Req, Resp, C> ServiceHandle<Req, Resp, C>
+where
+    C: Codec,
+    Req: Serialize + DeserializeOwned,
+    Resp: Serialize + DeserializeOwned,
+{
+    /// Returns the PID of the child process.
+    pub fn pid(&self) -> u32 {
+        match self.never {}
+    }
+
+    /// Sends a request to the child, and waits for the response.
+    pub fn call(&mut self, _req: Req) -> Result<Resp> {
+        match self.never {}
+    }
+
+    /// Sends a request to the child, and waits for the response for at most `timeout`.
+    pub fn call_timeout(&mut self, _req: Req, _timeout: Duration) -> Result<Resp> {
+        match self.never {}
+    }
+
+    /// Closes the channel, and waits for the child to exit.
+    pub fn shutdown(self) -> Result<ChildStatus> {
+        match self.never {}
+    }
+}
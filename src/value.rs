@@ -0,0 +1,491 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::marker::PhantomData;
+use std::os::fd::AsRawFd;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Bincode, Child, Codec, ForkBuilder, ForkError};
+
+/// Fork the current process, and execute the provided closure within child process, and wait for it to complete.
+///
+/// The value returned by the closure is serialized and sent back to the parent through a pipe. If
+/// the child fails before sending it back, the error wraps [`ForkError::ChildFailed`], or wraps
+/// [`ChildPanicked`](crate::ChildPanicked) if the closure panics.
+pub fn fork_join_value<T>(f: impl FnOnce() -> T) -> Result<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fork_join_value_codec::<Bincode, T>(f)
+}
+
+/// Fork the current process, and execute the provided closure within child process, and wait for
+/// it to complete, serializing the value with the codec `C`.
+///
+/// See [`fork_join_value`].
+pub fn fork_join_value_codec<C, T>(f: impl FnOnce() -> T) -> Result<T>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+{
+    join_value::<C, T>(f).map_err(Error::from)
+}
+
+/// Fork a child sending back the value returned by `f`, and wait for the value.
+fn join_value<C, T>(f: impl FnOnce() -> T) -> std::result::Result<T, ForkError>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+{
+    let (child, reader) = spawn_value::<C, T>(f)?;
+    let mut pipes = [Some(reader)];
+    let mut bufs = [Vec::new()];
+    let read = crate::read_all(&mut pipes, &mut bufs);
+    let [buf] = bufs;
+    finish::<C, T>(child, read.map(|()| buf))
+}
+
+/// Fork the current process, and execute the provided fallible closure within child process, and
+/// wait for it to complete.
+///
+/// Both the value and the error returned by the closure are serialized and sent back to the
+/// parent, and are returned in the inner [`Result`]. The outer one only fails for problems with
+/// the child process itself, so application errors are never conflated with process errors:
+///
+/// - [`ForkError::Panicked`] if the closure panics,
+/// - [`ForkError::ChildFailed`] if the child exits unsuccessfully or is terminated by a signal,
+///   e.g. because the result cannot be serialized,
+/// - [`ForkError::Io`] with [`ErrorKind::InvalidData`] if the result cannot be deserialized,
+/// - other variants if forking or waiting for the child fails.
+///
+/// # Example
+///
+/// ```
+/// let result = safe_fork::fork_join_result(|| "42".parse::<u32>().map_err(|e| e.to_string()));
+/// assert_eq!(result.unwrap(), Ok(42));
+/// let result = safe_fork::fork_join_result(|| "x".parse::<u32>().map_err(|e| e.to_string()));
+/// assert_eq!(result.unwrap(), Err(String::from("invalid digit found in string")));
+/// ```
+pub fn fork_join_result<T, E>(
+    f: impl FnOnce() -> std::result::Result<T, E>,
+) -> std::result::Result<std::result::Result<T, E>, ForkError>
+where
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+{
+    join_value::<Bincode, _>(f)
+}
+
+/// Fork the current process, and execute the provided fallible closure within child process, and
+/// wait for it to complete, serializing the value and the error with the codec `C`.
+///
+/// See [`fork_join_result`].
+pub fn fork_join_result_codec<C, T, E>(
+    f: impl FnOnce() -> std::result::Result<T, E>,
+) -> std::result::Result<std::result::Result<T, E>, ForkError>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+{
+    join_value::<C, _>(f)
+}
+
+/// Fork the current process once per item, and execute the provided closure on each item within
+/// the child processes in parallel, and wait for all of them to complete.
+///
+/// The values returned by the closure are serialized and sent back to the parent through pipes,
+/// and are returned in the same order as the items. See [`ForkMap`] for limiting the number of
+/// children, or processing several items in each child.
+pub fn fork_map<I, T>(items: impl IntoIterator<Item = I>, f: impl Fn(I) -> T) -> Result<Vec<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    ForkMap::new().run(items, f)
+}
+
+/// Configuration of a parallel map over child processes, as performed by [`fork_map`].
+///
+/// By default, one child is forked per item, and all children run at the same time. For many
+/// small items, [`chunk_size`](Self::chunk_size) amortizes the cost of forking over several items,
+/// and [`max_parallel`](Self::max_parallel) bounds the number of children alive at once.
+/// Values are serialized with the codec `C`, see [`codec`](Self::codec).
+///
+/// # Example
+///
+/// ```
+/// use safe_fork::ForkMap;
+///
+/// let squares = ForkMap::new()
+///     .max_parallel(4)
+///     .chunk_size(1000)
+///     .run(0..100_000u64, |x| x * x)
+///     .unwrap();
+/// assert_eq!(squares[1000], 1_000_000);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ForkMap<C = Bincode> {
+    max_parallel: usize,
+    chunk_size: usize,
+    _codec: PhantomData<fn() -> C>,
+}
+
+impl Default for ForkMap {
+    fn default() -> Self {
+        Self {
+            max_parallel: usize::MAX,
+            chunk_size: 1,
+            _codec: PhantomData,
+        }
+    }
+}
+
+impl ForkMap {
+    /// Creates a configuration that forks one child per item, all at the same time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: Codec> ForkMap<C> {
+    /// Sets the codec used to serialize the values returned by the closure, [`Bincode`] by
+    /// default.
+    pub fn codec<D: Codec>(self) -> ForkMap<D> {
+        ForkMap {
+            max_parallel: self.max_parallel,
+            chunk_size: self.chunk_size,
+            _codec: PhantomData,
+        }
+    }
+
+    /// Sets the maximum number of children that run at the same time.
+    ///
+    /// Further children are only forked once running ones complete.
+    pub fn max_parallel(mut self, n: usize) -> Self {
+        self.max_parallel = n;
+        self
+    }
+
+    /// Sets the number of items processed by each child.
+    ///
+    /// The last child may receive fewer items.
+    pub fn chunk_size(mut self, k: usize) -> Self {
+        self.chunk_size = k;
+        self
+    }
+
+    /// Runs the closure on each item in child processes, returning the values in the same order as
+    /// the items.
+    ///
+    /// Fails if any child fails; children that are still running are then reaped as with a dropped
+    /// [`Child`]. Fails with [`ErrorKind::InvalidInput`] if either the maximum parallelism or the
+    /// chunk size is zero.
+    pub fn run<I, T>(
+        &self,
+        items: impl IntoIterator<Item = I>,
+        f: impl Fn(I) -> T,
+    ) -> Result<Vec<T>>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if self.max_parallel == 0 || self.chunk_size == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "parallelism and chunk size must be nonzero",
+            ));
+        }
+
+        let mut items = items.into_iter();
+        let mut pending = Pending::default();
+        let mut results: Vec<Option<Vec<T>>> = Vec::new();
+        loop {
+            while pending.running() < self.max_parallel {
+                let chunk: Vec<I> = items.by_ref().take(self.chunk_size).collect();
+                if chunk.is_empty() {
+                    break;
+                }
+                let index =
+                    pending.spawn::<C, _>(|| chunk.into_iter().map(&f).collect::<Vec<T>>())?;
+                debug_assert_eq!(index, results.len());
+                results.push(None);
+            }
+            let Some((index, result)) = pending.next::<C, _>() else {
+                break;
+            };
+            results[index] = Some(result?);
+        }
+        Ok(results.into_iter().flatten().flatten().collect())
+    }
+}
+
+/// Fork the current process once per item, and execute the provided closure on each item within
+/// the child processes in parallel, returning an iterator over the results as the children
+/// complete.
+///
+/// Unlike [`fork_map`], the results are yielded in the order the children finish, each with the
+/// index of its item, so that processing can start as soon as the first child completes. A child
+/// that fails only fails its own result.
+///
+/// # Example
+///
+/// ```
+/// let mut results: Vec<_> = safe_fork::fork_map_iter(0..4u32, |x| x * x)
+///     .unwrap()
+///     .map(|(index, result)| (index, result.unwrap()))
+///     .collect();
+/// results.sort();
+/// assert_eq!(results, [(0, 0), (1, 1), (2, 4), (3, 9)]);
+/// ```
+pub fn fork_map_iter<I, T>(
+    items: impl IntoIterator<Item = I>,
+    f: impl Fn(I) -> T,
+) -> Result<ForkMapIter<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fork_map_iter_codec(items, f)
+}
+
+/// Fork the current process once per item, and execute the provided closure on each item within
+/// the child processes in parallel, serializing the values with the codec `C`.
+///
+/// See [`fork_map_iter`].
+pub fn fork_map_iter_codec<C, I, T>(
+    items: impl IntoIterator<Item = I>,
+    f: impl Fn(I) -> T,
+) -> Result<ForkMapIter<T, C>>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+{
+    let mut pending = Pending::default();
+    for item in items {
+        pending.spawn::<C, _>(|| f(item))?;
+    }
+    Ok(ForkMapIter {
+        pending,
+        _marker: PhantomData,
+    })
+}
+
+/// Iterator over the results of [`fork_map_iter`], in the order the children complete.
+///
+/// Each item is the index of the input item, and the value returned by the closure for it.
+/// If the iterator is dropped early, the remaining children are reaped as with a dropped [`Child`].
+#[derive(Debug)]
+pub struct ForkMapIter<T, C = Bincode> {
+    pending: Pending,
+    _marker: PhantomData<fn() -> (T, C)>,
+}
+
+impl<T: DeserializeOwned, C: Codec> Iterator for ForkMapIter<T, C> {
+    type Item = (usize, Result<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.pending.next::<C, _>()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.pending.running();
+        (len, Some(len))
+    }
+}
+
+impl<T: DeserializeOwned, C: Codec> ExactSizeIterator for ForkMapIter<T, C> {}
+
+/// Children sending back values, indexed in the order they are spawned.
+#[derive(Debug, Default)]
+struct Pending {
+    children: Vec<Option<Child>>,
+    /// Pipes of the children that have not sent their complete value yet.
+    pipes: Vec<Option<File>>,
+    bufs: Vec<Vec<u8>>,
+    /// Errors reading from the pipes, by index.
+    errors: Vec<(usize, Error)>,
+}
+
+impl Pending {
+    /// Fork a child sending back the value returned by `f`, returning its index.
+    fn spawn<C: Codec, T: Serialize>(&mut self, f: impl FnOnce() -> T) -> Result<usize> {
+        let (child, reader) = spawn_value::<C, T>(f)?;
+        self.children.push(Some(child));
+        self.pipes.push(Some(reader));
+        self.bufs.push(Vec::new());
+        Ok(self.children.len() - 1)
+    }
+
+    /// Number of children whose values have not been returned yet.
+    fn running(&self) -> usize {
+        self.children.iter().filter(|child| child.is_some()).count()
+    }
+
+    /// Wait for the next child to complete, and return its index and value.
+    fn next<C: Codec, T: DeserializeOwned>(&mut self) -> Option<(usize, Result<T>)> {
+        let mut chunk = [0; 8192];
+        loop {
+            if let Some((index, err)) = self.errors.pop() {
+                let child = self.children[index].take().unwrap();
+                return Some((index, finish::<C, T>(child, Err(err)).map_err(Error::from)));
+            }
+            let done = (0..self.children.len())
+                .find(|&index| self.pipes[index].is_none() && self.children[index].is_some());
+            if let Some(index) = done {
+                let child = self.children[index].take().unwrap();
+                let buf = std::mem::take(&mut self.bufs[index]);
+                return Some((index, finish::<C, T>(child, Ok(buf)).map_err(Error::from)));
+            }
+            if self.pipes.iter().all(Option::is_none) {
+                return None;
+            }
+
+            let mut pollfds: Vec<_> = self
+                .pipes
+                .iter()
+                .map(|pipe| libc::pollfd {
+                    fd: pipe.as_ref().map_or(-1, |pipe| pipe.as_raw_fd()),
+                    events: libc::POLLIN,
+                    revents: 0,
+                })
+                .collect();
+            if let Err(err) = crate::poll(&mut pollfds, None) {
+                // Attribute the failure to one of the pending children, so that iteration ends.
+                let index = self.pipes.iter().position(Option::is_some).unwrap();
+                self.pipes[index] = None;
+                self.errors.push((index, err));
+                continue;
+            }
+
+            for (index, pollfd) in pollfds.iter().enumerate() {
+                if pollfd.revents == 0 {
+                    continue;
+                }
+                // The poll result guarantees that this does not block.
+                match self.pipes[index].as_mut().unwrap().read(&mut chunk) {
+                    Ok(0) => self.pipes[index] = None,
+                    Ok(len) => self.bufs[index].extend_from_slice(&chunk[..len]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => (),
+                    Err(err) => {
+                        self.pipes[index] = None;
+                        self.errors.push((index, err));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Runs a fallible mutation of `state` in a child process as a dry run.
+///
+/// The child operates on a copy-on-write snapshot of the memory of the parent, so `state` and
+/// everything else in the parent are left untouched whatever `f` does, including panicking or
+/// corrupting memory. The result of `f` is serialized and sent back to the parent, which may then
+/// apply the mutation for real with [`SnapshotResult::commit`], or discard it.
+///
+/// Fails if forking fails, or if the child exits without sending back a result.
+///
+/// # Example
+///
+/// ```
+/// let mut config = vec![1, 2, 3];
+/// let result = safe_fork::snapshot(&mut config, |config| {
+///     config.push(4);
+///     if config.len() > 3 {
+///         return Err(String::from("too long"));
+///     }
+///     Ok(config.len())
+/// })
+/// .unwrap();
+/// assert_eq!(result.outcome(), &Err(String::from("too long")));
+/// assert_eq!(config, [1, 2, 3]);
+/// ```
+pub fn snapshot<S, T, E, F>(state: &mut S, f: F) -> Result<SnapshotResult<'_, S, T, E, F>>
+where
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+    F: FnMut(&mut S) -> std::result::Result<T, E>,
+{
+    snapshot_codec::<Bincode, S, T, E, F>(state, f)
+}
+
+/// Runs a fallible mutation of `state` in a child process as a dry run, serializing its result
+/// with the codec `C`.
+///
+/// See [`snapshot`].
+pub fn snapshot_codec<C, S, T, E, F>(
+    state: &mut S,
+    mut f: F,
+) -> Result<SnapshotResult<'_, S, T, E, F>>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+    E: Serialize + DeserializeOwned,
+    F: FnMut(&mut S) -> std::result::Result<T, E>,
+{
+    let outcome = fork_join_value_codec::<C, _>(|| f(state))?;
+    Ok(SnapshotResult { state, f, outcome })
+}
+
+/// Outcome of a dry run performed by [`snapshot`].
+#[derive(Debug)]
+pub struct SnapshotResult<'a, S, T, E, F> {
+    state: &'a mut S,
+    f: F,
+    outcome: std::result::Result<T, E>,
+}
+
+impl<S, T, E, F> SnapshotResult<'_, S, T, E, F>
+where
+    F: FnMut(&mut S) -> std::result::Result<T, E>,
+{
+    /// Returns the result of the dry run.
+    pub fn outcome(&self) -> &std::result::Result<T, E> {
+        &self.outcome
+    }
+
+    /// Returns whether the dry run succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.outcome.is_ok()
+    }
+
+    /// Applies the mutation to the state of the parent by running it again, returning its result.
+    ///
+    /// The mutation should be deterministic, so that it succeeds as the dry run did.
+    pub fn commit(mut self) -> std::result::Result<T, E> {
+        (self.f)(self.state)
+    }
+
+    /// Discards the dry run, leaving the state untouched, and returns its result.
+    pub fn discard(self) -> std::result::Result<T, E> {
+        self.outcome
+    }
+}
+
+/// Fork a child that sends the serialized return value of `f` into the returned pipe.
+fn spawn_value<C: Codec, T: Serialize>(
+    f: impl FnOnce() -> T,
+) -> std::result::Result<(Child, File), ForkError> {
+    let (reader, mut writer) = crate::pipe()?;
+    let child = ForkBuilder::new().capture_panics().spawn(move || {
+        match C::encode(&f()).and_then(|bytes| writer.write_all(&bytes)) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        }
+    })?;
+    // The write end has been dropped along with the closure, so reading proceeds until the child
+    // exits.
+    Ok((child, reader))
+}
+
+/// Join the child, and deserialize the value it has sent.
+fn finish<C: Codec, T: DeserializeOwned>(
+    child: Child,
+    buf: Result<Vec<u8>>,
+) -> std::result::Result<T, ForkError> {
+    let exit = child.join().map_err(ForkError::from_wait)?;
+    let buf = buf?;
+    if !exit.success() {
+        return Err(ForkError::ChildFailed(exit));
+    }
+    Ok(C::decode(&buf)?)
+}
@@ -0,0 +1,37 @@
+use std::sync::Mutex;
+
+static EVENTS: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+fn record(event: &'static str) -> impl Fn() + Send + Sync + 'static {
+    move || EVENTS.lock().unwrap().push(event)
+}
+
+fn take() -> Vec<&'static str> {
+    std::mem::take(&mut EVENTS.lock().unwrap())
+}
+
+fn main() {
+    let first =
+        safe_fork::register_atfork(record("prepare 1"), record("parent 1"), record("child 1"));
+    let _second =
+        safe_fork::register_atfork(record("prepare 2"), record("parent 2"), record("child 2"));
+
+    let child = safe_fork::fork_spawn(|| {
+        (take() == ["prepare 2", "prepare 1", "child 1", "child 2"]) as i32
+    })
+    .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert_eq!(take(), ["prepare 2", "prepare 1", "parent 1", "parent 2"]);
+
+    // Hooks also run for forks with extra setup.
+    let child = safe_fork::ForkBuilder::new()
+        .spawn(|| take().contains(&"child 1") as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    take();
+
+    first.unregister();
+    let child = safe_fork::fork_spawn(|| (take() == ["prepare 2", "child 2"]) as i32).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert_eq!(take(), ["prepare 2", "parent 2"]);
+}
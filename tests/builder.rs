@@ -0,0 +1,520 @@
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+
+use safe_fork::{ForkBuilder, ForkError, Resource, Rlimits, Scrubbable, SigSet, Stdio};
+
+fn main() {
+    let child = ForkBuilder::new()
+        .chdir("/")
+        .spawn(|| (std::env::current_dir().unwrap() == std::path::Path::new("/")) as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .umask(0o027)
+        // SAFETY: `umask` does not have special safety requirements.
+        .spawn(|| unsafe { libc::umask(0) } as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(0o027));
+
+    let child = ForkBuilder::new()
+        .stdin(Stdio::Null)
+        .spawn(|| {
+            let mut buf = Vec::new();
+            std::io::stdin().read_to_end(&mut buf).unwrap() as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(0));
+
+    let path = std::env::temp_dir().join(format!("safe-fork-stdio-{}", std::process::id()));
+    let file = std::fs::File::create(&path).unwrap();
+    let child = ForkBuilder::new()
+        .stdout(file)
+        .stderr(Stdio::Null)
+        .spawn(|| {
+            println!("to file");
+            eprintln!("to nowhere");
+        })
+        .unwrap();
+    assert!(child.join().unwrap().success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "to file\n");
+    std::fs::remove_file(&path).unwrap();
+
+    // The output of one child can be piped into another.
+    let mut producer = ForkBuilder::new()
+        .stdout(Stdio::Piped)
+        .spawn(|| println!("through a pipe"))
+        .unwrap();
+    let mut consumer = ForkBuilder::new()
+        .stdin(producer.stdout.take().unwrap())
+        .stdout(Stdio::Piped)
+        .spawn(|| {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).unwrap();
+            print!("{}", input.to_uppercase());
+        })
+        .unwrap();
+    let mut output = String::new();
+    consumer
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "THROUGH A PIPE\n");
+    assert!(producer.join().unwrap().success());
+    assert!(consumer.join().unwrap().success());
+
+    let child = ForkBuilder::new()
+        .env("SAFE_FORK_TEST", "value")
+        .spawn(|| (std::env::var("SAFE_FORK_TEST").as_deref() == Ok("value")) as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Variables are removed in the child only.
+    std::env::set_var("SAFE_FORK_SECRET", "secret");
+    let child = ForkBuilder::new()
+        .env_remove("SAFE_FORK_SECRET")
+        .spawn(|| std::env::var_os("SAFE_FORK_SECRET").is_none() as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    let child = ForkBuilder::new()
+        .env("SAFE_FORK_TEST", "dropped")
+        .env_clear()
+        .env("SAFE_FORK_TEST", "value")
+        .spawn(|| {
+            let vars: Vec<_> = std::env::vars_os().collect();
+            (vars == [("SAFE_FORK_TEST".into(), "value".into())]) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert_eq!(std::env::var("SAFE_FORK_SECRET").as_deref(), Ok("secret"));
+
+    // Secrets are wiped in the child before the closure runs, but kept in the parent.
+    let secrets = std::sync::Arc::new(std::sync::Mutex::new((
+        String::from("password"),
+        Box::new([0xffu64; 4]),
+        Some(vec![1u8, 2, 3]),
+    )));
+    let child = ForkBuilder::new()
+        .scrub({
+            let secrets = secrets.clone();
+            move || {
+                let (password, key, buf) = &mut *secrets.lock().unwrap();
+                password.scrub();
+                key.scrub();
+                buf.scrub();
+            }
+        })
+        .spawn(|| {
+            let (password, key, buf) = &*secrets.lock().unwrap();
+            (password.bytes().all(|b| b == 0)
+                && password.len() == 8
+                && *key == Box::new([0; 4])
+                && buf.as_deref() == Some(&[0; 3][..])) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert_eq!(secrets.lock().unwrap().0, "password");
+
+    let err = ForkBuilder::new()
+        .chdir("/nonexistent")
+        .spawn(|| 0)
+        .unwrap_err();
+    assert!(matches!(err, ForkError::SetupFailed(err) if err.kind() == ErrorKind::NotFound));
+
+    // The intermediate child exits right away, so the grandchild receives the signal.
+    #[cfg(target_os = "linux")]
+    {
+        let (mut reader, writer) = std::io::pipe().unwrap();
+        let child = safe_fork::fork_spawn(move || {
+            let grandchild = ForkBuilder::new()
+                .pdeathsig(libc::SIGKILL)
+                .spawn(|| {
+                    std::thread::sleep(std::time::Duration::from_secs(10));
+                    0
+                })
+                .unwrap();
+            grandchild.detach();
+            drop(writer);
+            0
+        })
+        .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(0));
+        // The grandchild holds a copy of the write end, so EOF means it has been killed.
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).unwrap();
+    }
+
+    let child = ForkBuilder::new()
+        .rlimits(
+            Rlimits::new()
+                .nofile(16)
+                .set(Resource::Core, 0, Rlimits::INFINITY),
+        )
+        .spawn(|| {
+            let mut limit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            // SAFETY: `limit` is valid for the duration of the call.
+            unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+            let nofile_ok = limit.rlim_cur == 16 && limit.rlim_max == 16;
+            // SAFETY: `limit` is valid for the duration of the call.
+            unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) };
+            let core_ok = limit.rlim_cur == 0 && limit.rlim_max == libc::RLIM_INFINITY;
+            (nofile_ok && core_ok) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Core dumps are enabled and written to the given directory.
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+    let child = ForkBuilder::new()
+        .core_dumps(&dir)
+        .spawn(|| {
+            let mut limit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            // SAFETY: `limit` is valid for the duration of the call.
+            unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) };
+            let core_ok = limit.rlim_cur == limit.rlim_max;
+            // SAFETY: `PR_GET_DUMPABLE` does not have special safety requirements.
+            #[cfg(target_os = "linux")]
+            let dumpable = unsafe { libc::prctl(libc::PR_GET_DUMPABLE) } == 1;
+            #[cfg(not(target_os = "linux"))]
+            let dumpable = true;
+            let cwd_ok = std::env::current_dir().unwrap() == dir;
+            (core_ok && dumpable && cwd_ok) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Exceeding the CPU limit kills the child with `SIGXCPU`.
+    let child = ForkBuilder::new()
+        .rlimits(Rlimits::new().set(Resource::Cpu, 1, 2))
+        .spawn(|| -> i32 {
+            loop {
+                std::hint::spin_loop();
+            }
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGXCPU));
+
+    // Exceeding the limit is told apart from crashing, even if `SIGXCPU` is ignored.
+    for ignore in [false, true] {
+        let child = ForkBuilder::new()
+            .cpu_limit(std::time::Duration::from_millis(500))
+            .spawn(move || -> i32 {
+                if ignore {
+                    // SAFETY: ignoring a signal is always sound.
+                    unsafe { libc::signal(libc::SIGXCPU, libc::SIG_IGN) };
+                }
+                loop {
+                    std::hint::spin_loop();
+                }
+            })
+            .unwrap();
+        assert!(matches!(
+            child.join_checked(),
+            Err(ForkError::CpuLimitExceeded)
+        ));
+    }
+    let child = ForkBuilder::new()
+        .cpu_limit(std::time::Duration::from_secs(10))
+        .spawn(|| -> i32 { std::process::abort() })
+        .unwrap();
+    assert_eq!(child.join_checked().unwrap().signal(), Some(libc::SIGABRT));
+
+    // Failing to allocate within the memory limit is told apart from crashing. Printing a backtrace
+    // would hide the cause of the failure.
+    let child = ForkBuilder::new()
+        .memory_limit(512 << 20)
+        .env_remove("RUST_BACKTRACE")
+        .spawn(|| std::hint::black_box(Vec::<u8>::with_capacity(1 << 30)).capacity() as i32)
+        .unwrap();
+    assert!(matches!(
+        child.join_checked(),
+        Err(ForkError::MemoryLimitExceeded)
+    ));
+    // This also works if other file descriptors are closed.
+    let child = ForkBuilder::new()
+        .memory_limit(512 << 20)
+        .close_fds()
+        .env_remove("RUST_BACKTRACE")
+        .spawn(|| std::hint::black_box(Vec::<u8>::with_capacity(1 << 30)).capacity() as i32)
+        .unwrap();
+    assert!(matches!(
+        child.join_checked(),
+        Err(ForkError::MemoryLimitExceeded)
+    ));
+    let child = ForkBuilder::new()
+        .memory_limit(512 << 20)
+        .spawn(|| std::hint::black_box(Vec::<u8>::with_capacity(1 << 20)).capacity() as i32 & 1)
+        .unwrap();
+    assert_eq!(child.join_checked().unwrap().code(), Some(0));
+    let child = ForkBuilder::new()
+        .memory_limit(512 << 20)
+        .spawn(|| -> i32 { std::process::abort() })
+        .unwrap();
+    assert_eq!(child.join_checked().unwrap().signal(), Some(libc::SIGABRT));
+
+    let child = ForkBuilder::new()
+        .nice(5)
+        // SAFETY: `getpriority` does not have special safety requirements.
+        .spawn(|| unsafe { libc::getpriority(libc::PRIO_PROCESS as _, 0) })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(5));
+
+    #[cfg(target_os = "linux")]
+    {
+        let child = ForkBuilder::new()
+            .sched_policy(safe_fork::SchedPolicy::Idle)
+            // SAFETY: `sched_getscheduler` does not have special safety requirements.
+            .spawn(|| unsafe { libc::sched_getscheduler(0) })
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(libc::SCHED_IDLE));
+
+        let child = ForkBuilder::new()
+            .cpu_affinity(&[0])
+            .spawn(|| {
+                // SAFETY: all-zero is a valid `cpu_set_t`, and `set` is valid for the duration of
+                // the call.
+                unsafe {
+                    let mut set: libc::cpu_set_t = std::mem::zeroed();
+                    libc::sched_getaffinity(0, std::mem::size_of_val(&set), &mut set);
+                    libc::CPU_COUNT(&set) * 10 + libc::CPU_ISSET(0, &set) as i32
+                }
+            })
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(11));
+
+        let get_io_priority = || {
+            // SAFETY: `ioprio_get` with `IOPRIO_WHO_PROCESS` does not have special safety
+            // requirements.
+            unsafe { libc::syscall(libc::SYS_ioprio_get, 1, 0) as i32 }
+        };
+        let child = ForkBuilder::new()
+            .io_priority(safe_fork::IoPriorityClass::BestEffort, 7)
+            .spawn(move || (get_io_priority() == 2 << 13 | 7) as i32)
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+
+        let err = ForkBuilder::new()
+            .io_priority(safe_fork::IoPriorityClass::Idle, 8)
+            .spawn(|| 0)
+            .unwrap_err();
+        assert!(
+            matches!(err, ForkError::SetupFailed(err) if err.kind() == ErrorKind::InvalidInput)
+        );
+
+        let child = ForkBuilder::new()
+            .name("worker-3")
+            .spawn(|| (std::fs::read_to_string("/proc/self/comm").unwrap() == "worker-3\n") as i32)
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+
+        let child = ForkBuilder::new()
+            .name("a-rather-long-worker-name")
+            .spawn(|| {
+                (std::fs::read_to_string("/proc/self/comm").unwrap() == "a-rather-long-w\n") as i32
+            })
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+
+        let err = ForkBuilder::new().name("nul\0").spawn(|| 0).unwrap_err();
+        assert!(
+            matches!(err, ForkError::SetupFailed(err) if err.kind() == ErrorKind::InvalidInput)
+        );
+
+        let child = ForkBuilder::new()
+            .oom_score_adj(500)
+            .spawn(|| {
+                let score = std::fs::read_to_string("/proc/self/oom_score_adj").unwrap();
+                (score == "500\n") as i32
+            })
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+
+        let err = ForkBuilder::new()
+            .oom_score_adj(2000)
+            .spawn(|| 0)
+            .unwrap_err();
+        assert!(
+            matches!(err, ForkError::SetupFailed(err) if err.kind() == ErrorKind::InvalidInput)
+        );
+
+        let err = ForkBuilder::new()
+            .cpu_affinity(&[usize::MAX])
+            .spawn(|| 0)
+            .unwrap_err();
+        assert!(
+            matches!(err, ForkError::SetupFailed(err) if err.kind() == ErrorKind::InvalidInput)
+        );
+    }
+
+    // SAFETY: `getuid` does not have special safety requirements.
+    if unsafe { libc::getuid() } == 0 {
+        let child = ForkBuilder::new()
+            .groups(&[1234])
+            .gid(2345)
+            .uid(3456)
+            .spawn(|| {
+                let mut groups = [0; 4];
+                // SAFETY: all functions called have no special safety requirements, and `groups`
+                // is valid for the duration of the call.
+                unsafe {
+                    let count = libc::getgroups(4, groups.as_mut_ptr());
+                    (libc::getuid() == 3456
+                        && libc::getgid() == 2345
+                        && groups[..count as usize] == [1234]) as i32
+                }
+            })
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+    } else {
+        let err = ForkBuilder::new().uid(0).spawn(|| 0).unwrap_err();
+        assert!(
+            matches!(err, ForkError::SetupFailed(err) if err.kind() == ErrorKind::PermissionDenied)
+        );
+    }
+
+    let sigpipe_default = || {
+        // SAFETY: all-zero is a valid `sigaction`, and `action` is valid for the duration of the
+        // call.
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            libc::sigaction(libc::SIGPIPE, std::ptr::null(), &mut action);
+            (action.sa_sigaction == libc::SIG_DFL) as i32
+        }
+    };
+    let child = ForkBuilder::new().spawn(sigpipe_default).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(0));
+    let child = ForkBuilder::new()
+        .reset_signal_handlers()
+        .spawn(sigpipe_default)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .signal_mask(SigSet::empty().with(libc::SIGUSR1))
+        .spawn(|| {
+            let mask = SigSet::current().unwrap();
+            (mask.contains(libc::SIGUSR1) && !mask.contains(libc::SIGUSR2)) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert!(!SigSet::current().unwrap().contains(libc::SIGUSR1));
+
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: `epoll_create1` and `timerfd_create` do not have special safety requirements.
+        let (epoll, timer) = unsafe {
+            (
+                libc::epoll_create1(libc::EPOLL_CLOEXEC),
+                libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_CLOEXEC),
+            )
+        };
+        assert!(epoll >= 0 && timer >= 0);
+        let child = ForkBuilder::new()
+            .sanitize_child()
+            .dumpable(false)
+            .spawn(move || {
+                // SAFETY: `fcntl` with `F_GETFD` and `prctl` with `PR_GET_DUMPABLE` do not have
+                // special safety requirements.
+                let (epoll_open, timer_open, dumpable) = unsafe {
+                    (
+                        libc::fcntl(epoll, libc::F_GETFD) >= 0,
+                        libc::fcntl(timer, libc::F_GETFD) >= 0,
+                        libc::prctl(libc::PR_GET_DUMPABLE),
+                    )
+                };
+                (!epoll_open && !timer_open && dumpable == 0 && sigpipe_default() == 1) as i32
+            })
+            .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+        // SAFETY: the file descriptors are owned by this test and not used afterwards.
+        unsafe {
+            libc::close(epoll);
+            libc::close(timer);
+        }
+    }
+
+    // SAFETY: `getpgrp` does not have special safety requirements.
+    let parent_pgid = unsafe { libc::getpgrp() } as u32;
+    let child = ForkBuilder::new().spawn(|| 0).unwrap();
+    assert_eq!(child.pgid().unwrap(), parent_pgid);
+    child.join().unwrap();
+
+    let child = ForkBuilder::new()
+        .new_process_group()
+        // SAFETY: `getpgrp` and `getpid` do not have special safety requirements.
+        .spawn(|| unsafe { libc::getpgrp() == libc::getpid() } as i32)
+        .unwrap();
+    assert_eq!(child.pgid().unwrap(), child.pid());
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .new_session()
+        // SAFETY: `getsid` and `getpid` do not have special safety requirements.
+        .spawn(|| unsafe { libc::getsid(0) == libc::getpid() } as i32)
+        .unwrap();
+    assert_eq!(child.pgid().unwrap(), child.pid());
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let kept = std::fs::File::open("/dev/null").unwrap();
+    let closed = std::fs::File::open("/dev/null").unwrap();
+    let (kept, closed) = (kept.as_raw_fd(), closed.as_raw_fd());
+    let child = ForkBuilder::new()
+        .keep_fds(&[kept])
+        .spawn(|| {
+            // SAFETY: `fcntl` with `F_GETFD` does not have special safety requirements.
+            let open = |fd| unsafe { libc::fcntl(fd, libc::F_GETFD) } >= 0;
+            (open(0) && open(1) && open(2) && open(kept) && !open(closed)) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Mappings can swap file descriptors, and other file descriptors are closed.
+    let (mut first_reader, first_writer) = std::io::pipe().unwrap();
+    let (mut second_reader, second_writer) = std::io::pipe().unwrap();
+    let (first, second) = (first_writer.as_raw_fd(), second_writer.as_raw_fd());
+    let child = ForkBuilder::new()
+        .inherit_fd(first, second)
+        .inherit_fd(second, first)
+        .spawn(move || {
+            // SAFETY: the file descriptors are installed by the builder and not closed elsewhere.
+            let (mut first_file, mut second_file) = unsafe {
+                (
+                    std::fs::File::from_raw_fd(second),
+                    std::fs::File::from_raw_fd(first),
+                )
+            };
+            first_file.write_all(b"first").unwrap();
+            second_file.write_all(b"second").unwrap();
+            // SAFETY: `fcntl` with `F_GETFD` does not have special safety requirements.
+            (unsafe { libc::fcntl(closed, libc::F_GETFD) } < 0) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    drop((first_writer, second_writer));
+    let mut output = String::new();
+    first_reader.read_to_string(&mut output).unwrap();
+    assert_eq!(output, "first");
+    output.clear();
+    second_reader.read_to_string(&mut output).unwrap();
+    assert_eq!(output, "second");
+
+    // The targets are inherited by executed programs.
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let child = ForkBuilder::new()
+        .inherit_fd(writer.as_raw_fd(), 9)
+        .exec("sh", ["-c", "echo hello >&9"])
+        .unwrap();
+    drop(writer);
+    assert!(child.join().unwrap().success());
+    output.clear();
+    reader.read_to_string(&mut output).unwrap();
+    assert_eq!(output, "hello\n");
+}
@@ -0,0 +1,49 @@
+//! Capabilities are only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn status(field: &str) -> u64 {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap();
+    let line = status
+        .lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .unwrap();
+    u64::from_str_radix(line.trim(), if field == "NoNewPrivs" { 10 } else { 16 }).unwrap()
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use safe_fork::{Capability, ForkBuilder};
+
+    let child = ForkBuilder::new()
+        .no_new_privs()
+        .spawn(|| status("NoNewPrivs") as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // The user namespace grants the capabilities required to drop them from the bounding set.
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .drop_capabilities(&[Capability::NET_RAW])
+        .spawn(|| {
+            let bit = 1 << Capability::NET_RAW.raw();
+            (status("CapEff") & bit == 0
+                && status("CapBnd") & bit == 0
+                && status("CapEff") & 1 << Capability::SYS_ADMIN.raw() != 0) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .clear_capabilities()
+        .spawn(|| {
+            ["CapInh", "CapPrm", "CapEff", "CapBnd", "CapAmb"]
+                .iter()
+                .all(|field| status(field) == 0) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+}
@@ -0,0 +1,90 @@
+//! Cgroups are only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use std::io::Read;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use safe_fork::ForkBuilder;
+
+    // Find the mount point of cgroup v2, which may be mounted alongside cgroup v1.
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").unwrap();
+    let Some(root) = mountinfo.lines().find_map(|line| {
+        let (mount, fs) = line.split_once(" - ")?;
+        fs.starts_with("cgroup2 ")
+            .then(|| PathBuf::from(mount.split(' ').nth(4).unwrap()))
+    }) else {
+        return;
+    };
+    let name = format!("safe-fork-{}", std::process::id());
+    let dir = root.join(&name);
+    // Creating cgroups requires privilege or delegation.
+    if std::fs::create_dir(&dir).is_err() {
+        return;
+    }
+
+    let current = || std::fs::read_to_string("/proc/self/cgroup").unwrap();
+    let parent = current();
+    let child = ForkBuilder::new()
+        .cgroup(&dir)
+        .spawn(|| current().contains(&format!("0::/{name}\n")) as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert_eq!(current(), parent);
+
+    let fd = std::fs::File::open(&dir).unwrap();
+    let child = ForkBuilder::new()
+        .cgroup_fd(fd.into())
+        .spawn(|| current().contains(&format!("0::/{name}\n")) as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Killing the cgroup also kills the grandchild, which holds the write end of the pipe.
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let child = ForkBuilder::new()
+        .cgroup(&dir)
+        .spawn(move || {
+            let grandchild = safe_fork::fork_spawn(move || {
+                let _writer = writer;
+                std::thread::sleep(Duration::from_secs(10));
+                0
+            })
+            .unwrap();
+            grandchild.detach();
+            std::thread::sleep(Duration::from_secs(10));
+            0
+        })
+        .unwrap();
+    child.kill_tree().unwrap();
+    reader.read_to_end(&mut Vec::new()).unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGKILL));
+
+    // Exceeding the memory limit of the cgroup gets the child killed, which is told apart from
+    // crashing. The memory controller may not be enabled for the cgroup.
+    if dir.join("memory.max").exists() {
+        let child = ForkBuilder::new()
+            .cgroup(&dir)
+            .memory_limit(32 << 20)
+            .spawn(|| std::hint::black_box(vec![1u8; 256 << 20]).len() as i32)
+            .unwrap();
+        assert!(matches!(
+            child.join_checked(),
+            Err(safe_fork::ForkError::MemoryLimitExceeded)
+        ));
+        let child = ForkBuilder::new()
+            .cgroup(&dir)
+            .memory_limit(32 << 20)
+            .spawn(|| -> i32 { std::process::abort() })
+            .unwrap();
+        assert_eq!(child.join_checked().unwrap().signal(), Some(libc::SIGABRT));
+    }
+
+    // The cgroup can only be removed once all processes in it are gone.
+    while std::fs::remove_dir(&dir).is_err() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
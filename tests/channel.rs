@@ -0,0 +1,55 @@
+use std::io::{ErrorKind, Read, Write};
+
+use safe_fork::ForkChannel;
+
+fn main() {
+    match safe_fork::fork_with_channel::<Vec<String>>().unwrap() {
+        ForkChannel::Parent(child, tx, rx) => {
+            tx.send(&vec!["hello".into(), "world".into()]).unwrap();
+            assert_eq!(rx.recv().unwrap(), vec!["world", "hello"]);
+            drop(tx);
+            assert_eq!(rx.recv().unwrap_err().kind(), ErrorKind::UnexpectedEof);
+            assert!(child.join().unwrap().success());
+        }
+        ForkChannel::Child(tx, rx) => {
+            let mut msg = rx.recv().unwrap();
+            msg.reverse();
+            tx.send(&msg).unwrap();
+            // Wait for the parent to close its end before exiting.
+            let code = (rx.recv().unwrap_err().kind() != ErrorKind::UnexpectedEof) as i32;
+            std::process::exit(code);
+        }
+    }
+
+    // The child opens a file and hands the descriptor to the parent.
+    match safe_fork::fork_with_channel::<()>().unwrap() {
+        ForkChannel::Parent(child, _tx, rx) => {
+            let fd = rx.recv_fd().unwrap();
+            let mut content = String::new();
+            std::fs::File::from(fd)
+                .read_to_string(&mut content)
+                .unwrap();
+            assert_eq!(content, "from child");
+            assert!(child.join().unwrap().success());
+        }
+        ForkChannel::Child(tx, _rx) => {
+            let (reader, mut writer) = std::io::pipe().unwrap();
+            writer.write_all(b"from child").unwrap();
+            drop(writer);
+            tx.send_fd(reader.into()).unwrap();
+            std::process::exit(0);
+        }
+    }
+
+    // Sending to a child that has exited fails instead of raising `SIGPIPE`, which the Rust runtime
+    // ignores by default.
+    // SAFETY: `signal` does not have special safety requirements.
+    unsafe { libc::signal(libc::SIGPIPE, libc::SIG_DFL) };
+    match safe_fork::fork_with_channel::<u32>().unwrap() {
+        ForkChannel::Parent(child, tx, _rx) => {
+            assert!(child.join().unwrap().success());
+            assert_eq!(tx.send(&1).unwrap_err().kind(), ErrorKind::BrokenPipe);
+        }
+        ForkChannel::Child(..) => std::process::exit(0),
+    }
+}
@@ -0,0 +1,188 @@
+use std::io::{Read, Write};
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::time::Duration;
+
+use safe_fork::ChildStatus;
+
+fn main() {
+    let mut child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(200));
+        7
+    })
+    .unwrap();
+    assert!(child.try_join().unwrap().is_none());
+    let status = loop {
+        if let Some(status) = child.try_join().unwrap() {
+            break status;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert_eq!(status.code(), Some(7));
+    assert_eq!(child.try_join().unwrap(), Some(status));
+    assert_eq!(child.join().unwrap(), status);
+
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_secs(1));
+        3
+    })
+    .unwrap();
+    let child = child
+        .join_timeout(Duration::from_millis(50))
+        .unwrap()
+        .unwrap_err();
+    let status = child
+        .join_timeout(Duration::from_secs(10))
+        .unwrap()
+        .unwrap();
+    assert_eq!(status.code(), Some(3));
+
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_secs(10));
+        0
+    })
+    .unwrap();
+    child.kill().unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGKILL));
+
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_secs(10));
+        0
+    })
+    .unwrap();
+    let status = child.shutdown(Duration::from_secs(10)).unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+    // A child ignoring `SIGTERM` is killed once the grace period elapses.
+    let (mut reader, mut writer) = std::io::pipe().unwrap();
+    let child = safe_fork::fork_spawn(move || {
+        // SAFETY: ignoring `SIGTERM` does not have special safety requirements.
+        unsafe { libc::signal(libc::SIGTERM, libc::SIG_IGN) };
+        writer.write_all(&[0]).unwrap();
+        std::thread::sleep(Duration::from_secs(10));
+        0
+    })
+    .unwrap();
+    reader.read_exact(&mut [0]).unwrap();
+    let status = child.shutdown(Duration::from_millis(50)).unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_secs(10));
+        0
+    })
+    .unwrap();
+    child.terminate().unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGTERM));
+
+    let child = safe_fork::fork_spawn(|| {
+        // Avoid writing an actual core dump.
+        let limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) };
+        unsafe { libc::raise(libc::SIGABRT) };
+        0
+    })
+    .unwrap();
+    let status = child.join().unwrap();
+    assert!(matches!(
+        status,
+        ChildStatus::Signaled {
+            signal: libc::SIGABRT,
+            ..
+        }
+    ));
+    assert!(!status.success());
+    assert_eq!(ChildStatus::from_raw(status.into_raw()), status);
+    assert_eq!(
+        ChildStatus::from_raw(ChildStatus::Exited(42).into_raw()),
+        ChildStatus::Exited(42)
+    );
+
+    let mut child = safe_fork::fork_spawn(|| 0).unwrap();
+    while child.try_join().unwrap().is_none() {}
+    child.signal(libc::SIGUSR1).unwrap();
+
+    // The pidfd becomes readable once the child exits.
+    #[cfg(target_os = "linux")]
+    {
+        let mut child = safe_fork::fork_spawn(|| 0).unwrap();
+        poll_readable(child.pidfd().unwrap());
+        assert!(child.try_join().unwrap().is_some());
+    }
+
+    // Ownership of the child can be transferred through its PID or its pidfd.
+    let child = safe_fork::fork_spawn(|| 5).unwrap();
+    let pid = child.pid();
+    assert_eq!(child.into_raw_pid(), pid);
+    assert!(safe_fork::active_children()
+        .iter()
+        .all(|info| info.pid() != pid));
+    // SAFETY: the child is not reaped by anything else.
+    let child = unsafe { safe_fork::Child::from_raw_pid(pid) };
+    #[cfg(target_os = "linux")]
+    let child = {
+        let pidfd = child.into_pidfd().unwrap();
+        // SAFETY: the child is not reaped by anything else.
+        unsafe { safe_fork::Child::from_pidfd(pidfd) }.unwrap()
+    };
+    assert_eq!(child.pid(), pid);
+    assert_eq!(child.join().unwrap().code(), Some(5));
+
+    let mut child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_secs(10));
+        0
+    })
+    .unwrap();
+    child.suspend().unwrap();
+    assert_eq!(
+        child.wait_event().unwrap(),
+        ChildStatus::Stopped(libc::SIGSTOP)
+    );
+    assert_eq!(child.try_wait_event().unwrap(), None);
+    child.resume().unwrap();
+    assert_eq!(child.wait_event().unwrap(), ChildStatus::Continued);
+    child.kill().unwrap();
+    let status = ChildStatus::Signaled {
+        signal: libc::SIGKILL,
+        core_dumped: false,
+    };
+    assert_eq!(child.wait_event().unwrap(), status);
+    assert_eq!(child.join().unwrap(), status);
+
+    let child = safe_fork::fork_spawn(|| {
+        // Touch a fresh allocation and burn some CPU time.
+        let buf = std::hint::black_box(vec![1u8; 64 << 20]);
+        let start = std::time::Instant::now();
+        while start.elapsed() < Duration::from_millis(100) {
+            std::hint::black_box(&buf);
+        }
+        0
+    })
+    .unwrap();
+    let (status, usage) = child.join_with_rusage().unwrap();
+    assert!(status.success());
+    assert!(usage.user_time + usage.system_time >= Duration::from_millis(50));
+    assert!(usage.max_rss >= 64 << 20);
+    assert!(usage.minor_faults > 0);
+
+    let child = safe_fork::fork_spawn(|| std::thread::sleep(Duration::from_millis(100))).unwrap();
+    let spawned_at = child.spawned_at();
+    let report = child.join_with_report().unwrap();
+    assert!(report.status.success());
+    assert!(report.duration >= Duration::from_millis(100));
+    assert!(spawned_at.elapsed() >= report.duration);
+}
+
+#[cfg(target_os = "linux")]
+fn poll_readable(fd: BorrowedFd<'_>) {
+    let mut pollfd = libc::pollfd {
+        fd: fd.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: `pollfd` is valid for the duration of the call.
+    assert_eq!(unsafe { libc::poll(&mut pollfd, 1, -1) }, 1);
+}
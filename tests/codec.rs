@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+
+use safe_fork::{ForkChannel, ForkMap, ForkPool, Json, Postcard};
+
+fn main() {
+    // Values round-trip through each codec.
+    assert_eq!(
+        safe_fork::fork_join_value_codec::<Postcard, _>(|| (42u32, String::from("hello"))).unwrap(),
+        (42, String::from("hello"))
+    );
+    assert_eq!(
+        safe_fork::fork_join_value_codec::<Json, _>(|| vec![Some(1.5f64), None]).unwrap(),
+        [Some(1.5), None]
+    );
+    assert_eq!(
+        safe_fork::fork_join_result_codec::<Postcard, _, _>(|| Err::<u8, _>(String::from("bad")))
+            .unwrap(),
+        Err(String::from("bad"))
+    );
+
+    // JSON cannot serialize maps with non-string keys, so the child fails to send the value.
+    let err = safe_fork::fork_join_value_codec::<Json, _>(|| HashMap::from([((1u8, 2u8), 3u8)]))
+        .unwrap_err();
+    assert!(err.to_string().contains("exit status: 1"), "{err}");
+
+    let squares = ForkMap::new()
+        .chunk_size(10)
+        .codec::<Postcard>()
+        .run(0..100u64, |x| x * x)
+        .unwrap();
+    assert_eq!(squares[99], 9801);
+
+    let mut results: Vec<_> = safe_fork::fork_map_iter_codec::<Json, _, _>(0..3u32, |x| x + 1)
+        .unwrap()
+        .map(|(index, result)| (index, result.unwrap()))
+        .collect();
+    results.sort();
+    assert_eq!(results, [(0, 1), (1, 2), (2, 3)]);
+
+    match safe_fork::fork_with_channel_codec::<Postcard, Vec<String>>().unwrap() {
+        ForkChannel::Parent(child, tx, rx) => {
+            tx.send(&vec!["hello".into(), "world".into()]).unwrap();
+            assert_eq!(rx.recv().unwrap(), vec!["world", "hello"]);
+            drop(tx);
+            assert!(child.join().unwrap().success());
+        }
+        ForkChannel::Child(tx, rx) => {
+            let mut msg = rx.recv().unwrap();
+            msg.reverse();
+            tx.send(&msg).unwrap();
+            let code = (rx.recv().unwrap_err().kind() != ErrorKind::UnexpectedEof) as i32;
+            std::process::exit(code);
+        }
+    }
+
+    let mut service = safe_fork::fork_service_codec::<Json, _, _>(|s: String| s.len()).unwrap();
+    assert_eq!(service.call(String::from("hello")).unwrap(), 5);
+    assert!(service.shutdown().unwrap().success());
+
+    let pool = ForkPool::<_, _, Postcard>::with_codec(2, |x: i64| -x).unwrap();
+    assert_eq!(pool.map([1, 2, 3]).unwrap(), [-1, -2, -3]);
+}
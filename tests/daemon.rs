@@ -0,0 +1,42 @@
+use std::path::Path;
+use std::time::Duration;
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("safe-fork-daemon-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let pidfile = dir.join("daemon.pid");
+    let marker = dir.join("marker");
+
+    let pid = safe_fork::Daemon::new()
+        .pidfile(&pidfile)
+        .spawn(|| {
+            // SAFETY: `getsid` does not have special safety requirements.
+            let sid = unsafe { libc::getsid(0) };
+            let report = format!(
+                "{} {}",
+                std::env::current_dir().unwrap().display(),
+                sid != std::process::id() as libc::pid_t,
+            );
+            std::fs::write(&marker, report).unwrap();
+            0
+        })
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&pidfile).unwrap(),
+        format!("{pid}\n")
+    );
+    while !marker.exists() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(std::fs::read_to_string(&marker).unwrap(), "/ true");
+
+    let err = safe_fork::Daemon::new()
+        .pidfile(Path::new("/nonexistent/daemon.pid"))
+        .spawn(|| 0)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
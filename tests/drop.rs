@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+fn waitpid(pid: u32) -> libc::c_int {
+    // SAFETY: `waitpid` does not have special safety requirements.
+    unsafe { libc::waitpid(pid as _, std::ptr::null_mut(), 0) }
+}
+
+fn main() {
+    // Dropping a child that has exited reaps it immediately.
+    let child = safe_fork::fork_spawn(|| 0).unwrap();
+    let pid = child.pid();
+    std::thread::sleep(Duration::from_millis(100));
+    drop(child);
+    assert_eq!(waitpid(pid), -1);
+
+    // Dropping a running child reaps it on the next fork.
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        0
+    })
+    .unwrap();
+    let pid = child.pid();
+    drop(child);
+    std::thread::sleep(Duration::from_millis(200));
+    assert_eq!(safe_fork::fork_join(|| 0).unwrap(), 0);
+    assert_eq!(waitpid(pid), -1);
+
+    // Detached children are left alone.
+    let child = safe_fork::fork_spawn(|| 0).unwrap();
+    let pid = child.pid();
+    child.detach();
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(safe_fork::fork_join(|| 0).unwrap(), 0);
+    assert_eq!(waitpid(pid), pid as libc::c_int);
+}
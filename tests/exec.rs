@@ -0,0 +1,137 @@
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::ExitStatusExt;
+
+use safe_fork::{ForkBuilder, ForkError, SpawnBackend, StdChild, Stdio};
+
+fn main() {
+    let child = ForkBuilder::new().exec("sh", ["-c", "exit 3"]).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(3));
+
+    let child = ForkBuilder::new()
+        .chdir("/")
+        .env("FOO", "bar")
+        .exec("sh", ["-c", r#"[ "$(pwd)" = / ] && [ "$FOO" = bar ]"#])
+        .unwrap();
+    assert!(child.join().unwrap().success());
+
+    let err = ForkBuilder::new()
+        .exec("/nonexistent", [""; 0])
+        .unwrap_err();
+    assert!(matches!(err, ForkError::ExecFailed(err) if err.kind() == ErrorKind::NotFound));
+
+    let err = ForkBuilder::new().exec("sh\0", [""; 0]).unwrap_err();
+    assert_eq!(std::io::Error::from(err).kind(), ErrorKind::InvalidInput);
+
+    // The environment is replaced, and close-on-exec file descriptors are inherited if kept.
+    std::env::set_var("INHERITED", "1");
+    let child = safe_fork::fork_exec(
+        "sh",
+        ["-c", r#"[ -z "$INHERITED" ] && [ "$FOO" = baz ]"#],
+        [("FOO", "baz")],
+    )
+    .unwrap();
+    assert!(child.join().unwrap().success());
+
+    // The inherited environment can be changed piecewise, or cleared.
+    let child = ForkBuilder::new()
+        .env_remove("INHERITED")
+        .exec("sh", ["-c", r#"[ -z "${INHERITED+set}" ]"#])
+        .unwrap();
+    assert!(child.join().unwrap().success());
+    let child = ForkBuilder::new()
+        .env_clear()
+        .env("FOO", "bar")
+        .stdout(Stdio::Piped)
+        .exec("env", [""; 0])
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.stdout, b"FOO=bar\n");
+
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let script = format!("echo hello >&{}", writer.as_raw_fd());
+    let child = ForkBuilder::new()
+        .keep_fds(&[writer.as_raw_fd()])
+        .exec("sh", ["-c", &script])
+        .unwrap();
+    drop(writer);
+    let mut output = String::new();
+    reader.read_to_string(&mut output).unwrap();
+    assert_eq!(output, "hello\n");
+    assert!(child.join().unwrap().success());
+
+    // Children can be used through the same interface as `std::process::Child`.
+    let mut child = StdChild::from(
+        ForkBuilder::new()
+            .stdin(Stdio::Piped)
+            .stdout(Stdio::Piped)
+            .exec("cat", [""; 0])
+            .unwrap(),
+    );
+    child.stdin.take().unwrap().write_all(b"hello").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"hello");
+
+    let mut child = StdChild::from(ForkBuilder::new().exec("sleep", ["10"]).unwrap());
+    assert!(child.id() > 0);
+    assert!(child.try_wait().unwrap().is_none());
+    child.kill().unwrap();
+    let status = child.wait().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+    assert_eq!(child.try_wait().unwrap(), Some(status));
+    child.kill().unwrap();
+
+    // Spawning an exec-only child does not require the process to be single-threaded.
+    let thread = std::thread::spawn(|| std::thread::sleep(std::time::Duration::from_millis(500)));
+    let child = safe_fork::fork_exec("true", [""; 0], [("", ""); 0]).unwrap();
+    assert!(child.join().unwrap().success());
+    let err = safe_fork::fork_exec("/nonexistent", [""; 0], [("", ""); 0]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // With `posix_spawn`, part of the configuration can be applied without forking.
+    let child = ForkBuilder::new()
+        .spawn_backend(SpawnBackend::PosixSpawn)
+        .chdir("/")
+        .env("FOO", "bar")
+        .new_process_group()
+        .stdout(Stdio::Piped)
+        .stderr_to_stdout()
+        .exec(
+            "sh",
+            [
+                "-c",
+                r#"echo "$(pwd) $FOO $INHERITED" >&2; cut -d' ' -f1,5 /proc/$$/stat"#,
+            ],
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let output = String::from_utf8(output.stdout).unwrap();
+    let (env, ids) = output.split_once('\n').unwrap();
+    assert_eq!(env, "/ bar 1");
+    let (pid, pgid) = ids.trim().split_once(' ').unwrap();
+    assert_eq!(pid, pgid);
+    let child = ForkBuilder::new()
+        .spawn_backend(SpawnBackend::PosixSpawn)
+        .env_clear()
+        .env("FOO", "bar")
+        .stdout(Stdio::Piped)
+        .exec("env", [""; 0])
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.stdout, b"FOO=bar\n");
+
+    let err = ForkBuilder::new()
+        .spawn_backend(SpawnBackend::PosixSpawn)
+        .umask(0o22)
+        .exec("true", [""; 0])
+        .unwrap_err();
+    assert_eq!(std::io::Error::from(err).kind(), ErrorKind::Unsupported);
+    let err = ForkBuilder::new()
+        .spawn_backend(SpawnBackend::PosixSpawn)
+        .exec("/nonexistent", [""; 0])
+        .unwrap_err();
+    assert!(matches!(err, ForkError::ExecFailed(err) if err.kind() == ErrorKind::NotFound));
+    thread.join().unwrap();
+}
@@ -0,0 +1,35 @@
+use std::io::Read;
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+fn main() {
+    // The forking process may be multi-threaded, as long as the child only calls
+    // async-signal-safe functions.
+    let thread = std::thread::spawn(|| std::thread::sleep(Duration::from_millis(500)));
+
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let fd = writer.as_raw_fd();
+    // SAFETY: the child only calls `write`, which is async-signal-safe.
+    let child = unsafe {
+        safe_fork::fork_spawn_raw(|| {
+            let msg = b"hello";
+            if libc::write(fd, msg.as_ptr().cast(), msg.len()) != msg.len() as isize {
+                return 1;
+            }
+            7
+        })
+    }
+    .unwrap();
+    drop(writer);
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"hello");
+    assert_eq!(child.join().unwrap().code(), Some(7));
+
+    // Unwinding aborts the child. The calling process is still single-threaded by now.
+    thread.join().unwrap();
+    // SAFETY: the process is single-threaded, so the child may panic.
+    let child =
+        unsafe { safe_fork::fork_spawn_raw(|| std::panic::resume_unwind(Box::new(()))) }.unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGABRT));
+}
@@ -0,0 +1,39 @@
+use safe_fork::forked_test;
+
+#[forked_test]
+fn sets_env() {
+    std::env::set_var("SAFE_FORK_FORKED_TEST", "1");
+    assert_eq!(std::env::var("SAFE_FORK_FORKED_TEST").as_deref(), Ok("1"));
+}
+
+#[forked_test]
+fn env_not_polluted() {
+    assert!(std::env::var_os("SAFE_FORK_FORKED_TEST").is_none());
+}
+
+#[forked_test]
+fn changes_directory() {
+    std::env::set_current_dir("/").unwrap();
+}
+
+#[test]
+fn directory_not_changed() {
+    assert_ne!(std::env::current_dir().unwrap(), std::path::Path::new("/"));
+}
+
+#[forked_test]
+fn returns_ok() -> Result<(), String> {
+    Ok(())
+}
+
+#[forked_test]
+#[should_panic]
+fn panics() {
+    panic!("expected");
+}
+
+#[forked_test]
+#[should_panic]
+fn returns_err() -> Result<(), String> {
+    Err(String::from("expected"))
+}
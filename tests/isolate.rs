@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use safe_fork::{IsolatedOutcome, Isolation, Rlimits};
+
+fn main() {
+    assert!(safe_fork::run_isolated(|| 0).unwrap().success());
+    assert_eq!(
+        safe_fork::run_isolated(|| 3).unwrap(),
+        IsolatedOutcome::Exited(3)
+    );
+
+    let outcome = safe_fork::run_isolated(|| -> i32 { panic!("bad input") }).unwrap();
+    assert!(
+        matches!(outcome, IsolatedOutcome::Panicked(panicked) if panicked.message == "bad input")
+    );
+
+    // Core dumps are disabled by default.
+    let outcome = safe_fork::run_isolated(|| -> i32 { std::process::abort() }).unwrap();
+    assert_eq!(
+        outcome,
+        IsolatedOutcome::Crashed {
+            signal: libc::SIGABRT,
+            core_dumped: false
+        }
+    );
+
+    let outcome = Isolation::new()
+        .timeout(Duration::from_millis(50))
+        .run(|| std::thread::sleep(Duration::from_secs(10)))
+        .unwrap();
+    assert_eq!(outcome, IsolatedOutcome::Timeout);
+
+    let outcome = Isolation::new()
+        .rlimits(Rlimits::new().set(safe_fork::Resource::Cpu, 1, 2))
+        .run(|| -> i32 {
+            loop {
+                std::hint::spin_loop();
+            }
+        })
+        .unwrap();
+    assert_eq!(outcome, IsolatedOutcome::Timeout);
+
+    // The OOM killer is emulated by killing the child from within.
+    let outcome = safe_fork::run_isolated(|| {
+        // SAFETY: raising `SIGKILL` does not have special safety requirements.
+        unsafe { libc::raise(libc::SIGKILL) }
+    })
+    .unwrap();
+    assert_eq!(outcome, IsolatedOutcome::OutOfMemory);
+}
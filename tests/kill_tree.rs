@@ -0,0 +1,32 @@
+use std::io::{ErrorKind, Read};
+use std::time::Duration;
+
+use safe_fork::ForkBuilder;
+
+fn main() {
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let child = ForkBuilder::new()
+        .new_process_group()
+        .spawn(move || {
+            let grandchild = safe_fork::fork_spawn(move || {
+                let _writer = writer;
+                std::thread::sleep(Duration::from_secs(10));
+                0
+            })
+            .unwrap();
+            grandchild.detach();
+            std::thread::sleep(Duration::from_secs(10));
+            0
+        })
+        .unwrap();
+    child.kill_tree().unwrap();
+    // The grandchild holds the write end, so EOF means it has been killed too.
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGKILL));
+
+    let child = ForkBuilder::new().spawn(|| 0).unwrap();
+    let err = child.kill_tree().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    child.join().unwrap();
+}
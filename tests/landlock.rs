@@ -0,0 +1,43 @@
+//! Landlock is only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use std::io::ErrorKind;
+
+    use safe_fork::ForkBuilder;
+
+    let dir = std::env::temp_dir().join(format!("safe-fork-landlock-{}", std::process::id()));
+    let (readonly, writable) = (dir.join("readonly"), dir.join("writable"));
+    std::fs::create_dir_all(&readonly).unwrap();
+    std::fs::create_dir_all(&writable).unwrap();
+    std::fs::write(readonly.join("file"), "content").unwrap();
+
+    let child = ForkBuilder::new()
+        .allow_read(&readonly)
+        .allow_write(&writable)
+        .spawn(|| {
+            let read = std::fs::read_to_string(readonly.join("file")).is_ok();
+            let denied_write = std::fs::write(readonly.join("file"), "")
+                .is_err_and(|err| err.kind() == ErrorKind::PermissionDenied);
+            let written = std::fs::write(writable.join("file"), "").is_ok();
+            let denied_read =
+                std::fs::read_dir("/").is_err_and(|err| err.kind() == ErrorKind::PermissionDenied);
+            (read && denied_write && written && denied_read) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Paths are opened in the child, so missing ones fail the setup.
+    let err = ForkBuilder::new()
+        .allow_read(dir.join("nonexistent"))
+        .spawn(|| 0)
+        .unwrap_err();
+    assert!(
+        matches!(err, safe_fork::ForkError::SetupFailed(err) if err.kind() == ErrorKind::NotFound)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
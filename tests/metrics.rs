@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use safe_fork::{ChildStatus, MetricsSink};
+
+#[derive(Default)]
+struct Recorder {
+    attempted: AtomicUsize,
+    failed: Mutex<Vec<i32>>,
+    reaped: Mutex<Vec<(ChildStatus, Duration)>>,
+}
+
+impl MetricsSink for Recorder {
+    fn fork_attempted(&self) {
+        self.attempted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn fork_failed(&self, errno: i32) {
+        self.failed.lock().unwrap().push(errno);
+    }
+
+    fn child_reaped(&self, status: ChildStatus, lifetime: Duration) {
+        self.reaped.lock().unwrap().push((status, lifetime));
+    }
+}
+
+fn main() {
+    let recorder = Arc::new(Recorder::default());
+    safe_fork::set_metrics_sink(Some(recorder.clone()));
+
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        2
+    })
+    .unwrap();
+    child.join().unwrap();
+
+    // Orphaned children are reported once they are reaped.
+    drop(safe_fork::fork_spawn(|| 3).unwrap());
+    std::thread::sleep(Duration::from_millis(100));
+    safe_fork::reap_exited();
+
+    let err = safe_fork::fork_exec("/nonexistent", [""; 0], [("", ""); 0]).unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::ENOENT));
+
+    assert_eq!(recorder.attempted.load(Ordering::Relaxed), 3);
+    assert_eq!(*recorder.failed.lock().unwrap(), [libc::ENOENT]);
+    let reaped = recorder.reaped.lock().unwrap().clone();
+    assert_eq!(reaped.len(), 2);
+    assert_eq!(reaped[0].0, ChildStatus::Exited(2));
+    assert!(reaped[0].1 >= Duration::from_millis(100));
+    assert_eq!(reaped[1].0, ChildStatus::Exited(3));
+
+    safe_fork::set_metrics_sink(None);
+    safe_fork::fork_join(|| 0).unwrap();
+    assert_eq!(recorder.attempted.load(Ordering::Relaxed), 3);
+}
@@ -0,0 +1,271 @@
+//! Namespaces are only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+use std::io::{Read, Write};
+
+#[cfg(target_os = "linux")]
+use safe_fork::{CloneFlags, ForkBuilder, IdMap, Pid, TimeOffsets};
+
+#[cfg(target_os = "linux")]
+fn ns(name: &str) -> std::path::PathBuf {
+    std::fs::read_link(format!("/proc/self/ns/{name}")).unwrap()
+}
+
+#[cfg(target_os = "linux")]
+fn clock(id: libc::clockid_t) -> std::time::Duration {
+    // SAFETY: all-zero is a valid `timespec`, which is valid for the duration of the call.
+    let ts = unsafe {
+        let mut ts = std::mem::zeroed::<libc::timespec>();
+        libc::clock_gettime(id, &mut ts);
+        ts
+    };
+    std::time::Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+}
+
+#[cfg(target_os = "linux")]
+fn check(name: &str, builder: &mut ForkBuilder) {
+    let parent = ns(name);
+    let child = builder.spawn(|| (ns(name) != parent) as i32).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1), "{name}");
+}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    // Children that do not execute a program are forked by the C library, which runs its fork
+    // handlers.
+    static FORKED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+    extern "C" fn forked() {
+        FORKED.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    // SAFETY: the handler only stores to an atomic.
+    unsafe { libc::pthread_atfork(None, None, Some(forked)) };
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .new_net_ns()
+        .spawn(|| FORKED.load(std::sync::atomic::Ordering::Relaxed) as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    check("user", ForkBuilder::new().new_user_ns());
+    check("mnt", ForkBuilder::new().new_mount_ns());
+    check("net", ForkBuilder::new().new_net_ns());
+    check("uts", ForkBuilder::new().new_uts_ns());
+    check("ipc", ForkBuilder::new().new_ipc_ns());
+
+    // System V IPC objects of the parent are not visible in a new IPC namespace.
+    // SAFETY: `shmget` does not have special safety requirements.
+    let shm = unsafe { libc::shmget(libc::IPC_PRIVATE, 4096, libc::IPC_CREAT | 0o600) };
+    assert!(shm >= 0);
+    // SAFETY: all-zero is a valid `shmid_ds`, which is valid for the duration of the call.
+    let visible = move || unsafe {
+        let mut ds = std::mem::zeroed::<libc::shmid_ds>();
+        (libc::shmctl(shm, libc::IPC_STAT, &mut ds) == 0) as i32
+    };
+    let child = ForkBuilder::new().spawn(visible).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .new_ipc_ns()
+        .spawn(visible)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(0));
+    // SAFETY: the segment is not attached anywhere.
+    unsafe { libc::shmctl(shm, libc::IPC_RMID, std::ptr::null_mut()) };
+
+    // The clocks are shifted in a new time namespace, but not in the parent.
+    let day = std::time::Duration::from_secs(24 * 60 * 60);
+    let (parent_ns, monotonic, boottime) = (
+        ns("time"),
+        clock(libc::CLOCK_MONOTONIC),
+        clock(libc::CLOCK_BOOTTIME),
+    );
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .new_time_ns(TimeOffsets::new().monotonic(day).boottime(365 * day))
+        .spawn(|| {
+            (ns("time") != parent_ns
+                && clock(libc::CLOCK_MONOTONIC) >= monotonic + day
+                && clock(libc::CLOCK_MONOTONIC) < monotonic + 2 * day
+                && clock(libc::CLOCK_BOOTTIME) >= boottime + 365 * day) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert!(clock(libc::CLOCK_MONOTONIC) < monotonic + day);
+
+    let hostname = || std::fs::read_to_string("/proc/sys/kernel/hostname").unwrap();
+    let parent = hostname();
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .hostname("sandbox-1")
+        .spawn(|| (hostname() == "sandbox-1\n") as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    assert_eq!(hostname(), parent);
+
+    // The loopback interface is down in a new network namespace unless brought up.
+    let connect = || {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        std::net::TcpStream::connect(listener.local_addr().unwrap()).is_ok() as i32
+    };
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .new_net_ns()
+        .spawn(connect)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(0));
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .loopback_up()
+        .spawn(connect)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .new_pid_ns()
+        .spawn(|| std::process::id() as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    // The parent is outside the new PID namespace.
+    let child = ForkBuilder::new()
+        .new_pid_ns()
+        .spawn(|| Pid::parent().is_none() as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Namespaces compose with each other.
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .new_mount_ns()
+        .new_pid_ns()
+        .new_net_ns()
+        .new_ipc_ns()
+        .spawn(|| std::process::id() as i32)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // SAFETY: `getuid` and `getgid` do not have special safety requirements.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    let child = ForkBuilder::new()
+        .uid_map(&[IdMap::new(0, uid, 1)])
+        .gid_map(&[IdMap::new(0, gid, 1)])
+        .spawn(|| {
+            let setgroups = std::fs::read_to_string("/proc/self/setgroups").unwrap();
+            // SAFETY: `getuid` and `getgid` do not have special safety requirements.
+            (unsafe { libc::getuid() == 0 && libc::getgid() == 0 } && setgroups == "deny\n") as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    // Mapping IDs that are not delegated fails before the closure runs.
+    let err = ForkBuilder::new()
+        .uid_map(&[IdMap::new(0, uid, 1), IdMap::new(0, uid + 1, 1)])
+        .spawn(|| 0)
+        .unwrap_err();
+    assert!(matches!(err, safe_fork::ForkError::SetupFailed(_)));
+
+    let dir = std::env::temp_dir().join(format!("safe-fork-mount-{}", std::process::id()));
+    let (src, dst) = (dir.join("src"), dir.join("dst"));
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::create_dir_all(&dst).unwrap();
+    std::fs::write(src.join("file"), "content").unwrap();
+    // Files can only be created with a mapped user ID.
+    let child = ForkBuilder::new()
+        .uid_map(&[IdMap::new(0, uid, 1)])
+        .gid_map(&[IdMap::new(0, gid, 1)])
+        .readonly_root()
+        .mount_tmpfs(&dir)
+        .spawn(|| {
+            // SAFETY: all-zero is a valid `statvfs`, which is valid for the duration of the call.
+            let root_readonly = unsafe {
+                let mut stat = std::mem::zeroed::<libc::statvfs>();
+                libc::statvfs(c"/".as_ptr(), &mut stat);
+                stat.f_flag & libc::ST_RDONLY != 0
+            };
+            let written = std::fs::write(dir.join("tmp"), "").is_ok();
+            (root_readonly && written) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    // The tmpfs is only visible to the child.
+    assert!(!dir.join("tmp").exists());
+
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .bind_mount(&src, &dst, true)
+        .spawn(|| {
+            let content = std::fs::read_to_string(dst.join("file")).unwrap();
+            let err = std::fs::write(dst.join("file"), "").unwrap_err();
+            (content == "content" && err.kind() == std::io::ErrorKind::ReadOnlyFilesystem) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let root = std::env::temp_dir().join(format!("safe-fork-root-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("marker"), "").unwrap();
+    let in_root = || {
+        let cwd = std::env::current_dir().unwrap();
+        (std::path::Path::new("/marker").exists() && cwd == std::path::Path::new("/")) as i32
+    };
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .chroot(&root)
+        .spawn(in_root)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    let child = ForkBuilder::new()
+        .new_user_ns()
+        .pivot_root(&root)
+        .spawn(in_root)
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+    std::fs::remove_dir_all(&root).unwrap();
+
+    // The init process forwards signals to the child running the closure.
+    let (mut reader, mut writer) = std::io::pipe().unwrap();
+    let child = ForkBuilder::new()
+        .new_pid_ns()
+        .spawn(move || {
+            let status = safe_fork::pid1_init(|| {
+                writer.write_all(&[std::process::id() as u8]).unwrap();
+                std::thread::sleep(std::time::Duration::from_secs(10));
+            })
+            .unwrap();
+            status.signal().unwrap_or(0)
+        })
+        .unwrap();
+    let mut pid = [0];
+    reader.read_exact(&mut pid).unwrap();
+    assert_eq!(pid, [2]);
+    child.terminate().unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(libc::SIGTERM));
+
+    check(
+        "cgroup",
+        ForkBuilder::new().clone_flags(CloneFlags::NEWCGROUP),
+    );
+    // SAFETY: `CLONE_NEWUTS` keeps the fork semantics.
+    check("uts", unsafe {
+        ForkBuilder::new().raw_clone_flags(libc::CLONE_NEWUTS as u64)
+    });
+
+    extern "C" fn handler(_: libc::c_int) {}
+    // SAFETY: the handler is async-signal-safe.
+    unsafe {
+        libc::signal(
+            libc::SIGUSR1,
+            handler as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        )
+    };
+    let child = ForkBuilder::new()
+        .clone_flags(CloneFlags::CLEAR_SIGHAND)
+        // SAFETY: restoring the default disposition is always sound.
+        .spawn(|| (unsafe { libc::signal(libc::SIGUSR1, libc::SIG_DFL) } == libc::SIG_DFL) as i32)
+        .unwrap();
+    assert!(child.pidfd().is_some());
+    assert_eq!(child.join().unwrap().code(), Some(1));
+}
@@ -0,0 +1,94 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use safe_fork::{ForkBuilder, Stdio};
+
+fn main() {
+    print!("unflushed ");
+    let output = safe_fork::fork_output(|| {
+        println!("hello");
+        eprintln!("world");
+        3
+    })
+    .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"hello\n");
+    assert_eq!(output.stderr, b"world\n");
+
+    // Large outputs on both streams must not deadlock.
+    let output = safe_fork::fork_output(|| {
+        let chunk = vec![b'x'; 1 << 20];
+        std::io::Write::write_all(&mut std::io::stdout(), &chunk).unwrap();
+        std::io::Write::write_all(&mut std::io::stderr(), &chunk).unwrap();
+        0
+    })
+    .unwrap();
+    assert_eq!(output.stdout.len(), 1 << 20);
+    assert_eq!(output.stderr.len(), 1 << 20);
+
+    // Output can be collected after writing to the child, which must not deadlock even if it
+    // writes more than fits into the pipes before its input is closed.
+    let mut child = ForkBuilder::new()
+        .stdin(Stdio::Piped)
+        .stdout(Stdio::Piped)
+        .stderr(Stdio::Piped)
+        .spawn(|| {
+            let chunk = vec![b'y'; 1 << 20];
+            std::io::stdout().write_all(&chunk).unwrap();
+            std::io::stderr().write_all(&chunk).unwrap();
+            let mut input = Vec::new();
+            std::io::stdin().read_to_end(&mut input).unwrap();
+            (input == b"input") as i32
+        })
+        .unwrap();
+    child.stdin.as_mut().unwrap().write_all(b"input").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout.len(), 1 << 20);
+    assert_eq!(output.stderr.len(), 1 << 20);
+
+    // With stderr merged into stdout, the order between the streams is preserved.
+    let mut child = ForkBuilder::new()
+        .stdout(Stdio::Piped)
+        .stderr(Stdio::Piped)
+        .stderr_to_stdout()
+        .spawn(|| {
+            println!("one");
+            eprintln!("two");
+            println!("three");
+        })
+        .unwrap();
+    assert!(child.stderr.is_none());
+    let mut output = String::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "one\ntwo\nthree\n");
+    assert!(child.join().unwrap().success());
+
+    // Piped streams can be used while the child is running.
+    let mut child = ForkBuilder::new()
+        .stdin(Stdio::Piped)
+        .stdout(Stdio::Piped)
+        .spawn(|| {
+            for line in std::io::stdin().lines() {
+                println!("{}", line.unwrap().to_uppercase());
+            }
+        })
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    for word in ["foo", "bar"] {
+        writeln!(stdin, "{word}").unwrap();
+        line.clear();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(line, format!("{}\n", word.to_uppercase()));
+    }
+    drop(stdin);
+    assert_eq!(stdout.read_line(&mut line).unwrap(), 0);
+    assert!(child.join().unwrap().success());
+    println!();
+}
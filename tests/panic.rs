@@ -0,0 +1,66 @@
+use std::os::fd::AsRawFd;
+
+use safe_fork::{ChildPanicked, ForkBuilder, ForkError};
+
+fn main() {
+    // Keep the output clean; the messages are checked below instead.
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let child = safe_fork::fork_spawn(|| -> i32 { panic!("oops") }).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(101));
+
+    let child = ForkBuilder::new()
+        .panic_exit_code(7)
+        .spawn(|| -> i32 { panic!("oops") })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(7));
+
+    let child = ForkBuilder::new()
+        .capture_panics()
+        .spawn(|| -> i32 { panic!("oops: {}", 42) })
+        .unwrap();
+    let err = child.join().unwrap_err();
+    let panicked = err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<ChildPanicked>())
+        .unwrap();
+    assert_eq!(panicked.message, "oops: 42");
+    assert!(
+        panicked.backtrace.contains("panic"),
+        "{}",
+        panicked.backtrace
+    );
+    assert_eq!(err.to_string(), "child process panicked: oops: 42");
+
+    // Typed errors report the panic as its own variant.
+    let child = ForkBuilder::new()
+        .capture_panics()
+        .spawn(|| -> i32 { panic!("oops") })
+        .unwrap();
+    assert!(matches!(
+        child.join_checked(),
+        Err(ForkError::Panicked(panicked)) if panicked.message == "oops"
+    ));
+
+    // Panics are still captured if other file descriptors are closed, or overwritten by mappings.
+    let child = ForkBuilder::new()
+        .close_fds()
+        .capture_panics()
+        .spawn(|| -> i32 { panic!("boom") })
+        .unwrap();
+    let err = child.join().unwrap_err();
+    assert_eq!(err.to_string(), "child process panicked: boom");
+    let devnull = std::fs::File::open("/dev/null").unwrap();
+    let mut builder = ForkBuilder::new();
+    builder.close_fds().capture_panics();
+    for target in 3..16 {
+        builder.inherit_fd(devnull.as_raw_fd(), target);
+    }
+    let child = builder.spawn(|| -> i32 { panic!("boom") }).unwrap();
+    let err = child.join().unwrap_err();
+    assert_eq!(err.to_string(), "child process panicked: boom");
+
+    // Failures other than panics are still reported as the exit status.
+    let child = ForkBuilder::new().capture_panics().spawn(|| 3).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(3));
+}
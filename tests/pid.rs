@@ -0,0 +1,37 @@
+use safe_fork::Pid;
+
+fn main() {
+    // Conversions reject values that are not process IDs.
+    assert!(Pid::try_from(0i32).is_err());
+    assert!(Pid::try_from(-1i32).is_err());
+    assert!(Pid::try_from(0u32).is_err());
+    assert!(Pid::try_from(u32::MAX).is_err());
+    let current = Pid::try_from(std::process::id()).unwrap();
+    assert_eq!(current, Pid::current());
+    assert_eq!(u32::from(current), std::process::id());
+    assert_eq!(current.to_string(), std::process::id().to_string());
+    assert!(current.is_alive().unwrap());
+    assert!(Pid::parent().unwrap().is_alive().unwrap());
+    assert_eq!(
+        safe_fork::fork_join(|| (Pid::parent() == Some(current)) as i32).unwrap(),
+        1
+    );
+
+    #[cfg(feature = "nix")]
+    {
+        let pid = nix::unistd::Pid::from(current);
+        assert_eq!(pid, nix::unistd::getpid());
+        assert_eq!(Pid::try_from(pid).unwrap(), current);
+    }
+
+    // A child exists until it is reaped.
+    let child = safe_fork::fork_spawn(|| {}).unwrap();
+    let pid = Pid::from(&child);
+    assert_eq!(u32::from(pid), child.pid());
+    while child.is_alive().unwrap() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(pid.is_alive().unwrap());
+    assert!(child.join().unwrap().success());
+    assert!(!pid.is_alive().unwrap());
+}
@@ -0,0 +1,101 @@
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+use safe_fork::{ChildLimit, ForkPool};
+
+fn main() {
+    let pool = ForkPool::new(4, |x: u64| {
+        if x == 13 {
+            std::process::exit(1);
+        }
+        (x * x, std::process::id())
+    })
+    .unwrap();
+    assert_eq!(pool.workers(), 4);
+
+    let results = pool.map(0..12).unwrap();
+    let squares: Vec<u64> = results.iter().map(|&(sq, _)| sq).collect();
+    assert_eq!(squares, (0..12).map(|x| x * x).collect::<Vec<_>>());
+    let mut pids: Vec<u32> = results.iter().map(|&(_, pid)| pid).collect();
+    pids.sort();
+    pids.dedup();
+    assert_eq!(pids.len(), 4);
+
+    // A task killing its worker fails, and the worker is replaced.
+    let handles: Vec<_> = (10..16).map(|x| pool.submit(x).unwrap()).collect();
+    let results: Vec<_> = handles.into_iter().map(|h| h.join()).collect();
+    for (x, result) in (10..16).zip(results) {
+        if x == 13 {
+            assert!(result.is_err());
+        } else {
+            assert_eq!(result.unwrap().0, x * x);
+        }
+    }
+    assert_eq!(pool.call(20).unwrap().0, 400);
+
+    // Results of dropped handles are discarded.
+    drop(pool.submit(1).unwrap());
+    assert_eq!(pool.call(2).unwrap().0, 4);
+
+    // Stragglers time out without affecting other tasks, and their workers are replaced.
+    let pool = ForkPool::new(2, |ms: u64| {
+        std::thread::sleep(Duration::from_millis(ms));
+        std::process::id()
+    })
+    .unwrap();
+    let start = Instant::now();
+    let straggler = pool
+        .submit_timeout(10_000, Duration::from_millis(100))
+        .unwrap();
+    let queued = pool.submit_timeout(50, Duration::from_millis(100)).unwrap();
+    let handles: Vec<_> = (0..4)
+        .map(|_| pool.submit_timeout(50, Duration::from_secs(5)).unwrap())
+        .collect();
+    let err = straggler.join().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    queued.join().unwrap();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // Short tasks are not held up by a slow task submitted before them.
+    let start = Instant::now();
+    let slow = pool.submit(1000).unwrap();
+    let handles: Vec<_> = (0..6).map(|_| pool.submit(50).unwrap()).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(start.elapsed() < Duration::from_millis(900));
+    slow.join().unwrap();
+
+    // Cancelling a running task kills its worker, which is then respawned.
+    let start = Instant::now();
+    let running = pool.submit(10_000).unwrap();
+    pool.call(0).unwrap();
+    running.cancel().unwrap();
+    assert_eq!(pool.map([10, 10]).unwrap().len(), 2);
+
+    // Cancelling a queued task removes it from the queue.
+    let handles = [10, 10_000, 10].map(|ms| pool.submit(ms).unwrap());
+    let [first, second, third] = handles;
+    third.cancel().unwrap();
+    second.cancel().unwrap();
+    first.join().unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // Failing to respawn a worker fails the task waiting for it, and the pool recovers later.
+    let pool = ForkPool::new(1, |x: i32| if x < 0 { std::process::exit(1) } else { x }).unwrap();
+    let handle = pool.submit(-1).unwrap();
+    safe_fork::set_child_limit(ChildLimit::Fail(0));
+    assert_eq!(handle.join().unwrap_err().kind(), ErrorKind::WouldBlock);
+    safe_fork::set_child_limit(ChildLimit::Unlimited);
+    assert_eq!(pool.call(1).unwrap(), 1);
+
+    // Dropping the pool does not wait for running tasks.
+    let pool = ForkPool::new(1, |ms: u64| std::thread::sleep(Duration::from_millis(ms))).unwrap();
+    let start = Instant::now();
+    drop(pool.submit(10_000).unwrap());
+    drop(pool);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
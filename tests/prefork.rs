@@ -0,0 +1,82 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use safe_fork::{PreforkServer, Restart, RestartPolicy};
+
+fn serve_pid(mut stream: impl Read + Write) {
+    let mut request = [0];
+    stream.read_exact(&mut request).unwrap();
+    // A request of 0 makes the handler panic, so that the child is restarted.
+    if request[0] == 0 {
+        panic!("bad request");
+    }
+    stream.write_all(&std::process::id().to_ne_bytes()).unwrap();
+}
+
+fn request(mut stream: impl Read + Write, request: u8) -> Option<u32> {
+    stream.write_all(&[request]).unwrap();
+    let mut pid = [0; 4];
+    stream.read_exact(&mut pid).ok()?;
+    Some(u32::from_ne_bytes(pid))
+}
+
+fn main() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut supervisor = PreforkServer::new(listener, 3).spawn(serve_pid).unwrap();
+    let pids = supervisor.pids();
+    assert_eq!(pids.len(), 3);
+    for _ in 0..10 {
+        let pid = request(TcpStream::connect(addr).unwrap(), 1).unwrap();
+        assert!(pids.contains(&pid));
+    }
+    supervisor.shutdown().unwrap();
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::collections::HashSet;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut supervisor = PreforkServer::new(listener, 3)
+            .reuse_port(true)
+            .spawn(serve_pid)
+            .unwrap();
+        // Each child listens on its own socket once it has started, and the kernel balances new
+        // connections between the sockets.
+        let mut served = HashSet::new();
+        for _ in 0..1000 {
+            served.insert(request(TcpStream::connect(addr).unwrap(), 1).unwrap());
+            if served.len() > 1 {
+                break;
+            }
+        }
+        assert!(served.len() > 1);
+        assert!(served.is_subset(&supervisor.pids().into_iter().collect()));
+        supervisor.shutdown().unwrap();
+    }
+
+    // Children that exit are restarted by the supervisor.
+    let path = std::env::temp_dir().join(format!("safe-fork-prefork-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    let mut supervisor = PreforkServer::new(listener, 1)
+        .restart_policy(RestartPolicy::new(Restart::Always).max_restarts(1))
+        .spawn(serve_pid)
+        .unwrap();
+    let first = supervisor.pids()[0];
+    let client = safe_fork::fork_spawn(|| {
+        let connect = || UnixStream::connect(&path).unwrap();
+        let restarted = request(connect(), 0).is_none()
+            && request(connect(), 1).is_some_and(|pid| pid != first)
+            && request(connect(), 0).is_none();
+        restarted as i32
+    })
+    .unwrap();
+    let statuses = supervisor.run().unwrap();
+    assert_eq!(statuses.len(), 1);
+    assert!(!statuses[0].success());
+    assert_eq!(client.join().unwrap().code(), Some(1));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,85 @@
+//! Tracing is only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+use safe_fork::{ChildStatus, ForkBuilder, TraceEvent};
+
+#[cfg(target_os = "linux")]
+fn main() {
+    // Exec and signal-delivery events are reported, and signals can be suppressed.
+    let mut child = ForkBuilder::new()
+        .traced()
+        .exec("sh", ["-c", "kill -USR1 $$; kill -USR2 $$; exit 3"])
+        .unwrap();
+    assert_eq!(child.next_trace_event().unwrap(), TraceEvent::Exec);
+    assert_eq!(
+        child.next_trace_event().unwrap(),
+        TraceEvent::Signal(libc::SIGUSR1)
+    );
+    child.suppress_signal();
+    assert_eq!(
+        child.next_trace_event().unwrap(),
+        TraceEvent::Signal(libc::SIGUSR2)
+    );
+    let status = ChildStatus::Signaled {
+        signal: libc::SIGUSR2,
+        core_dumped: false,
+    };
+    assert_eq!(
+        child.next_trace_event().unwrap(),
+        TraceEvent::Exited(status)
+    );
+    assert_eq!(
+        child.next_trace_event().unwrap(),
+        TraceEvent::Exited(status)
+    );
+    assert_eq!(child.join().unwrap(), status);
+
+    // System calls are reported in pairs of entry and exit.
+    let mut child = ForkBuilder::new()
+        .trace_syscalls()
+        .spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            // SAFETY: `getppid` does not have special safety requirements.
+            unsafe { libc::getppid() };
+            0
+        })
+        .unwrap();
+    let mut entered = None;
+    let mut seen = false;
+    loop {
+        match child.next_trace_event().unwrap() {
+            TraceEvent::SyscallEnter { nr, .. } => entered = Some(nr),
+            TraceEvent::SyscallExit { ret, is_error } => {
+                if entered == Some(libc::SYS_getppid as u64) {
+                    assert_eq!(ret, std::process::id() as i64);
+                    assert!(!is_error);
+                    seen = true;
+                }
+                entered = None;
+            }
+            TraceEvent::Exited(status) => {
+                assert!(status.success());
+                break;
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
+    assert!(seen);
+
+    // Joining resumes the child at every event, and dropping kills it.
+    let child = ForkBuilder::new().trace_syscalls().spawn(|| 5).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(5));
+    let mut child = ForkBuilder::new().traced().exec("sleep", ["10"]).unwrap();
+    assert_eq!(child.next_trace_event().unwrap(), TraceEvent::Exec);
+    drop(child);
+
+    // Untraced children cannot report events.
+    let mut child = safe_fork::fork_spawn(|| 0).unwrap();
+    assert_eq!(
+        child.next_trace_event().unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
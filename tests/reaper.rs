@@ -0,0 +1,46 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use safe_fork::Reaper;
+
+fn main() {
+    let reaper = Reaper::install().unwrap();
+    assert_eq!(
+        Reaper::install().unwrap_err().kind(),
+        ErrorKind::AlreadyExists
+    );
+
+    let dropped = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        7
+    })
+    .unwrap();
+    let dropped_pid = dropped.pid();
+    drop(dropped);
+    let detached = safe_fork::fork_spawn(|| 3).unwrap();
+    let detached_pid = detached.pid();
+    detached.detach();
+
+    let mut exited = Vec::new();
+    while exited.len() < 2 {
+        let reaped = reaper.wait(Some(Duration::from_secs(5))).unwrap();
+        assert!(!reaped.is_empty());
+        exited.extend(reaped);
+    }
+    exited.sort_by_key(|&(pid, _)| pid != dropped_pid);
+    assert_eq!(exited[0].0, dropped_pid);
+    assert_eq!(exited[0].1.code(), Some(7));
+    assert_eq!(exited[1].0, detached_pid);
+    assert_eq!(exited[1].1.code(), Some(3));
+
+    // Children with live handles are not reaped.
+    let child = safe_fork::fork_spawn(|| 5).unwrap();
+    assert!(reaper
+        .wait(Some(Duration::from_millis(100)))
+        .unwrap()
+        .is_empty());
+    assert_eq!(child.join().unwrap().code(), Some(5));
+
+    drop(reaper);
+    Reaper::install().unwrap();
+}
@@ -0,0 +1,80 @@
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+use safe_fork::{active_children, reap_exited, set_child_limit, ChildLimit, ChildStatus};
+
+fn main() {
+    assert!(active_children().is_empty());
+
+    let running = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(200));
+        0
+    })
+    .unwrap();
+    let exiting = safe_fork::fork_spawn(|| 0).unwrap();
+    let pids: Vec<_> = active_children().iter().map(|info| info.pid()).collect();
+    assert_eq!(pids, [running.pid(), exiting.pid()]);
+    assert!(active_children().iter().all(|info| !info.is_orphaned()));
+
+    // Children are removed once reaped.
+    exiting.join().unwrap();
+    let pid = running.pid();
+    assert_eq!(active_children().len(), 1);
+
+    // Children dropped while running stay in the registry until they are reaped.
+    drop(running);
+    let children = active_children();
+    assert_eq!(children.len(), 1);
+    assert_eq!(children[0].pid(), pid);
+    assert!(children[0].is_orphaned());
+    assert!(reap_exited().is_empty());
+
+    std::thread::sleep(Duration::from_millis(400));
+    assert_eq!(reap_exited(), [(pid, ChildStatus::Exited(0))]);
+    assert!(active_children().is_empty());
+
+    // Forked children start with an empty registry.
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        0
+    })
+    .unwrap();
+    assert_eq!(
+        safe_fork::fork_join(|| active_children().is_empty() as i32).unwrap(),
+        1
+    );
+    assert_eq!(active_children().len(), 1);
+    child.join().unwrap();
+    assert!(active_children().is_empty());
+
+    // With a limit, creating children fails or blocks while too many are running.
+    set_child_limit(ChildLimit::Fail(1));
+    let sleeper = || {
+        std::thread::sleep(Duration::from_millis(200));
+        0
+    };
+    let child = safe_fork::fork_spawn(sleeper).unwrap();
+    let err = std::io::Error::from(safe_fork::fork_spawn(|| 0).unwrap_err());
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    child.join().unwrap();
+    // Exited children do not count even before they are joined.
+    let child = safe_fork::fork_spawn(|| 0).unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(safe_fork::fork_join(|| 0).unwrap(), 0);
+    child.join().unwrap();
+
+    set_child_limit(ChildLimit::Block(1));
+    let start = Instant::now();
+    let child = safe_fork::fork_spawn(sleeper).unwrap();
+    assert_eq!(safe_fork::fork_join(|| 0).unwrap(), 0);
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    child.join().unwrap();
+
+    // A limit of zero fails instead of blocking forever.
+    for limit in [ChildLimit::Fail(0), ChildLimit::Block(0)] {
+        set_child_limit(limit);
+        let err = std::io::Error::from(safe_fork::fork_spawn(|| 0).unwrap_err());
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+    }
+    set_child_limit(ChildLimit::Unlimited);
+}
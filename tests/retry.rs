@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use safe_fork::{ChildStatus, RetryPolicy};
+
+/// Records an attempt in the file, returning the number of attempts so far.
+fn attempt(path: &Path) -> usize {
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .unwrap();
+    file.write_all(b".").unwrap();
+    std::fs::read(path).unwrap().len()
+}
+
+fn main() {
+    let path = std::env::temp_dir().join(format!("safe-fork-retry-{}", std::process::id()));
+    let attempts = || std::fs::read(&path).unwrap().len();
+
+    // Failures are retried until the closure succeeds.
+    let _ = std::fs::remove_file(&path);
+    let policy = RetryPolicy::new(5).backoff(Duration::from_millis(50), Duration::from_secs(1));
+    let start = Instant::now();
+    let status = safe_fork::fork_retry(&policy, || if attempt(&path) < 3 { 3 } else { 0 }).unwrap();
+    assert!(status.success());
+    assert_eq!(attempts(), 3);
+    assert!(start.elapsed() >= Duration::from_millis(150));
+
+    // The number of attempts is limited.
+    let _ = std::fs::remove_file(&path);
+    let status = safe_fork::fork_retry(&RetryPolicy::new(2), || {
+        attempt(&path);
+        3
+    })
+    .unwrap();
+    assert_eq!(status, ChildStatus::Exited(3));
+    assert_eq!(attempts(), 2);
+
+    // Only the given outcomes are retried.
+    let policy = RetryPolicy::new(5)
+        .retry_on_exit_code(3)
+        .retry_on_signal(libc::SIGKILL);
+    let _ = std::fs::remove_file(&path);
+    let status = safe_fork::fork_retry(&policy, || {
+        attempt(&path);
+        4
+    })
+    .unwrap();
+    assert_eq!(status, ChildStatus::Exited(4));
+    assert_eq!(attempts(), 1);
+
+    let _ = std::fs::remove_file(&path);
+    let status = safe_fork::fork_retry(&policy, || {
+        if attempt(&path) == 1 {
+            // SAFETY: `raise` does not have special safety requirements.
+            unsafe { libc::raise(libc::SIGKILL) };
+        }
+        0
+    })
+    .unwrap();
+    assert!(status.success());
+    assert_eq!(attempts(), 2);
+    std::fs::remove_file(&path).unwrap();
+}
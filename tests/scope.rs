@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+fn main() {
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let code = safe_fork::fork_scope(|scope| {
+        let child = scope.spawn(|| 3).unwrap();
+        // Not joined explicitly, but the scope waits for it.
+        scope
+            .spawn(move || {
+                std::thread::sleep(Duration::from_millis(100));
+                drop(writer);
+            })
+            .unwrap();
+        child.join().unwrap().code()
+    });
+    assert_eq!(code, Some(3));
+    // The second child has been reaped, so the pipe has been closed by it.
+    std::io::Read::read_to_end(&mut reader, &mut Vec::new()).unwrap();
+    assert!(safe_fork::is_single_threaded());
+
+    // Children are killed if the scope panics.
+    std::panic::set_hook(Box::new(|_| {}));
+    let start = Instant::now();
+    let mut pid = 0;
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        safe_fork::fork_scope(|scope| {
+            let child = scope
+                .spawn(|| std::thread::sleep(Duration::from_secs(10)))
+                .unwrap();
+            pid = child.pid();
+            panic!("oops");
+        })
+    }));
+    assert!(result.is_err());
+    assert!(start.elapsed() < Duration::from_secs(5));
+    // The child has been reaped.
+    assert_eq!(unsafe { libc::kill(pid as _, 0) }, -1);
+}
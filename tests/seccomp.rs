@@ -0,0 +1,45 @@
+//! Seccomp filters are only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use safe_fork::{ForkBuilder, SeccompAction, SeccompFilter};
+
+    let child = ForkBuilder::new()
+        .seccomp(SeccompFilter::basic_compute())
+        .spawn(|| {
+            let sum: i32 = (1..=10).collect::<Vec<_>>().iter().sum();
+            let err = std::fs::File::open("/dev/null").unwrap_err();
+            (sum == 55 && err.raw_os_error() == Some(libc::EPERM)) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .seccomp(
+            SeccompFilter::new(SeccompAction::Allow)
+                .rule(libc::SYS_getppid, SeccompAction::Errno(libc::ENOSYS as u16)),
+        )
+        .spawn(|| {
+            // The C library does not check for errors from `getppid`, so make the system call
+            // directly.
+            // SAFETY: `getppid` does not have special safety requirements.
+            let ret = unsafe { libc::syscall(libc::SYS_getppid) };
+            let errno = std::io::Error::last_os_error().raw_os_error();
+            (ret == -1 && errno == Some(libc::ENOSYS)) as i32
+        })
+        .unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let child = ForkBuilder::new()
+        .seccomp(
+            SeccompFilter::new(SeccompAction::Allow)
+                .rule(libc::SYS_getppid, SeccompAction::KillProcess),
+        )
+        // SAFETY: `getppid` does not have special safety requirements.
+        .spawn(|| unsafe { libc::getppid() })
+        .unwrap();
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGSYS));
+}
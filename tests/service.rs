@@ -0,0 +1,74 @@
+use std::io::ErrorKind;
+use std::os::fd::AsRawFd;
+use std::time::Duration;
+
+use safe_fork::ForkBuilder;
+
+fn main() {
+    // The handler keeps its state across calls.
+    let mut total = 0;
+    let mut service = safe_fork::fork_service(|x: u64| {
+        total += x;
+        (total, std::process::id())
+    })
+    .unwrap();
+    assert_eq!(service.call(1).unwrap(), (1, service.pid()));
+    assert_eq!(service.call(2).unwrap(), (3, service.pid()));
+    assert_eq!(total, 0);
+    assert!(service.shutdown().unwrap().success());
+
+    let mut service = ForkBuilder::new()
+        .env("SAFE_FORK_SERVICE", "child")
+        .spawn_service(|name: String| std::env::var(name).ok())
+        .unwrap();
+    let value = service.call(String::from("SAFE_FORK_SERVICE")).unwrap();
+    assert_eq!(value.as_deref(), Some("child"));
+    drop(service);
+
+    // The channel survives closing other file descriptors, and mappings over its descriptors.
+    let mut service = ForkBuilder::new()
+        .close_fds()
+        .spawn_service(|x: u32| x + 1)
+        .unwrap();
+    assert_eq!(service.call(1).unwrap(), 2);
+    assert!(service.shutdown().unwrap().success());
+    let devnull = std::fs::File::open("/dev/null").unwrap();
+    let mut builder = ForkBuilder::new();
+    builder.close_fds();
+    for target in 3..16 {
+        builder.inherit_fd(devnull.as_raw_fd(), target);
+    }
+    let mut service = builder.spawn_service(|x: u32| x + 1).unwrap();
+    assert_eq!(service.call(1).unwrap(), 2);
+    assert!(service.shutdown().unwrap().success());
+
+    let mut service = safe_fork::fork_service(|ms: u64| {
+        std::thread::sleep(Duration::from_millis(ms));
+    })
+    .unwrap();
+    service.call_timeout(0, Duration::from_secs(10)).unwrap();
+    // The timeout only applies to the call it is given to.
+    service.call_timeout(0, Duration::from_millis(50)).unwrap();
+    service.call(100).unwrap();
+    let err = service
+        .call_timeout(10_000, Duration::from_millis(50))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+    let err = service.call(0).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotConnected);
+    assert_eq!(service.shutdown().unwrap().signal(), Some(libc::SIGKILL));
+
+    // Large responses arriving in many pieces complete within the timeout.
+    let mut service = safe_fork::fork_service(|len: usize| vec![1u8; len]).unwrap();
+    let resp = service
+        .call_timeout(64 << 20, Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(resp.len(), 64 << 20);
+    assert!(service.shutdown().unwrap().success());
+
+    // A panicking handler takes the child down, failing the call.
+    let mut service =
+        safe_fork::fork_service(|x: u32| -> u32 { panic!("bad request {x}") }).unwrap();
+    assert!(service.call(1).is_err());
+    assert_eq!(service.shutdown().unwrap().code(), Some(101));
+}
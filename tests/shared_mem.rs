@@ -0,0 +1,45 @@
+use std::sync::atomic::Ordering;
+
+use safe_fork::SharedMem;
+
+fn main() {
+    let counter = SharedMem::<u64>::new().unwrap();
+    let children: Vec<_> = (0..4)
+        .map(|_| {
+            safe_fork::fork_spawn(|| {
+                for _ in 0..1000 {
+                    counter.as_atomic_u64()[0].fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .unwrap()
+        })
+        .collect();
+    safe_fork::join_all(children).unwrap();
+    assert_eq!(counter.get(0), 4000);
+
+    let buffer = SharedMem::<u32>::with_len(1 << 20).unwrap();
+    assert_eq!(buffer.len(), 1 << 20);
+    let child = safe_fork::fork_spawn(|| {
+        // SAFETY: the parent does not access the buffer until the child exits.
+        let slice = unsafe { buffer.as_mut_slice() };
+        for (i, value) in slice.iter_mut().enumerate() {
+            *value = i as u32;
+        }
+    })
+    .unwrap();
+    child.join().unwrap();
+    // SAFETY: the child has exited, so nothing writes to the buffer.
+    let slice = unsafe { buffer.as_slice() };
+    assert!(slice
+        .iter()
+        .enumerate()
+        .all(|(i, &value)| value == i as u32));
+
+    buffer.set(3, 42);
+    let child = safe_fork::fork_spawn(|| (buffer.get(3) == 42) as i32).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(1));
+
+    let empty = SharedMem::<u8>::with_len(0).unwrap();
+    assert!(empty.is_empty());
+    assert!(empty.as_atomic_u32().is_empty());
+}
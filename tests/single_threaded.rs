@@ -2,7 +2,12 @@ fn main() {
     std::thread::scope(|scope| {
         scope
             .spawn(|| {
-                assert!(safe_fork::fork_join(|| 42).is_err());
+                assert!(matches!(
+                    safe_fork::fork_join(|| 42),
+                    Err(safe_fork::ForkError::MultiThreaded)
+                ));
+                let err = std::io::Error::from(safe_fork::fork().unwrap_err());
+                assert_eq!(err.to_string(), "cannot fork a multi-threaded process");
             })
             .join()
             .unwrap();
@@ -0,0 +1,55 @@
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    // Spawns a grandchild that exits with `code` after `delay`, returning its PID.
+    fn orphan(code: i32, delay: Duration) -> u32 {
+        let (mut reader, mut writer) = std::io::pipe().unwrap();
+        let child = safe_fork::fork_spawn(move || {
+            let grandchild = safe_fork::fork_spawn(move || {
+                std::thread::sleep(delay);
+                code
+            })
+            .unwrap();
+            writer.write_all(&grandchild.pid().to_ne_bytes()).unwrap();
+            grandchild.detach();
+            0
+        })
+        .unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(0));
+        let mut pid = [0; 4];
+        reader.read_exact(&mut pid).unwrap();
+        u32::from_ne_bytes(pid)
+    }
+
+    safe_fork::set_child_subreaper(true).unwrap();
+
+    let pid = orphan(9, Duration::ZERO);
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(
+        safe_fork::reap_children()
+            .unwrap()
+            .into_iter()
+            .map(|(pid, status)| (pid, status.code()))
+            .collect::<Vec<_>>(),
+        [(pid, Some(9))]
+    );
+    assert!(safe_fork::reap_children().unwrap().is_empty());
+
+    let pid = orphan(0, Duration::from_millis(50));
+    let main = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(200));
+        4
+    })
+    .unwrap();
+    assert_eq!(safe_fork::join_reaping(main).unwrap().code(), Some(4));
+    // The orphan has been reaped while waiting.
+    // SAFETY: signal 0 only checks whether the process exists.
+    assert_eq!(unsafe { libc::kill(pid as _, 0) }, -1);
+
+    safe_fork::set_child_subreaper(false).unwrap();
+}
@@ -0,0 +1,54 @@
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use safe_fork::{Restart, RestartPolicy, Supervisor};
+
+fn main() {
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let mut supervisor = Supervisor::new();
+    let mut ok = writer.try_clone().unwrap();
+    supervisor
+        .spawn(RestartPolicy::new(Restart::OnFailure), move || {
+            ok.write_all(b"o").unwrap();
+            0
+        })
+        .unwrap();
+    let mut failing = writer.try_clone().unwrap();
+    supervisor
+        .spawn(
+            RestartPolicy::new(Restart::Always)
+                .max_restarts(3)
+                .backoff(Duration::from_millis(50), Duration::from_millis(100)),
+            move || {
+                failing.write_all(b"f").unwrap();
+                1
+            },
+        )
+        .unwrap();
+    drop(writer);
+
+    let start = Instant::now();
+    let statuses = supervisor.run().unwrap();
+    // Restarts are delayed by 50ms, 100ms and 100ms.
+    assert!(start.elapsed() >= Duration::from_millis(250));
+    assert_eq!(statuses[0].code(), Some(0));
+    assert_eq!(statuses[1].code(), Some(1));
+    drop(supervisor);
+
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    buf.sort();
+    assert_eq!(buf, b"ffffo");
+
+    // Children that keep running are terminated on shutdown.
+    let mut supervisor = Supervisor::new();
+    supervisor
+        .spawn(RestartPolicy::new(Restart::Always), || {
+            std::thread::sleep(Duration::from_secs(10))
+        })
+        .unwrap();
+    assert_eq!(supervisor.pids().len(), 1);
+    supervisor.shutdown().unwrap();
+    assert!(supervisor.pids().is_empty());
+    assert_eq!(supervisor.run().unwrap()[0].signal(), Some(libc::SIGTERM));
+}
@@ -0,0 +1,66 @@
+//! Futex-based synchronization is only supported on Linux.
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use std::time::Duration;
+
+    use safe_fork::{Event, ProcessBarrier};
+
+    #[cfg(feature = "bytemuck")]
+    {
+        let counter = safe_fork::ProcessMutex::new(0u64).unwrap();
+        let children: Vec<_> = (0..4)
+            .map(|_| {
+                safe_fork::fork_spawn(|| {
+                    for _ in 0..1000 {
+                        let mut guard = counter.lock();
+                        // A non-atomic read-modify-write, which loses updates without the lock.
+                        let value = *guard;
+                        std::thread::yield_now();
+                        *guard = value + 1;
+                    }
+                })
+                .unwrap()
+            })
+            .collect();
+        safe_fork::join_all(children).unwrap();
+        assert_eq!(*counter.lock(), 4000);
+
+        let guard = counter.lock();
+        let child = safe_fork::fork_spawn(|| counter.try_lock().is_none() as i32).unwrap();
+        assert_eq!(child.join().unwrap().code(), Some(1));
+        drop(guard);
+        assert!(counter.try_lock().is_some());
+    }
+
+    let ready = Event::new().unwrap();
+    assert!(!ready.wait_timeout(Duration::from_millis(10)));
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(50));
+        ready.set();
+    })
+    .unwrap();
+    ready.wait();
+    assert!(ready.is_set());
+    child.join().unwrap();
+    ready.reset();
+    assert!(!ready.is_set());
+
+    // Exactly one process leads each round, so the numbers of rounds led add up.
+    let barrier = ProcessBarrier::new(3).unwrap();
+    let rounds = || (0..10).filter(|_| barrier.wait()).count() as i32;
+    let children: Vec<_> = (0..2)
+        .map(|_| safe_fork::fork_spawn(rounds).unwrap())
+        .collect();
+    let led = rounds();
+    let statuses = safe_fork::join_all(children).unwrap();
+    let total: i32 = led
+        + statuses
+            .iter()
+            .map(|status| status.code().unwrap())
+            .sum::<i32>();
+    assert_eq!(total, 10);
+}
@@ -0,0 +1,20 @@
+use std::process::ExitCode;
+
+fn main() {
+    assert_eq!(safe_fork::fork_join(|| 42).unwrap(), 42);
+    assert_eq!(safe_fork::fork_join(|| ()).unwrap(), 0);
+    assert_eq!(safe_fork::fork_join(|| ExitCode::from(9)).unwrap(), 9);
+    assert_eq!(safe_fork::fork_join(|| ExitCode::FAILURE).unwrap(), 1);
+    assert_eq!(
+        safe_fork::fork_join(|| -> Result<(), String> { Ok(()) }).unwrap(),
+        0
+    );
+    assert_eq!(
+        safe_fork::fork_join(|| -> Result<i32, String> { Ok(5) }).unwrap(),
+        5
+    );
+    assert_eq!(
+        safe_fork::fork_join(|| -> Result<(), String> { Err("failed".into()) }).unwrap(),
+        1
+    );
+}
@@ -0,0 +1,24 @@
+fn main() {
+    assert_eq!(safe_fork::thread_count().unwrap(), 1);
+    #[cfg(target_os = "linux")]
+    {
+        let threads = safe_fork::threads().unwrap();
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].tid, std::process::id());
+    }
+
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("safe-fork-test".into())
+            .spawn_scoped(scope, || {
+                assert_eq!(safe_fork::thread_count().unwrap(), 2);
+                #[cfg(target_os = "linux")]
+                {
+                    let threads = safe_fork::threads().unwrap();
+                    assert_eq!(threads.len(), 2);
+                    assert!(threads.iter().any(|thread| thread.name == "safe-fork-test"));
+                }
+            })
+            .unwrap();
+    });
+}
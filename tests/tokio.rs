@@ -0,0 +1,16 @@
+use std::time::Duration;
+
+fn main() {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        let child = safe_fork::fork_spawn(|| {
+            std::thread::sleep(Duration::from_millis(100));
+            5
+        })
+        .unwrap();
+        assert_eq!(child.join_async().await.unwrap().code(), Some(5));
+    });
+}
@@ -0,0 +1,68 @@
+use std::fmt::Write;
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Messages of the events emitted, with their fields.
+static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Recorder;
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        write!(self.0, " {}={:?}", field.name(), value).unwrap();
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        EVENTS.lock().unwrap().push(fields.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn main() {
+    tracing::subscriber::set_global_default(Recorder).unwrap();
+
+    let child = safe_fork::fork_spawn(|| 3).unwrap();
+    let pid = child.pid();
+    child.join().unwrap();
+    let err = safe_fork::ForkBuilder::new()
+        .chdir("/nonexistent")
+        .spawn(|| 0)
+        .unwrap_err();
+    assert!(matches!(err, safe_fork::ForkError::SetupFailed(_)));
+
+    let events = EVENTS.lock().unwrap();
+    let position = |needle: &str| {
+        events
+            .iter()
+            .position(|event| event.contains(needle))
+            .unwrap_or_else(|| panic!("no event matching {needle:?} in {events:?}"))
+    };
+    let forked = position(&format!("message=forked child pid={pid}"));
+    let reaped = position(&format!("message=reaped child pid={pid} status=Exited(3)"));
+    assert!(forked < reaped);
+    position("message=child failed to start");
+}
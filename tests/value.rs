@@ -0,0 +1,188 @@
+use std::io::ErrorKind;
+
+use safe_fork::{Codec, ForkError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Codec dropping the last byte of every value.
+struct Truncated;
+
+impl Codec for Truncated {
+    fn encode<T: Serialize + ?Sized>(value: &T) -> std::io::Result<Vec<u8>> {
+        let mut bytes = safe_fork::Bincode::encode(value)?;
+        bytes.pop();
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> std::io::Result<T> {
+        safe_fork::Bincode::decode(bytes)
+    }
+}
+
+fn main() {
+    assert_eq!(
+        safe_fork::fork_join_value(|| (42u32, String::from("hello"))).unwrap(),
+        (42, String::from("hello"))
+    );
+
+    let data: Vec<u64> = (0..100_000).collect();
+    assert_eq!(
+        safe_fork::fork_join_value(|| data.iter().sum::<u64>()).unwrap(),
+        data.iter().sum::<u64>()
+    );
+
+    assert!(safe_fork::fork_join_value(|| -> u32 { std::process::exit(3) }).is_err());
+
+    // Application errors are sent back, while process failures are reported separately.
+    assert_eq!(
+        safe_fork::fork_join_result(|| Ok::<_, String>(1u8)).unwrap(),
+        Ok(1)
+    );
+    assert_eq!(
+        safe_fork::fork_join_result(|| Err::<u8, _>(String::from("bad input"))).unwrap(),
+        Err(String::from("bad input"))
+    );
+    let err = safe_fork::fork_join_result(|| -> Result<u8, String> {
+        // SAFETY: `raise` does not have special safety requirements.
+        unsafe { libc::raise(libc::SIGKILL) };
+        Ok(0)
+    })
+    .unwrap_err();
+    assert!(err.to_string().contains("signal: 9"), "{err}");
+    assert!(
+        matches!(err, ForkError::ChildFailed(status) if status.signal() == Some(libc::SIGKILL)),
+        "{err:?}"
+    );
+    let err = safe_fork::fork_join_result(|| -> Result<u8, String> { std::process::exit(3) })
+        .unwrap_err();
+    assert!(
+        matches!(err, ForkError::ChildFailed(status) if status.code() == Some(3)),
+        "{err:?}"
+    );
+    // Panics are reported with their message.
+    let err = safe_fork::fork_join_result(|| -> Result<u8, String> { panic!("bad request") })
+        .unwrap_err();
+    assert!(
+        matches!(&err, ForkError::Panicked(panicked) if panicked.message == "bad request"),
+        "{err:?}"
+    );
+    // A value that cannot be deserialized is reported as invalid data.
+    let err = safe_fork::fork_join_result_codec::<Truncated, u64, String>(|| Ok(1)).unwrap_err();
+    assert!(
+        matches!(&err, ForkError::Io(err) if err.kind() == ErrorKind::InvalidData),
+        "{err:?}"
+    );
+
+    // Plain values wrap the typed error.
+    let err = safe_fork::fork_join_value(|| -> u32 { std::process::exit(3) }).unwrap_err();
+    assert!(matches!(
+        err.get_ref().and_then(|err| err.downcast_ref::<ForkError>()),
+        Some(ForkError::ChildFailed(status)) if status.code() == Some(3)
+    ));
+    let err = safe_fork::fork_join_value(|| -> u32 { panic!("bad request") }).unwrap_err();
+    assert_eq!(err.to_string(), "child process panicked: bad request");
+
+    let pids = safe_fork::fork_map(0..8u32, |x| (x * 2, std::process::id())).unwrap();
+    assert_eq!(
+        pids.iter().map(|&(x, _)| x).collect::<Vec<_>>(),
+        (0..8).map(|x| x * 2).collect::<Vec<_>>()
+    );
+    let mut pids: Vec<_> = pids.into_iter().map(|(_, pid)| pid).collect();
+    pids.sort();
+    pids.dedup();
+    assert_eq!(pids.len(), 8);
+
+    // Large results from many children must not deadlock.
+    let sizes = safe_fork::fork_map(0..4, |_| vec![0u8; 1 << 20]).unwrap();
+    assert!(sizes.iter().all(|v| v.len() == 1 << 20));
+
+    assert!(safe_fork::fork_map(0..4, |x| {
+        if x == 2 {
+            std::process::exit(1);
+        }
+        x
+    })
+    .is_err());
+
+    // Items can be processed in chunks by a bounded number of children.
+    let map = safe_fork::ForkMap::new().max_parallel(3).chunk_size(1000);
+    let squares = map
+        .run(0..10_001u64, |x| (x * x, std::process::id()))
+        .unwrap();
+    assert_eq!(
+        squares.iter().map(|&(sq, _)| sq).collect::<Vec<_>>(),
+        (0..10_001).map(|x| x * x).collect::<Vec<_>>()
+    );
+    let mut pids: Vec<_> = squares.into_iter().map(|(_, pid)| pid).collect();
+    pids.dedup();
+    assert_eq!(pids.len(), 11);
+    // With at most two children at a time, four items run in two rounds.
+    let start = std::time::Instant::now();
+    safe_fork::ForkMap::new()
+        .max_parallel(2)
+        .run(0..4, |_| {
+            std::thread::sleep(std::time::Duration::from_millis(300))
+        })
+        .unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(600));
+    assert!(map
+        .run(0..4, |x| {
+            if x == 2 {
+                std::process::exit(1);
+            }
+            x
+        })
+        .is_err());
+    let err = safe_fork::ForkMap::new()
+        .chunk_size(0)
+        .run(0..4, |x| x)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    // Results are yielded as soon as each child completes.
+    let start = std::time::Instant::now();
+    let mut results = safe_fork::fork_map_iter([500u64, 0, 1000], |ms| {
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+        if ms == 1000 {
+            std::process::exit(1);
+        }
+        ms
+    })
+    .unwrap();
+    assert_eq!(results.len(), 3);
+    let (index, result) = results.next().unwrap();
+    assert_eq!((index, result.unwrap()), (1, 0));
+    assert!(start.elapsed() < std::time::Duration::from_millis(500));
+    let (index, result) = results.next().unwrap();
+    assert_eq!((index, result.unwrap()), (0, 500));
+    let (index, result) = results.next().unwrap();
+    assert_eq!(index, 2);
+    assert!(result.is_err());
+    assert!(results.next().is_none());
+
+    let mut state = vec![1, 2, 3];
+    let result = safe_fork::snapshot(&mut state, |state| -> Result<_, String> {
+        state.push(4);
+        Ok(state.len())
+    })
+    .unwrap();
+    assert!(result.is_ok());
+    assert_eq!(result.outcome(), &Ok(4));
+    assert_eq!(result.commit(), Ok(4));
+    assert_eq!(state, [1, 2, 3, 4]);
+
+    let result = safe_fork::snapshot(&mut state, |state| -> Result<(), String> {
+        state.clear();
+        Err(String::from("rejected"))
+    })
+    .unwrap();
+    assert_eq!(result.discard(), Err(String::from("rejected")));
+    assert_eq!(state, [1, 2, 3, 4]);
+
+    // A panic in the dry run does not affect the parent.
+    assert!(safe_fork::snapshot(&mut state, |_| -> Result<(), ()> {
+        panic!("crashed");
+    })
+    .is_err());
+    assert_eq!(state, [1, 2, 3, 4]);
+}
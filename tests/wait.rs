@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use safe_fork::ChildStatus;
+
+fn main() {
+    let mut children: Vec<_> = [300, 100, 200]
+        .into_iter()
+        .map(|ms| {
+            safe_fork::fork_spawn(move || {
+                std::thread::sleep(Duration::from_millis(ms));
+                (ms / 100) as i32
+            })
+            .unwrap()
+        })
+        .collect();
+    let pids: Vec<_> = children.iter().map(|child| child.pid()).collect();
+
+    let (pid, status) = safe_fork::wait_any(&mut children).unwrap();
+    assert_eq!(pid, pids[1]);
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(children.len(), 2);
+
+    let (pid, status) = safe_fork::wait_any(&mut children).unwrap();
+    assert_eq!(pid, pids[2]);
+    assert_eq!(status.code(), Some(2));
+
+    let (pid, status) = safe_fork::wait_any(&mut children).unwrap();
+    assert_eq!(pid, pids[0]);
+    assert_eq!(status.code(), Some(3));
+    assert!(safe_fork::wait_any(&mut children).is_err());
+
+    // A child that is not passed in is left alone.
+    let other = safe_fork::fork_spawn(|| 9).unwrap();
+    let mut children = vec![safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        0
+    })
+    .unwrap()];
+    safe_fork::wait_any(&mut children).unwrap();
+    assert_eq!(other.join().unwrap().code(), Some(9));
+
+    let children: Vec<_> = (0..4)
+        .map(|x| safe_fork::fork_spawn(move || x).unwrap())
+        .collect();
+    let codes: Vec<_> = safe_fork::join_all(children)
+        .unwrap()
+        .into_iter()
+        .map(|status| status.code().unwrap())
+        .collect();
+    assert_eq!(codes, [0, 1, 2, 3]);
+
+    // Waiting is retried if a signal handler runs, unless it is meant to be interrupted.
+    extern "C" fn handler(_: libc::c_int) {}
+    // SAFETY: all-zero is a valid `sigaction`, and the handler is async-signal-safe.
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler as *const () as usize;
+        libc::sigaction(libc::SIGUSR1, &action, std::ptr::null_mut());
+    }
+    let signal_parent = || {
+        std::thread::sleep(Duration::from_millis(100));
+        // SAFETY: `kill` and `getppid` do not have special safety requirements.
+        unsafe { libc::kill(libc::getppid(), libc::SIGUSR1) };
+        std::thread::sleep(Duration::from_millis(100));
+        5
+    };
+    let child = safe_fork::fork_spawn(signal_parent).unwrap();
+    assert_eq!(child.join().unwrap().code(), Some(5));
+    let mut child = safe_fork::fork_spawn(signal_parent).unwrap();
+    let err = child.join_interruptible().unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+    assert_eq!(child.join_interruptible().unwrap().code(), Some(5));
+
+    // Peeking at the status does not reap the child.
+    let child = safe_fork::fork_spawn(|| {
+        std::thread::sleep(Duration::from_millis(100));
+        7
+    })
+    .unwrap();
+    assert_eq!(child.peek_status().unwrap(), None);
+    std::thread::sleep(Duration::from_millis(200));
+    let status = ChildStatus::Exited(7);
+    assert_eq!(child.peek_status().unwrap(), Some(status));
+    assert_eq!(child.peek_status().unwrap(), Some(status));
+    let pid = child.pid();
+    child.detach();
+    let mut raw = 0;
+    // SAFETY: `raw` is valid for the duration of the call.
+    assert_eq!(
+        unsafe { libc::waitpid(pid as _, &mut raw, 0) },
+        pid as libc::pid_t
+    );
+    assert_eq!(ChildStatus::from_raw(raw), status);
+
+    let child = safe_fork::fork_spawn(|| -> i32 { std::process::abort() }).unwrap();
+    let status = loop {
+        if let Some(status) = child.peek_status().unwrap() {
+            break status;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    assert!(matches!(
+        status,
+        ChildStatus::Signaled {
+            signal: libc::SIGABRT,
+            ..
+        }
+    ));
+    assert_eq!(child.join().unwrap().signal(), Some(libc::SIGABRT));
+
+    assert_eq!(
+        safe_fork::fork_join_deadline(|| 3, Duration::from_secs(10)).unwrap(),
+        3
+    );
+    // Descendants of the child are killed along with it once the deadline passes.
+    let (mut reader, writer) = std::io::pipe().unwrap();
+    let err = safe_fork::fork_join_deadline(
+        move || {
+            let grandchild = safe_fork::fork_spawn(move || {
+                let _writer = &writer;
+                std::thread::sleep(Duration::from_secs(10));
+            })
+            .unwrap();
+            grandchild.detach();
+            std::thread::sleep(Duration::from_secs(10));
+        },
+        Duration::from_millis(100),
+    )
+    .unwrap_err();
+    assert!(matches!(err, safe_fork::ForkError::TimedOut));
+    let mut buf = Vec::new();
+    std::io::Read::read_to_end(&mut reader, &mut buf).unwrap();
+}